@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE task_outputs DROP COLUMN error;
+ALTER TABLE task_outputs DROP COLUMN status;
+ALTER TABLE task_outputs DROP COLUMN sink;
@@ -0,0 +1,4 @@
+-- Your SQL goes here
+ALTER TABLE task_outputs ADD COLUMN sink TEXT NULL;
+ALTER TABLE task_outputs ADD COLUMN status TEXT NULL;
+ALTER TABLE task_outputs ADD COLUMN error TEXT NULL;
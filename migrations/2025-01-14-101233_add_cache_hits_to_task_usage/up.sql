@@ -0,0 +1,2 @@
+-- Your SQL goes here
+ALTER TABLE task_usage ADD COLUMN cache_hits BIGINT NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+-- Your SQL goes here
+ALTER TABLE task_outputs ADD COLUMN model TEXT;
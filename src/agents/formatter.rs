@@ -1,73 +1,78 @@
 use super::{AgentBehavior, AgentOutcome};
-use crate::config::AgentConfig;
+use crate::config::{AgentConfig, OutputSink, ParametersConfig};
 use crate::constants::FORMATTER_USER_PROMPT;
-use crate::core::Task;
+use crate::core::{deliver, SinkResult, SinkStatus, Task};
 use crate::event::Event;
-use crate::llm::{ChatMessage, LlmClient};
 use crate::llm::{build_validator, validate_response};
+use crate::llm::{ChatMessage, LlmClient};
 use async_trait::async_trait;
-use std::fs;
+use serde_json::Value;
 use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
 use tracing::{debug, info};
 
-/// Agent responsible for formatting the final solution into the desired output format
+/// Agent responsible for formatting the final solution into the desired output formats
 pub struct FormatterAgent {
     /// LLM client for interacting with the language model
     pub llm_client: LlmClient,
     /// Custom prompt to guide the formatting behavior
     pub user_prompt: String,
-    /// Target format for the output (e.g. "markdown", "html")
-    pub output_format: String,
-    /// File path where the formatted output will be written
-    pub output_file: String,
+    /// Output sinks the solution is formatted and delivered to, with their schemas loaded
+    pub sinks: Vec<OutputSink>,
     /// Channel receiver for incoming events
     pub self_rx: UnboundedReceiver<Event>,
-    /// Schema for the output
+    /// Schema for the output, used by sinks without their own schema
     pub schema: Option<String>,
 }
 
+/// Loads a schema given inline or as a `file://` path
+///
+/// # Arguments
+/// * `schema` - The inline schema or `file://` path
+///
+/// # Returns
+/// * `Option<String>` - The schema content, or None if the file cannot be read
+fn load_schema(schema: String) -> Option<String> {
+    match schema.strip_prefix("file://") {
+        Some(path) => std::fs::read_to_string(path).ok(),
+        None => Some(schema),
+    }
+}
+
 impl FormatterAgent {
     /// Creates a new FormatterAgent instance
     ///
     /// # Arguments
     /// * `config` - Configuration for the agent
-    /// * `llm_provider` - The LLM provider to use (e.g. "openai")
-    /// * `llm_model` - The specific model to use (e.g. "gpt-4")
-    /// * `output_format` - The desired output format (e.g. "markdown", "html")
-    /// * `output_file` - Path where the formatted output will be written
+    /// * `parameters` - Task-level parameters, providing the LLM settings the role does not override
+    /// * `sinks` - The output sinks to format and deliver
     ///
     /// # Returns
     /// * `(Self, UnboundedSender<Event>)` - The agent instance and a channel sender for events
     pub fn new(
         config: AgentConfig,
-        llm_provider: &str,
-        llm_model: &str,
-        output_format: String,
-        output_file: String,
+        parameters: &ParametersConfig,
+        sinks: Vec<OutputSink>,
     ) -> (Self, UnboundedSender<Event>) {
         let (self_tx, self_rx) = tokio::sync::mpsc::unbounded_channel();
-        let content_schema = match config.schema.clone() {
-            Some(schema) => {
-                if let Some(stripped) = schema.strip_prefix("file://") {
-                    std::fs::read_to_string(stripped).ok()
-                } else {
-                    Some(schema)
-                }
-            }
-            None => None
-        };
+        let content_schema = config.schema.clone().and_then(load_schema);
+        let sinks = sinks
+            .into_iter()
+            .map(|mut sink| {
+                sink.schema = sink.schema.and_then(load_schema);
+                sink
+            })
+            .collect();
 
-        (   
+        (
             Self {
-                llm_client: LlmClient::new(llm_provider, llm_model)
+                llm_client: LlmClient::from_settings(&parameters.llm_settings_for(&config))
                     .expect("Failed to create LLM client"),
                 user_prompt: config
                     .user_prompt
                     .as_deref()
                     .unwrap_or(FORMATTER_USER_PROMPT)
                     .to_string(),
-                output_format,
-                output_file,
+                sinks,
                 self_rx,
                 schema: content_schema,
             },
@@ -75,6 +80,109 @@ impl FormatterAgent {
         )
     }
 
+    /// Builds the formatting prompt of a sink
+    ///
+    /// # Arguments
+    /// * `sink` - The sink being formatted
+    /// * `proposal` - The validated solution
+    /// * `schema` - The schema the output must match, if any
+    fn sink_prompt(&self, sink: &OutputSink, proposal: &str, schema: Option<&str>) -> String {
+        let mut prompt = String::new();
+        prompt.push_str("Current role: formatter\n");
+        prompt.push_str("Specific instructions: ");
+        prompt.push_str(&self.user_prompt);
+        if let Some(sink_prompt) = &sink.prompt {
+            prompt.push('\n');
+            prompt.push_str(sink_prompt);
+        }
+        prompt.push_str("\n\nValidated solution:\n");
+        prompt.push_str(proposal);
+        prompt.push_str("\n\nConvert this solution to ");
+        prompt.push_str(&sink.format);
+        prompt.push_str(" and only output the final formatted result, without comments.");
+
+        if let Some(schema) = schema {
+            prompt.push_str("\n\nSchema:\n");
+            prompt.push_str(schema);
+        }
+        prompt
+    }
+
+    /// Formats the solution for one sink and delivers it
+    ///
+    /// # Arguments
+    /// * `sink` - The sink to format and deliver
+    /// * `proposal` - The validated solution
+    /// * `task` - The task, whose conversation records the formatting exchange
+    ///
+    /// # Returns
+    /// * `SinkResult` - Whether the sink succeeded, with its content or failure reason
+    async fn run_sink(&self, sink: &OutputSink, proposal: &str, task: &mut Task) -> SinkResult {
+        let schema = sink.schema.as_deref().or(self.schema.as_deref());
+        let prompt = self.sink_prompt(sink, proposal, schema);
+        let mut messages = task.conversation.clone();
+        messages.push(ChatMessage::new("user", &prompt));
+        task.conversation.push(ChatMessage::new("user", &prompt));
+
+        // The schema is enforced by the provider when it supports structured output
+        let schema_value = schema.and_then(|schema| serde_json::from_str::<Value>(schema).ok());
+        let response = match &schema_value {
+            Some(schema_value) => self
+                .llm_client
+                .call_llm_with_schema(&mut messages, schema_value, validate_final_output, "", 2)
+                .await
+                .map_err(|e| e.to_string()),
+            None => self
+                .llm_client
+                .call_llm_with_format_check(&mut messages, validate_final_output, "", 2)
+                .await
+                .map_err(|e| e.to_string()),
+        };
+        let response = match response {
+            Ok(response) => response.content,
+            Err(e) => {
+                return SinkResult::failed(sink, None, format!("LLM error in Formatter: {}", e))
+            }
+        };
+        let model = self.llm_client.last_model();
+        debug!(
+            "FormatterAgent: raw formatted output received for '{}'.",
+            sink.label()
+        );
+        task.conversation
+            .push(ChatMessage::new("assistant", &response));
+
+        if !validate_final_output(&response) {
+            return SinkResult::failed(
+                sink,
+                None,
+                "Formatted output is invalid or empty.".to_string(),
+            )
+            .with_model(model);
+        }
+        let response = match schema {
+            Some(schema) => {
+                let response = strip_code_fence(&response).to_string();
+                if !validate_schema(schema, &response) {
+                    return SinkResult::failed(
+                        sink,
+                        Some(response),
+                        "Output does not match schema".to_string(),
+                    )
+                    .with_model(model);
+                }
+                response
+            }
+            None => response,
+        };
+
+        match deliver(sink, &response).await {
+            Ok(()) => SinkResult::succeeded(sink, response),
+            Err(e) => SinkResult::failed(sink, Some(response), e),
+        }
+        .with_model(model)
+    }
+
     /// Main event loop that processes incoming events
     ///
     /// # Arguments
@@ -104,6 +212,28 @@ fn validate_final_output(resp: &str) -> bool {
     !resp.trim().is_empty()
 }
 
+/// Removes the Markdown code fence wrapping a response, if any
+///
+/// # Arguments
+/// * `resp` - The response, possibly wrapped in ```json ... ```
+///
+/// # Returns
+/// * `&str` - The content inside the fence, or the trimmed response
+fn strip_code_fence(resp: &str) -> &str {
+    let trimmed = resp.trim();
+    let Some(fenced) = trimmed
+        .strip_prefix("```")
+        .and_then(|rest| rest.strip_suffix("```"))
+    else {
+        return trimmed;
+    };
+    // Drop the language tag on the opening line
+    match fenced.split_once('\n') {
+        Some((_, content)) => content.trim(),
+        None => fenced.trim(),
+    }
+}
+
 /// Validates a response string against a JSON schema
 ///
 /// # Arguments
@@ -131,72 +261,56 @@ impl AgentBehavior for FormatterAgent {
     async fn execute_step(&self, mut task: Task) -> (AgentOutcome, Task) {
         debug!("FormatterAgent: formatting final proposal...");
         let proposal = if let Some(sol) = &task.current_proposal {
-            sol
+            sol.clone()
         } else {
             return (
                 AgentOutcome::Failed("No final solution found in task for formatting".to_string()),
                 task,
             );
         };
-
-        let mut prompt = String::new();
-        prompt.push_str("Current role: formatter\n");
-        prompt.push_str("Specific instructions: ");
-        prompt.push_str(&self.user_prompt);
-        prompt.push_str("\n\nValidated solution:\n");
-        prompt.push_str(proposal);
-        prompt.push_str("\n\nConvert this solution to ");
-        prompt.push_str(&self.output_format);
-        prompt.push_str(" and only output the final formatted result, without comments.");
-
-        if let Some(schema) = &self.schema {
-            prompt.push_str("\n\nSchema:\n");
-            prompt.push_str(schema);
+        if self.sinks.is_empty() {
+            return (
+                AgentOutcome::Failed("No output sink configured".to_string()),
+                task,
+            );
         }
 
-        task.conversation.push(ChatMessage::new("user", &prompt));
-
-        match self
-            .llm_client
-            .call_llm_with_format_check(&mut task.conversation, validate_final_output, "", 2)
-            .await
-        {
-            Ok(response) => {
-                debug!("FormatterAgent: raw formatted output received.");
-                if !validate_final_output(&response) {
-                    return (
-                        AgentOutcome::Failed("Formatted output is invalid or empty.".to_string()),
-                        task,
-                    );
-                }
-
-                if let Some(schema) = &self.schema {
-                    if !validate_schema(schema, &response) {
-                        return (
-                            AgentOutcome::Failed("Output does not match schema".to_string()),
-                            task,
-                        );
-                    }
-                }
-
-                if let Err(e) = fs::write(&self.output_file, response.as_bytes()) {
-                    return (
-                        AgentOutcome::Failed(format!("Failed to write output file: {}", e)),
-                        task,
-                    );
-                }
-
-                info!("FormatterAgent: result written to file {}", self.output_file);
+        let mut results = vec![];
+        for sink in &self.sinks {
+            let result = self.run_sink(sink, &proposal, &mut task).await;
+            info!("FormatterAgent: output '{}' {}", result.sink, result.status);
+            results.push(result);
+        }
 
-                let cleaned_response = response.replace("```json", "").replace("```", "").trim().to_string();
-                task.final_output = Some(serde_json::from_str(&cleaned_response).unwrap_or_default());
-                
-                (AgentOutcome::Exported, task)
+        let first_output = results
+            .iter()
+            .find(|result| result.status == SinkStatus::Succeeded)
+            .and_then(|result| result.content.as_deref());
+        let outcome = match first_output {
+            Some(response) => {
+                // Outputs that are not JSON, such as Markdown, are kept as a string
+                task.final_output = Some(
+                    serde_json::from_str(strip_code_fence(response))
+                        .unwrap_or_else(|_| Value::String(response.to_string())),
+                );
+                AgentOutcome::Exported
             }
-            Err(e) => (
-                AgentOutcome::Failed(format!("LLM error in Formatter: {}", e)),
-                task,
+            None => AgentOutcome::Failed(
+                results
+                    .iter()
+                    .map(|result| {
+                        format!(
+                            "{}: {}",
+                            result.sink,
+                            result.error.as_deref().unwrap_or_default()
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("; "),
             ),
-        }
+        };
+        task.sink_results = results;
+
+        (outcome, task)
     }
 }
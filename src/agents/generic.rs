@@ -0,0 +1,243 @@
+use super::{AgentBehavior, AgentOutcome};
+use crate::config::{AgentConfig, ParametersConfig, ResponseCapture, ResponseRule};
+use crate::constants::CUSTOM_ROLE_USER_PROMPT;
+use crate::core::Task;
+use crate::event::Event;
+use crate::llm::{build_validator, validate_response};
+use crate::llm::{ChatMessage, LlmClient};
+use async_trait::async_trait;
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+use tracing::debug;
+
+/// Agent running a user-defined role.
+///
+/// The role's behavior is entirely described by its configuration:
+/// - its prompts guide the LLM
+/// - its response protocol maps response prefixes to workflow conditions
+/// - its optional schema constrains the captured content
+pub struct GenericAgent {
+    /// Name of the role this agent plays
+    pub role: String,
+    /// Client for interacting with the language model
+    pub llm_client: LlmClient,
+    /// Custom prompt to guide the agent's behavior
+    pub user_prompt: String,
+    /// Response prefixes the agent understands, tried in order
+    pub protocol: Vec<ResponseRule>,
+    /// Schema the captured content must match
+    pub schema: Option<String>,
+    /// Receiver for events from the task manager
+    pub self_rx: UnboundedReceiver<Event>,
+}
+
+impl GenericAgent {
+    /// Creates a new GenericAgent instance
+    ///
+    /// # Arguments
+    /// * `role` - Name of the user-defined role
+    /// * `config` - Configuration for the agent
+    /// * `parameters` - Task-level parameters, providing the LLM settings the role does not override
+    ///
+    /// # Returns
+    /// * `(Self, UnboundedSender<Event>)` - The agent instance and a channel sender for events
+    pub fn new(
+        role: String,
+        config: AgentConfig,
+        parameters: &ParametersConfig,
+    ) -> (Self, UnboundedSender<Event>) {
+        let (self_tx, self_rx) = tokio::sync::mpsc::unbounded_channel();
+        let llm_client = LlmClient::from_settings(&parameters.llm_settings_for(&config))
+            .expect("Failed to create LLM client");
+        let schema = config
+            .schema
+            .map(|schema| match schema.strip_prefix("file://") {
+                Some(path) => std::fs::read_to_string(path).unwrap_or(schema),
+                None => schema,
+            });
+
+        (
+            Self {
+                role,
+                llm_client,
+                user_prompt: config
+                    .user_prompt
+                    .as_deref()
+                    .unwrap_or(CUSTOM_ROLE_USER_PROMPT)
+                    .to_string(),
+                protocol: config.protocol,
+                schema,
+                self_rx,
+            },
+            self_tx,
+        )
+    }
+
+    pub async fn run_loop(mut self, worker_tx: UnboundedSender<Event>) {
+        loop {
+            while let Some(event) = self.self_rx.recv().await {
+                if let Event::NewRequest(role, task) = event {
+                    if role == self.role {
+                        let (outcome, task) = self.execute_step(task).await;
+                        let _ = worker_tx.send(Event::AgentResponse(role, outcome, task));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Finds the first protocol rule whose prefix starts the response
+    ///
+    /// # Arguments
+    /// * `resp` - The response string from the LLM
+    ///
+    /// # Returns
+    /// * `Option<(&ResponseRule, &str)>` - The matching rule and the text following its prefix
+    fn match_rule<'a>(&self, resp: &'a str) -> Option<(&ResponseRule, &'a str)> {
+        let resp = resp.trim();
+        self.protocol.iter().find_map(|rule| {
+            resp.get(..rule.prefix.len())
+                .filter(|start| start.eq_ignore_ascii_case(&rule.prefix))
+                .map(|_| (rule, resp[rule.prefix.len()..].trim()))
+        })
+    }
+
+    /// Describes the expected response prefixes for prompts and format reminders
+    fn expected_prefixes(&self) -> String {
+        self.protocol
+            .iter()
+            .map(|rule| format!("'{}'", rule.prefix))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+#[async_trait]
+impl AgentBehavior for GenericAgent {
+    /// Executes a single step of the user-defined role.
+    ///
+    /// This method:
+    /// 1. Builds a prompt from the context, the current proposal and previous feedback
+    /// 2. Calls the LLM, requiring one of the protocol prefixes or a module request
+    /// 3. Stores the captured content and returns the matching condition
+    ///
+    /// # Arguments
+    /// * `task` - The task being processed
+    ///
+    /// # Returns
+    /// * `AgentOutcome` - The condition of the matching rule, a module request, or Failed
+    async fn execute_step(&self, mut task: Task) -> (AgentOutcome, Task) {
+        debug!("GenericAgent: running role '{}'...", self.role);
+
+        if self.protocol.is_empty() {
+            return (
+                AgentOutcome::Failed(format!("Role '{}' has no response protocol", self.role)),
+                task,
+            );
+        }
+
+        let expected = self.expected_prefixes();
+        let mut prompt = String::new();
+        prompt.push_str(&format!("Current role: {}\n", self.role));
+        prompt.push_str("Specific instructions: ");
+        prompt.push_str(&self.user_prompt);
+        prompt.push_str("\n\nContext:\n");
+        prompt.push_str(&task.context.combined_context());
+
+        if let Some(proposal) = &task.current_proposal {
+            prompt.push_str("\n\nCurrent proposal:\n");
+            prompt.push_str(proposal);
+        }
+
+        let feedbacks = task.feedback_for_prompt();
+        if !feedbacks.trim().is_empty() {
+            prompt.push_str("\n\nPrevious feedback:\n");
+            prompt.push_str(&feedbacks);
+        }
+
+        if let Some(schema) = &self.schema {
+            prompt.push_str("\n\nThe content following your prefix must match this schema:\n");
+            prompt.push_str(schema);
+        }
+
+        prompt.push_str(&format!(
+            "\n\nPlease respond starting with one of {} or with a 'MODULE_REQUEST:'.",
+            expected
+        ));
+
+        let attachments = task.unsent_attachment_parts();
+        task.conversation
+            .push(ChatMessage::new("user", &prompt).with_parts(attachments));
+
+        let reminder = format!(
+            "The response must start with one of {} followed by your content, or be a 'MODULE_REQUEST:'.",
+            expected
+        );
+
+        match self
+            .llm_client
+            .call_llm_with_format_check(
+                &mut task.conversation,
+                |resp| self.match_rule(resp).is_some() || resp.contains("MODULE_REQUEST:"),
+                &reminder,
+                2,
+            )
+            .await
+        {
+            Ok(response) => {
+                task.conversation.push(response.to_message());
+                if let Some(mr) = response
+                    .tool_calls
+                    .first()
+                    .and_then(|call| self.parse_tool_call(call))
+                {
+                    return (mr, task);
+                }
+                let resp = response.content.trim();
+                if let Some(mr) = self.parse_module_request(resp) {
+                    return (mr, task);
+                }
+
+                let Some((rule, content)) = self.match_rule(resp) else {
+                    return (
+                        AgentOutcome::Failed(format!(
+                            "Unexpected LLM response in role '{}'",
+                            self.role
+                        )),
+                        task,
+                    );
+                };
+
+                if let Some(schema) = &self.schema {
+                    let valid = build_validator(schema)
+                        .ok()
+                        .and_then(|validator| validate_response(&validator, content).ok())
+                        .unwrap_or(false);
+                    if !valid {
+                        return (
+                            AgentOutcome::Failed(format!(
+                                "Response of role '{}' does not match schema",
+                                self.role
+                            )),
+                            task,
+                        );
+                    }
+                }
+
+                match rule.capture {
+                    Some(ResponseCapture::Proposal) => task.add_proposal(content.to_string()),
+                    Some(ResponseCapture::Feedback) => task.set_feedback(Some(content.to_string())),
+                    None => {}
+                }
+
+                (AgentOutcome::Custom(rule.condition.clone()), task)
+            }
+            Err(e) => (
+                AgentOutcome::llm_error(
+                    format!("LLM error in role '{}': {}", self.role, e),
+                    e.as_ref(),
+                ),
+                task,
+            ),
+        }
+    }
+}
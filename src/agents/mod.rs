@@ -1,15 +1,19 @@
 /// Module containing agent-related functionality
 mod agent_manager;
 mod formatter;
+mod generic;
 mod proposer;
 mod reviewer;
 mod validator;
 
 pub use formatter::*;
+pub use generic::*;
 pub use proposer::*;
 pub use reviewer::*;
 pub use validator::*;
 
+use crate::llm::{is_fatal_error, ToolCall};
+use std::error::Error;
 use tracing::debug;
 
 /// Represents the possible outcomes of an agent's execution
@@ -25,10 +29,14 @@ pub enum AgentOutcome {
     Validated,
     /// The result was exported
     Exported,
+    /// A user-defined role produced the given workflow condition
+    Custom(String),
     /// A module request was made with module name, action and parameters
     ModuleRequest(String, String, Vec<String>),
     /// The execution failed with an error message
     Failed(String), // error message
+    /// The execution failed with an error that retrying cannot fix (e.g. invalid API key)
+    Fatal(String),
 }
 
 /// Defines the behavior that all agents must implement
@@ -76,9 +84,61 @@ pub trait AgentBehavior {
         }
         None
     }
+
+    /// Converts a native tool call into a module request
+    ///
+    /// Tools are named `<module>_<action>`; module names contain no underscore, so the
+    /// name is split on the first one.
+    ///
+    /// # Arguments
+    /// * `call` - The tool call returned by the LLM
+    ///
+    /// # Returns
+    /// Some(AgentOutcome::ModuleRequest) if the tool name is valid, None otherwise
+    fn parse_tool_call(&self, call: &ToolCall) -> Option<AgentOutcome> {
+        let (module_name, action) = call.name.split_once('_')?;
+        let params = call.arguments["params"]
+            .as_array()
+            .map(|params| {
+                params
+                    .iter()
+                    .map(|param| match param.as_str() {
+                        Some(param) => param.to_string(),
+                        None => param.to_string(),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        debug!(
+            "executing tool call: {} {} {:?}",
+            module_name, action, params
+        );
+        Some(AgentOutcome::ModuleRequest(
+            module_name.to_string(),
+            action.to_string(),
+            params,
+        ))
+    }
 }
 
 impl AgentOutcome {
+    /// Builds the outcome of a failed LLM call
+    ///
+    /// # Arguments
+    /// * `message` - Description of the failure, including the error
+    /// * `error` - The error returned by the LLM client
+    ///
+    /// # Returns
+    /// `Fatal` if retrying cannot fix the error, `Failed` otherwise
+    pub fn llm_error(message: String, error: &(dyn Error + 'static)) -> Self {
+        if is_fatal_error(error) {
+            AgentOutcome::Fatal(message)
+        } else {
+            AgentOutcome::Failed(message)
+        }
+    }
+
     /// Converts the outcome to its string condition representation
     ///
     /// # Returns
@@ -90,8 +150,29 @@ impl AgentOutcome {
             AgentOutcome::Approved => "approved",
             AgentOutcome::Validated => "validated",
             AgentOutcome::Exported => "exported",
-            AgentOutcome::Failed(_) => "failed",
+            AgentOutcome::Custom(condition) => condition,
+            AgentOutcome::Failed(_) | AgentOutcome::Fatal(_) => "failed",
             AgentOutcome::ModuleRequest(_, _, _) => "module_request",
         }
     }
+
+    /// Returns the workflow conditions a built-in role can produce
+    ///
+    /// Module requests and failures are handled by the worker itself and never
+    /// reach the workflow, so they are not listed.
+    ///
+    /// # Arguments
+    /// * `role` - Name of the role
+    ///
+    /// # Returns
+    /// The list of conditions, or None if the role is not a built-in role
+    pub fn conditions_for_role(role: &str) -> Option<&'static [&'static str]> {
+        match role {
+            "proposer" => Some(&["proposal_generated"]),
+            "reviewer" => Some(&["approved", "revision_requested"]),
+            "validator" => Some(&["validated", "revision_requested"]),
+            "formatter" => Some(&["exported"]),
+            _ => None,
+        }
+    }
 }
@@ -1,5 +1,5 @@
 use super::{AgentBehavior, AgentOutcome};
-use crate::config::AgentConfig;
+use crate::config::{AgentConfig, ParametersConfig};
 use crate::constants::PROPOSER_FORMAT_REMINDER;
 use crate::constants::PROPOSER_USER_PROMPT;
 use crate::core::Task;
@@ -30,21 +30,19 @@ impl ProposerAgent {
     ///
     /// # Arguments
     /// * `config` - Configuration for the agent
-    /// * `llm_provider` - The LLM provider to use (e.g. "openai")
-    /// * `llm_model` - The specific model to use (e.g. "gpt-4")
+    /// * `parameters` - Task-level parameters, providing the LLM settings the role does not override
     ///
     /// # Returns
     /// * `(Self, UnboundedSender<Event>)` - The agent instance and a channel sender for events
     pub fn new(
         config: AgentConfig,
-        llm_provider: &str,
-        llm_model: &str,
+        parameters: &ParametersConfig,
     ) -> (Self, UnboundedSender<Event>) {
         let (self_tx, self_rx) = tokio::sync::mpsc::unbounded_channel();
 
         (
             Self {
-                llm_client: LlmClient::new(llm_provider, llm_model)
+                llm_client: LlmClient::from_settings(&parameters.llm_settings_for(&config))
                     .expect("Failed to create LLM client"),
                 user_prompt: config
                     .user_prompt
@@ -128,7 +126,9 @@ impl AgentBehavior for ProposerAgent {
 
         prompt.push_str("\n\nPlease now provide a 'Proposal:' or a 'MODULE_REQUEST:'.");
 
-        task.conversation.push(ChatMessage::new("user", &prompt));
+        let attachments = task.unsent_attachment_parts();
+        task.conversation
+            .push(ChatMessage::new("user", &prompt).with_parts(attachments));
 
         match self
             .llm_client
@@ -140,9 +140,16 @@ impl AgentBehavior for ProposerAgent {
             )
             .await
         {
-            Ok(new_proposal) => {
-                task.conversation
-                    .push(ChatMessage::new("assistant", &new_proposal));
+            Ok(response) => {
+                task.conversation.push(response.to_message());
+                if let Some(mr) = response
+                    .tool_calls
+                    .first()
+                    .and_then(|call| self.parse_tool_call(call))
+                {
+                    return (mr, task);
+                }
+                let new_proposal = response.content;
                 if let Some(mr) = self.parse_module_request(&new_proposal) {
                     return (mr, task);
                 }
@@ -152,7 +159,7 @@ impl AgentBehavior for ProposerAgent {
                 (AgentOutcome::ProposalGenerated, task)
             }
             Err(e) => (
-                AgentOutcome::Failed(format!("LLM error in Proposer: {}", e)),
+                AgentOutcome::llm_error(format!("LLM error in Proposer: {}", e), e.as_ref()),
                 task,
             ),
         }
@@ -1,5 +1,5 @@
 use super::{AgentBehavior, AgentOutcome};
-use crate::config::AgentConfig;
+use crate::config::{AgentConfig, ParametersConfig};
 use crate::constants::REVIEWER_FORMAT_REMINDER;
 use crate::constants::REVIEWER_USER_PROMPT;
 use crate::core::Task;
@@ -30,21 +30,19 @@ impl ReviewerAgent {
     ///
     /// # Arguments
     /// * `config` - Configuration for the agent
-    /// * `llm_provider` - The LLM provider to use (e.g. "openai")
-    /// * `llm_model` - The specific model to use (e.g. "gpt-4")
+    /// * `parameters` - Task-level parameters, providing the LLM settings the role does not override
     ///
     /// # Returns
     /// * `(Self, UnboundedSender<Event>)` - The agent instance and a channel sender for events
     pub fn new(
         config: AgentConfig,
-        llm_provider: &str,
-        llm_model: &str,
+        parameters: &ParametersConfig,
     ) -> (Self, UnboundedSender<Event>) {
         let (self_tx, self_rx) = tokio::sync::mpsc::unbounded_channel();
 
         (
             Self {
-                llm_client: LlmClient::new(llm_provider, llm_model)
+                llm_client: LlmClient::from_settings(&parameters.llm_settings_for(&config))
                     .expect("Failed to create LLM client"),
                 user_prompt: config
                     .user_prompt
@@ -136,7 +134,9 @@ impl AgentBehavior for ReviewerAgent {
             "\n\nPlease respond with 'approved' or 'revise: ...' or optionally 'MODULE_REQUEST:'.",
         );
 
-        task.conversation.push(ChatMessage::new("user", &prompt));
+        let attachments = task.unsent_attachment_parts();
+        task.conversation
+            .push(ChatMessage::new("user", &prompt).with_parts(attachments));
 
         match self
             .llm_client
@@ -149,8 +149,15 @@ impl AgentBehavior for ReviewerAgent {
             .await
         {
             Ok(response) => {
-                let resp = response.trim();
-                task.conversation.push(ChatMessage::new("assistant", resp));
+                task.conversation.push(response.to_message());
+                if let Some(mr) = response
+                    .tool_calls
+                    .first()
+                    .and_then(|call| self.parse_tool_call(call))
+                {
+                    return (mr, task);
+                }
+                let resp = response.content.trim();
                 if let Some(mr) = self.parse_module_request(resp) {
                     return (mr, task);
                 }
@@ -169,7 +176,7 @@ impl AgentBehavior for ReviewerAgent {
                 }
             }
             Err(e) => (
-                AgentOutcome::Failed(format!("LLM error in Reviewer: {}", e)),
+                AgentOutcome::llm_error(format!("LLM error in Reviewer: {}", e), e.as_ref()),
                 task,
             ),
         }
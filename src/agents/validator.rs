@@ -1,5 +1,5 @@
 use super::{AgentBehavior, AgentOutcome};
-use crate::config::AgentConfig;
+use crate::config::{AgentConfig, ParametersConfig};
 use crate::constants::VALIDATOR_FORMAT_REMINDER;
 use crate::constants::VALIDATOR_USER_PROMPT;
 use crate::core::Task;
@@ -29,21 +29,19 @@ impl ValidatorAgent {
     ///
     /// # Arguments
     /// * `config` - Configuration for the agent
-    /// * `llm_provider` - The LLM provider to use (e.g. "openai")
-    /// * `llm_model` - The specific model to use (e.g. "gpt-4")
+    /// * `parameters` - Task-level parameters, providing the LLM settings the role does not override
     ///
     /// # Returns
     /// * `(Self, UnboundedSender<Event>)` - The agent instance and a channel sender for events
     pub fn new(
         config: AgentConfig,
-        llm_provider: &str,
-        llm_model: &str,
+        parameters: &ParametersConfig,
     ) -> (Self, UnboundedSender<Event>) {
         let (self_tx, self_rx) = tokio::sync::mpsc::unbounded_channel();
 
         (
             Self {
-                llm_client: LlmClient::new(llm_provider, llm_model)
+                llm_client: LlmClient::from_settings(&parameters.llm_settings_for(&config))
                     .expect("Failed to create LLM client"),
                 user_prompt: config
                     .user_prompt
@@ -130,7 +128,9 @@ impl AgentBehavior for ValidatorAgent {
             "\n\nPlease respond with 'validated', 'not valid: ...' or 'MODULE_REQUEST:'.",
         );
 
-        task.conversation.push(ChatMessage::new("user", &prompt));
+        let attachments = task.unsent_attachment_parts();
+        task.conversation
+            .push(ChatMessage::new("user", &prompt).with_parts(attachments));
 
         match self
             .llm_client
@@ -143,8 +143,15 @@ impl AgentBehavior for ValidatorAgent {
             .await
         {
             Ok(response) => {
-                let resp = response.trim();
-                task.conversation.push(ChatMessage::new("assistant", resp));
+                task.conversation.push(response.to_message());
+                if let Some(mr) = response
+                    .tool_calls
+                    .first()
+                    .and_then(|call| self.parse_tool_call(call))
+                {
+                    return (mr, task);
+                }
+                let resp = response.content.trim();
                 if let Some(mr) = self.parse_module_request(resp) {
                     return (mr, task);
                 }
@@ -163,7 +170,7 @@ impl AgentBehavior for ValidatorAgent {
                 }
             }
             Err(e) => (
-                AgentOutcome::Failed(format!("LLM error in Validator: {}", e)),
+                AgentOutcome::llm_error(format!("LLM error in Validator: {}", e), e.as_ref()),
                 task,
             ),
         }
@@ -3,6 +3,7 @@ use crate::core::TaskState;
 /// Database and task management imports
 use crate::db::Database;
 use crate::db::TaskRepository;
+use crate::llm::FallbackEntry;
 use axum::http::StatusCode;
 use axum::{
     extract::{Extension, Path},
@@ -10,6 +11,7 @@ use axum::{
 };
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use uuid::Uuid;
 
 /// Represents the request payload for creating a new task
@@ -17,6 +19,9 @@ use uuid::Uuid;
 pub struct CreateTaskRequest {
     pub prompt: String,
     pub interval: Option<String>,
+    /// Values for the task inputs, referenced as `{{ inputs.<name> }}` in the generated task
+    #[serde(default)]
+    pub inputs: BTreeMap<String, String>,
 }
 
 /// Represents the response payload after successfully creating a task
@@ -35,7 +40,39 @@ pub struct TaskInfo {
     pub name: String,
     pub description: String,
     pub state: String,
+    /// Partial text of the LLM response currently being streamed, if any
+    pub progress: Option<String>,
     pub outputs: Vec<TaskOutputDTO>,
+    /// Token usage and cost of the LLM calls of the task
+    pub usage: TaskUsageDTO,
+    /// LLM calls that failed over to another provider/model, oldest first
+    pub fallbacks: Vec<FallbackEntry>,
+}
+
+/// Data transfer object representing the token usage and cost of a task
+#[derive(Serialize, Default)]
+pub struct TaskUsageDTO {
+    pub calls: i64,
+    /// Calls answered from the response cache, included in `calls`
+    pub cache_hits: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    /// Total cost of the priced models, None if no model is priced
+    pub cost: Option<f64>,
+    /// Usage per role and model
+    pub by_role: Vec<RoleUsageDTO>,
+}
+
+/// Data transfer object representing the token usage of a task for one role and model
+#[derive(Serialize)]
+pub struct RoleUsageDTO {
+    pub role: String,
+    pub model: String,
+    pub calls: i64,
+    pub cache_hits: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub cost: Option<f64>,
 }
 
 /// Data transfer object representing a task's output information
@@ -44,6 +81,11 @@ pub struct TaskOutputDTO {
     pub id: String,
     pub output: String,
     pub created_at: String,
+    pub sink: Option<String>,
+    pub status: Option<String>,
+    pub error: Option<String>,
+    /// Model that produced the output
+    pub model: Option<String>,
 }
 
 /// Creates a new task in the system
@@ -66,6 +108,11 @@ pub async fn create_task(
     let now = Utc::now().to_rfc3339();
     let interval = payload.interval.unwrap_or_default();
     let last_run_at = None;
+    let inputs = if payload.inputs.is_empty() {
+        None
+    } else {
+        serde_json::to_string(&payload.inputs).ok()
+    };
 
     let db_id = repo
         .insert_task(
@@ -81,6 +128,7 @@ pub async fn create_task(
             None,
             last_run_at,
             Some(interval),
+            inputs,
         )
         .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
 
@@ -124,16 +172,53 @@ pub async fn get_task(
             id: o.id.unwrap_or_default(),
             output: o.output,
             created_at: o.created_at,
+            sink: o.sink,
+            status: o.status,
+            error: o.error,
+            model: o.model,
         })
         .collect();
 
+    let usage_rows = repo
+        .get_task_usage(&task.task_id)
+        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?;
+    let mut usage = TaskUsageDTO::default();
+    for row in usage_rows {
+        usage.calls += row.calls;
+        usage.cache_hits += row.cache_hits;
+        usage.input_tokens += row.input_tokens;
+        usage.output_tokens += row.output_tokens;
+        if let Some(cost) = row.cost {
+            *usage.cost.get_or_insert(0.0) += cost;
+        }
+        usage.by_role.push(RoleUsageDTO {
+            role: row.role,
+            model: row.model,
+            calls: row.calls,
+            cache_hits: row.cache_hits,
+            input_tokens: row.input_tokens,
+            output_tokens: row.output_tokens,
+            cost: row.cost,
+        });
+    }
+
+    let fallbacks = repo
+        .get_task_events(&task.task_id, "llm_fallback")
+        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))?
+        .into_iter()
+        .filter_map(|event| serde_json::from_str(&event.content?).ok())
+        .collect();
+
     Ok(Json(TaskInfo {
         id: task.id.unwrap_or("".to_string()),
         task_id: task.task_id,
         name: task.name.unwrap_or_default(),
         description: task.description.unwrap_or_default(),
         state: task.state,
+        progress: task.progress,
         outputs: task_outputs,
+        usage,
+        fallbacks,
     }))
 }
 
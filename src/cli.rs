@@ -1,4 +1,4 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
 
 /// Command line interface for the application
 #[derive(Parser)]
@@ -32,4 +32,46 @@ pub struct Cli {
     /// LLM model (e.g gpt-4)
     #[arg(long)]
     pub llm_model: Option<String>,
+
+    /// Disables the LLM response cache (`parameters.llm_cache`) for every task of this run
+    #[arg(long, default_value_t = false)]
+    pub no_cache: bool,
+
+    /// Sets a task input, e.g. `--set base_path=/src/foo` (can be repeated)
+    #[arg(long = "set", value_name = "KEY=VALUE", value_parser = parse_input)]
+    pub inputs: Vec<(String, String)>,
+
+    /// Optional command to run instead of executing a task
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Standalone commands
+#[derive(Subcommand)]
+pub enum Command {
+    /// Statically checks a task configuration file without calling any LLM
+    Validate {
+        /// Path to the task configuration file to check
+        task_config: String,
+
+        /// Sets a task input used while checking, e.g. `--set base_path=/src/foo`
+        #[arg(long = "set", value_name = "KEY=VALUE", value_parser = parse_input)]
+        inputs: Vec<(String, String)>,
+    },
+}
+
+/// Parses a `KEY=VALUE` task input
+///
+/// # Arguments
+/// * `s` - The raw argument
+///
+/// # Returns
+/// * `Result<(String, String), String>` - The input name and value
+fn parse_input(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => {
+            Ok((key.trim().to_string(), value.to_string()))
+        }
+        _ => Err(format!("invalid input '{}', expected KEY=VALUE", s)),
+    }
 }
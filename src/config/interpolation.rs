@@ -0,0 +1,325 @@
+use super::TaskConfig;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+
+/// Format used for the `{{ now }}` placeholder, safe to use in file names
+const NOW_FORMAT: &str = "%Y-%m-%d_%H-%M-%S";
+
+/// Resolves the value of every declared input
+///
+/// Supplied values take precedence over declared defaults. Inputs supplied but not
+/// declared are rejected so that typos in `--set` do not go unnoticed.
+///
+/// # Arguments
+/// * `config` - The task configuration declaring the inputs
+/// * `supplied` - Values supplied by the caller (CLI `--set` or API payload)
+///
+/// # Returns
+/// * `Result<BTreeMap<String, String>, String>` - The resolved values, or a description of
+///   the unknown or missing inputs
+pub fn resolve_inputs(
+    config: &TaskConfig,
+    supplied: &BTreeMap<String, String>,
+) -> Result<BTreeMap<String, String>, String> {
+    if let Some(unknown) = supplied.keys().find(|k| !config.inputs.contains_key(*k)) {
+        let declared = config.inputs.keys().cloned().collect::<Vec<_>>();
+        return Err(format!(
+            "unknown input '{}' (declared inputs: {})",
+            unknown,
+            if declared.is_empty() {
+                "none".to_string()
+            } else {
+                declared.join(", ")
+            }
+        ));
+    }
+
+    let mut values = BTreeMap::new();
+    let mut missing = vec![];
+    for (name, input) in &config.inputs {
+        match supplied.get(name).or(input.default.as_ref()) {
+            Some(value) => {
+                values.insert(name.clone(), value.clone());
+            }
+            None => missing.push(name.as_str()),
+        }
+    }
+
+    if !missing.is_empty() {
+        return Err(format!(
+            "missing value for input(s) without default: {}",
+            missing.join(", ")
+        ));
+    }
+    Ok(values)
+}
+
+/// Replaces the `{{ now }}`, `{{ inputs.<name> }}` and `{{ env.<NAME> }}` placeholders of a string
+///
+/// Any other `{{ ... }}` text, such as a Handlebars or Jinja snippet in a prompt, and
+/// unclosed braces are left as is.
+///
+/// # Arguments
+/// * `text` - The string to interpolate
+/// * `inputs` - Resolved input values
+/// * `now` - Value substituted for `{{ now }}`
+///
+/// # Returns
+/// * `Result<String, String>` - The interpolated string or a description of the undeclared
+///   input or unset environment variable
+fn interpolate_str(
+    text: &str,
+    inputs: &BTreeMap<String, String>,
+    now: &str,
+) -> Result<String, String> {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find("{{") {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            rest = &rest[start..];
+            break;
+        };
+        let placeholder = &rest[start..start + end + 4];
+        let expr = after[..end].trim();
+        rest = &after[end + 2..];
+
+        if expr == "now" {
+            result.push_str(now);
+        } else if let Some(name) = expr.strip_prefix("inputs.") {
+            let value = inputs
+                .get(name)
+                .ok_or_else(|| format!("undeclared input '{}' in placeholder", name))?;
+            result.push_str(value);
+        } else if let Some(name) = expr.strip_prefix("env.") {
+            let value = std::env::var(name)
+                .map_err(|_| format!("environment variable '{}' is not set", name))?;
+            result.push_str(&value);
+        } else {
+            result.push_str(placeholder);
+        }
+    }
+
+    result.push_str(rest);
+    Ok(result)
+}
+
+/// Recursively interpolates every string of a JSON value
+fn interpolate_value(
+    value: &mut Value,
+    inputs: &BTreeMap<String, String>,
+    now: &str,
+) -> Result<(), String> {
+    match value {
+        Value::String(text) if text.contains("{{") => {
+            *text = interpolate_str(text, inputs, now)?;
+        }
+        Value::Array(items) => {
+            for item in items {
+                interpolate_value(item, inputs, now)?;
+            }
+        }
+        Value::Object(map) => {
+            for item in map.values_mut() {
+                interpolate_value(item, inputs, now)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Recursively collects the names referenced by `{{ inputs.<name> }}` placeholders
+fn collect_input_references(value: &Value, names: &mut BTreeSet<String>) {
+    match value {
+        Value::String(text) => {
+            let mut rest = text.as_str();
+            while let Some(start) = rest.find("{{") {
+                let after = &rest[start + 2..];
+                let Some(end) = after.find("}}") else { break };
+                if let Some(name) = after[..end].trim().strip_prefix("inputs.") {
+                    names.insert(name.to_string());
+                }
+                rest = &after[end + 2..];
+            }
+        }
+        Value::Array(items) => items
+            .iter()
+            .for_each(|item| collect_input_references(item, names)),
+        Value::Object(map) => map
+            .values()
+            .for_each(|item| collect_input_references(item, names)),
+        _ => {}
+    }
+}
+
+/// Lists the supplied inputs that no placeholder of the configuration refers to
+///
+/// # Arguments
+/// * `config` - The task configuration, before interpolation
+/// * `supplied` - Input values supplied by the caller
+///
+/// # Returns
+/// * `Vec<String>` - The names of the supplied inputs that would have no effect
+pub fn unreferenced_inputs(
+    config: &TaskConfig,
+    supplied: &BTreeMap<String, String>,
+) -> Vec<String> {
+    let mut referenced = BTreeSet::new();
+    if let Ok(mut value) = serde_json::to_value(config) {
+        if let Value::Object(map) = &mut value {
+            map.remove("inputs");
+        }
+        collect_input_references(&value, &mut referenced);
+    }
+    supplied
+        .keys()
+        .filter(|name| !referenced.contains(*name))
+        .cloned()
+        .collect()
+}
+
+/// Resolves the task inputs and substitutes placeholders throughout the configuration
+///
+/// Supported placeholders are `{{ inputs.<name> }}`, `{{ env.<NAME> }}` and `{{ now }}`.
+/// They are replaced in every string of the configuration (context, prompts, module
+/// config, output path...) except the `inputs` declarations themselves; other `{{ ... }}`
+/// text is kept. The resolved
+/// values are written back as the inputs' defaults so that the returned configuration
+/// is self-contained.
+///
+/// # Arguments
+/// * `config` - The task configuration to interpolate
+/// * `supplied` - Input values supplied by the caller
+///
+/// # Returns
+/// * `Result<TaskConfig, Box<dyn Error>>` - The interpolated configuration
+pub fn apply_inputs(
+    mut config: TaskConfig,
+    supplied: &BTreeMap<String, String>,
+) -> Result<TaskConfig, Box<dyn Error>> {
+    let inputs = resolve_inputs(&config, supplied)?;
+    let declarations = std::mem::take(&mut config.inputs);
+    let now = chrono::Local::now().format(NOW_FORMAT).to_string();
+
+    let mut value = serde_json::to_value(&config)?;
+    interpolate_value(&mut value, &inputs, &now)?;
+    let mut config: TaskConfig = serde_json::from_value(value)?;
+
+    config.inputs = declarations;
+    for (name, input) in config.inputs.iter_mut() {
+        input.default = inputs.get(name).cloned();
+    }
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(yaml: &str) -> TaskConfig {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    fn supplied(values: &[(&str, &str)]) -> BTreeMap<String, String> {
+        values
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    const TASK: &str = r#"
+name: "audit {{ inputs.repo }}"
+description: "Audit {{ inputs.repo }} on {{ inputs.branch }}"
+inputs:
+  repo: {}
+  branch:
+    default: "main"
+"#;
+
+    #[test]
+    fn resolve_prefers_supplied_values_over_defaults() {
+        let config = parse(TASK);
+        let values = resolve_inputs(&config, &supplied(&[("repo", "kheish")])).unwrap();
+        assert_eq!(values, supplied(&[("branch", "main"), ("repo", "kheish")]));
+
+        let values =
+            resolve_inputs(&config, &supplied(&[("repo", "kheish"), ("branch", "dev")])).unwrap();
+        assert_eq!(values["branch"], "dev");
+    }
+
+    #[test]
+    fn resolve_rejects_missing_and_unknown_inputs() {
+        let config = parse(TASK);
+        let error = resolve_inputs(&config, &BTreeMap::new()).unwrap_err();
+        assert!(
+            error.contains("missing value") && error.contains("repo"),
+            "{}",
+            error
+        );
+
+        let error =
+            resolve_inputs(&config, &supplied(&[("repo", "kheish"), ("rep", "x")])).unwrap_err();
+        assert!(error.contains("unknown input 'rep'"), "{}", error);
+    }
+
+    #[test]
+    fn apply_substitutes_placeholders_and_records_the_values() {
+        let config = apply_inputs(parse(TASK), &supplied(&[("repo", "kheish")])).unwrap();
+        assert_eq!(config.name, "audit kheish");
+        assert_eq!(config.description.as_deref(), Some("Audit kheish on main"));
+        assert_eq!(config.inputs["repo"].default.as_deref(), Some("kheish"));
+        assert_eq!(config.inputs["branch"].default.as_deref(), Some("main"));
+    }
+
+    #[test]
+    fn apply_substitutes_now_and_environment_variables() {
+        std::env::set_var("KHEISH_INTERPOLATION_TEST", "from-env");
+        let config = apply_inputs(
+            parse("name: \"{{ env.KHEISH_INTERPOLATION_TEST }} {{now}}\""),
+            &BTreeMap::new(),
+        )
+        .unwrap();
+        let (env, now) = config.name.split_once(' ').unwrap();
+        assert_eq!(env, "from-env");
+        assert!(chrono::NaiveDateTime::parse_from_str(now, NOW_FORMAT).is_ok());
+
+        let error = apply_inputs(
+            parse("name: \"{{ env.KHEISH_INTERPOLATION_UNSET }}\""),
+            &BTreeMap::new(),
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("is not set"));
+    }
+
+    #[test]
+    fn apply_keeps_other_braces() {
+        let text = "Answer with {{ answer }}, {\"a\": {{b}}} and {{ unclosed";
+        let config = apply_inputs(
+            parse(&format!("name: {}", serde_json::json!(text))),
+            &BTreeMap::new(),
+        )
+        .unwrap();
+        assert_eq!(config.name, text);
+    }
+
+    #[test]
+    fn apply_rejects_undeclared_inputs_in_placeholders() {
+        let error =
+            apply_inputs(parse("name: \"{{ inputs.repo }}\""), &BTreeMap::new()).unwrap_err();
+        assert!(error.to_string().contains("undeclared input 'repo'"));
+    }
+
+    #[test]
+    fn unreferenced_inputs_are_listed() {
+        let config = parse(TASK);
+        let unused = unreferenced_inputs(
+            &config,
+            &supplied(&[("repo", "kheish"), ("branch", "dev"), ("extra", "x")]),
+        );
+        assert_eq!(unused, ["extra"]);
+    }
+}
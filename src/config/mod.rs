@@ -1,7 +1,12 @@
+mod interpolation;
 mod parser;
+mod validation;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
+pub use interpolation::{apply_inputs, unreferenced_inputs};
 pub use parser::load_task_config;
+pub use validation::{validate_task_file, Severity};
 
 /// Main configuration structure for a task
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -14,6 +19,9 @@ pub struct TaskConfig {
     /// Optional version of the task configuration
     #[serde(default)]
     pub version: Option<String>,
+    /// Declared inputs, referenced as `{{ inputs.<name> }}` anywhere in the configuration
+    #[serde(default)]
+    pub inputs: BTreeMap<String, InputConfig>,
     /// List of context items providing input data
     #[serde(default)]
     pub context: Vec<ContextItem>,
@@ -37,6 +45,17 @@ pub struct TaskConfig {
     pub interval: Option<String>,
 }
 
+/// Declaration of a task input
+#[derive(Debug, Deserialize, Default, Clone, Serialize)]
+pub struct InputConfig {
+    /// Optional description of the input
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Value used when the input is not supplied; inputs without default are required
+    #[serde(default)]
+    pub default: Option<String>,
+}
+
 /// Configuration for the different agent roles
 #[derive(Debug, Deserialize, Default, Clone, Serialize)]
 pub struct AgentsConfig {
@@ -52,6 +71,9 @@ pub struct AgentsConfig {
     /// Configuration for formatter agents
     #[serde(default)]
     pub formatter: AgentConfig,
+    /// User-defined roles, keyed by role name
+    #[serde(flatten)]
+    pub custom: BTreeMap<String, AgentConfig>,
 }
 
 /// Configuration for a specific agent role
@@ -70,14 +92,159 @@ pub struct AgentConfig {
     /// Optional schema for the agent
     #[serde(default)]
     pub schema: Option<String>,
+    /// Response protocol of a user-defined role, mapping response prefixes to
+    /// workflow conditions. Ignored for built-in roles.
+    #[serde(default)]
+    pub protocol: Vec<ResponseRule>,
+    /// LLM provider used by this role, overriding `parameters.llm_provider`
+    #[serde(default)]
+    pub llm_provider: Option<String>,
+    /// LLM model used by this role, overriding `parameters.llm_model`
+    #[serde(default)]
+    pub llm_model: Option<String>,
+    /// Generation settings of this role, each one overriding `parameters.generation`
+    #[serde(default)]
+    pub generation: GenerationConfig,
+    /// Endpoint of the LLM provider of this role, overriding `parameters.llm_endpoint`
+    #[serde(default)]
+    pub llm_endpoint: Option<EndpointConfig>,
+}
+
+/// Settings sent to the LLM with every request; unset values use the provider defaults
+#[derive(Debug, Deserialize, Default, Clone, Serialize, PartialEq)]
+pub struct GenerationConfig {
+    /// Sampling temperature
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    /// Maximum number of tokens in a response
+    #[serde(default)]
+    pub max_tokens: Option<u32>,
+    /// Context window of the model in tokens, overriding the built-in per-model limit
+    #[serde(default)]
+    pub context_window: Option<usize>,
+    /// Nucleus sampling probability mass
+    #[serde(default)]
+    pub top_p: Option<f32>,
+    /// Sequences stopping the generation
+    #[serde(default)]
+    pub stop: Vec<String>,
+    /// Seed of the sampling, for reproducible responses
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// Reasoning effort of the reasoning models (OpenAI o-series)
+    #[serde(default)]
+    pub reasoning_effort: Option<ReasoningEffort>,
+}
+
+/// Reasoning effort requested from a reasoning model
+#[derive(Debug, Deserialize, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ReasoningEffort {
+    /// Faster and cheaper responses
+    Low,
+    /// Default of the models
+    Medium,
+    /// More thorough reasoning
+    High,
+}
+
+impl GenerationConfig {
+    /// Returns these settings with every unset value taken from `fallback`
+    ///
+    /// # Arguments
+    /// * `fallback` - Settings used for the values not set here
+    pub fn or(&self, fallback: &GenerationConfig) -> GenerationConfig {
+        GenerationConfig {
+            temperature: self.temperature.or(fallback.temperature),
+            max_tokens: self.max_tokens.or(fallback.max_tokens),
+            context_window: self.context_window.or(fallback.context_window),
+            top_p: self.top_p.or(fallback.top_p),
+            stop: if self.stop.is_empty() {
+                fallback.stop.clone()
+            } else {
+                self.stop.clone()
+            },
+            seed: self.seed.or(fallback.seed),
+            reasoning_effort: self.reasoning_effort.or(fallback.reasoning_effort),
+        }
+    }
+}
+
+/// Endpoint of an LLM provider; unset values use the environment, then the provider defaults
+#[derive(Debug, Deserialize, Default, Clone, Serialize, PartialEq)]
+pub struct EndpointConfig {
+    /// Base URL of the API (e.g. "http://localhost:8000/v1"), overriding `<PROVIDER>_BASE_URL`
+    #[serde(default)]
+    pub base_url: Option<String>,
+    /// Name of the environment variable holding the API key
+    #[serde(default)]
+    pub api_key_env: Option<String>,
+    /// Header carrying the API key of an `openai_compatible` provider; "Authorization"
+    /// (the default) sends it as a bearer token, any other header sends it as is
+    #[serde(default)]
+    pub auth_header: Option<String>,
+    /// Extra headers sent with every request
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// `api-version` of an `azure_openai` provider, overriding `AZURE_OPENAI_API_VERSION`
+    #[serde(default)]
+    pub api_version: Option<String>,
+}
+
+/// LLM provider, model and generation settings resolved for one role
+#[derive(Debug, Clone, PartialEq)]
+pub struct LlmSettings {
+    /// Name of the LLM provider
+    pub provider: Option<String>,
+    /// Name of the LLM model
+    pub model: Option<String>,
+    /// Generation settings
+    pub generation: GenerationConfig,
+    /// HTTP retry settings of the provider requests
+    pub retry: RetryConfig,
+    /// Endpoint of the provider
+    pub endpoint: EndpointConfig,
+    /// Provider/model pairs failed over to, in order, when the provider keeps failing
+    pub fallbacks: Vec<(String, String)>,
+    /// Settings of the `mock` provider
+    pub mock: MockConfig,
+    /// On-disk cache of the responses
+    pub cache: CacheConfig,
+    /// Compaction of the conversations outgrowing the context window
+    pub compaction: CompactionConfig,
+}
+
+/// Maps a response prefix of a user-defined role to a workflow condition
+#[derive(Debug, Deserialize, Clone, Serialize)]
+pub struct ResponseRule {
+    /// Prefix the response must start with, matched case-insensitively (e.g. "Plan:")
+    pub prefix: String,
+    /// Workflow condition emitted when the prefix matches
+    pub condition: String,
+    /// What the text following the prefix is stored as
+    #[serde(default)]
+    pub capture: Option<ResponseCapture>,
+}
+
+/// Where the text following a matched response prefix is stored in the task
+#[derive(Debug, Deserialize, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ResponseCapture {
+    /// Replaces the current proposal
+    Proposal,
+    /// Recorded as feedback for the next proposer turn
+    Feedback,
 }
 
 /// Represents a single context item providing input data
+///
+/// Supported kinds are `file`, `text`, `user_input`, `directory`, `glob`, `url`,
+/// `command`, `json` and `yaml`.
 #[derive(Debug, Deserialize, Clone, Serialize)]
 pub struct ContextItem {
     /// Type of the context item
     pub kind: String,
-    /// Optional file path for the context
+    /// Optional file path for the context; the root for `directory` and the pattern for `glob`
     #[serde(default)]
     pub path: Option<String>,
     /// Optional inline content
@@ -86,6 +253,32 @@ pub struct ContextItem {
     /// Optional alias name for the context
     #[serde(default)]
     pub alias: Option<String>,
+    /// URL fetched by `url` items
+    #[serde(default)]
+    pub url: Option<String>,
+    /// Shell command whose standard output is captured by `command` items
+    #[serde(default)]
+    pub command: Option<String>,
+    /// Glob patterns, relative to the directory, a file must match to be loaded
+    /// by `directory` items; all files are loaded when empty
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Glob patterns excluding files from `directory` and `glob` items
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Files larger than this number of bytes are skipped
+    #[serde(default)]
+    pub max_file_size: Option<u64>,
+    /// Loading stops once this number of bytes has been read by the item
+    #[serde(default)]
+    pub max_total_size: Option<u64>,
+    /// Dotted path of the value picked from `json` / `yaml` files (e.g. `servers.0.host`);
+    /// the whole document is used when unset
+    #[serde(default)]
+    pub query: Option<String>,
+    /// Whether a missing or failing source only produces a warning
+    #[serde(default)]
+    pub optional: bool,
 }
 
 /// Configuration for a module
@@ -107,6 +300,37 @@ pub struct WorkflowConfig {
     /// List of workflow steps
     #[serde(default)]
     pub steps: Vec<WorkflowStep>,
+    /// Groups of roles run in parallel; a group name can be used as a role in steps
+    #[serde(default)]
+    pub parallel: Vec<ParallelGroup>,
+}
+
+/// A set of roles that receive the same task at once and whose verdicts are joined
+#[derive(Debug, Deserialize, Clone, Serialize)]
+pub struct ParallelGroup {
+    /// Name of the group, used as `to` / `from` in workflow steps
+    pub name: String,
+    /// Roles run in parallel, each on its own copy of the task
+    pub branches: Vec<String>,
+    /// How branch verdicts are combined into the group outcome
+    #[serde(default)]
+    pub join: JoinPolicy,
+}
+
+/// Policy combining the verdicts of parallel branches
+///
+/// A branch approves unless it requests a revision. The group then emits
+/// `approved` or `revision_requested`.
+#[derive(Debug, Deserialize, Clone, Copy, Serialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum JoinPolicy {
+    /// Approved only if every branch approves
+    #[default]
+    AllApproved,
+    /// Approved if at least one branch approves
+    AnyApproved,
+    /// Approved if strictly more than half of the branches approve
+    Majority,
 }
 
 /// Represents a single step in the workflow
@@ -118,17 +342,46 @@ pub struct WorkflowStep {
     pub to: String,
     /// Condition for the transition
     pub condition: String,
+    /// Optional guard expression that must hold for the transition to be taken,
+    /// e.g. `revision_count >= 5`; a task with an invalid guard does not start
+    #[serde(default)]
+    pub guard: Option<String>,
+}
+
+/// A single value or an ordered list of values
+#[derive(Debug, Deserialize, Clone, Serialize, PartialEq)]
+#[serde(untagged)]
+pub enum OneOrMany {
+    /// A single value
+    One(String),
+    /// An ordered list of values
+    Many(Vec<String>),
+}
+
+impl OneOrMany {
+    /// Returns the first value
+    pub fn first(&self) -> Option<&str> {
+        self.values().first().copied()
+    }
+
+    /// Returns the values, in order
+    pub fn values(&self) -> Vec<&str> {
+        match self {
+            OneOrMany::One(value) => vec![value.as_str()],
+            OneOrMany::Many(values) => values.iter().map(String::as_str).collect(),
+        }
+    }
 }
 
 /// Global parameters for task execution
 #[derive(Debug, Deserialize, Default, Clone, Serialize)]
 pub struct ParametersConfig {
-    /// Name/identifier of the LLM model to use
+    /// Name/identifier of the LLM model to use, or the models of the fallback chain
     #[serde(default)]
-    pub llm_model: Option<String>,
-    /// Name/identifier of the LLM provider to use
+    pub llm_model: Option<OneOrMany>,
+    /// Name/identifier of the LLM provider to use, or the providers of the fallback chain
     #[serde(default)]
-    pub llm_provider: Option<String>,
+    pub llm_provider: Option<OneOrMany>,
     /// Whether to export the conversation to a JSON file
     #[serde(default)]
     pub export_conversation: bool,
@@ -141,20 +394,376 @@ pub struct ParametersConfig {
     /// Maximum number of retries allowed
     #[serde(default)]
     pub max_retries: Option<usize>,
+    /// Default generation settings for every role
+    #[serde(default)]
+    pub generation: GenerationConfig,
+    /// Hard budgets bounding the execution of the task
+    #[serde(default)]
+    pub limits: LimitsConfig,
+    /// HTTP retry policy of the LLM provider requests
+    #[serde(default)]
+    pub llm_retry: RetryConfig,
+    /// Endpoint of the LLM provider (base URL, API key variable, headers)
+    #[serde(default)]
+    pub llm_endpoint: EndpointConfig,
+    /// Price of the tokens of each model, keyed by model name
+    #[serde(default)]
+    pub pricing: BTreeMap<String, ModelPrice>,
+    /// Behaviour of the `mock` provider, whose `llm_model` is the path of its script or cassette
+    #[serde(default)]
+    pub mock: MockConfig,
+    /// On-disk cache of the LLM responses
+    #[serde(default)]
+    pub llm_cache: CacheConfig,
+    /// Compaction of the conversations outgrowing the context window
+    #[serde(default)]
+    pub compaction: CompactionConfig,
+    /// HTTP transport of the process, read from the startup task only
+    #[serde(default)]
+    pub http: HttpConfig,
 }
 
-/// Output configuration
+/// How a conversation nearing the context window of the model is shortened
+#[derive(Debug, Deserialize, Default, Clone, Serialize, PartialEq)]
+pub struct CompactionConfig {
+    /// Whether the oldest turns are dropped or replaced by a summary
+    #[serde(default)]
+    pub strategy: CompactionStrategy,
+    /// Fraction of the input budget above which older turns are summarized (default 0.8)
+    #[serde(default)]
+    pub threshold: Option<f32>,
+    /// Number of messages before the latest user turn that are never summarized (default 4)
+    #[serde(default)]
+    pub keep_recent: Option<usize>,
+}
+
+/// Strategy shortening a conversation that outgrows the context window
+#[derive(Debug, Deserialize, Clone, Copy, Serialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CompactionStrategy {
+    /// The oldest turns are evicted once the conversation exceeds the context window
+    #[default]
+    Truncate,
+    /// Older turns are replaced by an LLM-written summary once the threshold is crossed
+    Summarize,
+}
+
+/// On-disk cache of the LLM responses, keyed by provider, model, generation settings and messages
+#[derive(Debug, Deserialize, Default, Clone, Serialize, PartialEq)]
+pub struct CacheConfig {
+    /// Whether responses are cached; `--no-cache` disables it for every task of a run
+    #[serde(default)]
+    pub enabled: bool,
+    /// Directory of the cache, defaults to `<user cache dir>/kheish/llm`
+    #[serde(default)]
+    pub dir: Option<String>,
+    /// Age after which a cached response is ignored (e.g. "7d"); never expires if unset
+    #[serde(default)]
+    pub ttl: Option<String>,
+    /// Size above which the oldest responses are evicted (e.g. "200MB"); unbounded if unset
+    #[serde(default)]
+    pub max_size: Option<String>,
+}
+
+/// Settings of the `mock` provider
+#[derive(Debug, Deserialize, Default, Clone, Serialize, PartialEq)]
+pub struct MockConfig {
+    /// Whether responses are scripted, recorded from a real provider or replayed
+    #[serde(default)]
+    pub mode: MockMode,
+    /// Real provider called when recording; also sizes the context window when replaying
+    #[serde(default)]
+    pub provider: Option<String>,
+    /// Model of the real provider
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+/// Source of the responses of the `mock` provider
+#[derive(Debug, Deserialize, Clone, Copy, Serialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MockMode {
+    /// Scripted responses, matched by role and by a regex on the last message
+    #[default]
+    Script,
+    /// Calls the real provider and appends every request/response pair to a cassette
+    Record,
+    /// Serves back the responses of a recorded cassette
+    Replay,
+}
+
+/// Price of the tokens of a model, in currency units per million tokens
+#[derive(Debug, Deserialize, Default, Clone, Copy, Serialize, PartialEq)]
+pub struct ModelPrice {
+    /// Price of a million input (prompt) tokens
+    #[serde(default)]
+    pub input_per_mtok: f64,
+    /// Price of a million output (completion) tokens
+    #[serde(default)]
+    pub output_per_mtok: f64,
+}
+
+impl ModelPrice {
+    /// Returns the cost of the given number of tokens
+    ///
+    /// # Arguments
+    /// * `input_tokens` - Number of input tokens
+    /// * `output_tokens` - Number of output tokens
+    pub fn cost(&self, input_tokens: u64, output_tokens: u64) -> f64 {
+        (input_tokens as f64 * self.input_per_mtok + output_tokens as f64 * self.output_per_mtok)
+            / 1_000_000.0
+    }
+}
+
+/// HTTP retry policy of the LLM provider requests; unset values use the defaults
+#[derive(Debug, Deserialize, Default, Clone, Serialize, PartialEq)]
+pub struct RetryConfig {
+    /// Number of attempts of a request, the first one included
+    #[serde(default)]
+    pub max_attempts: Option<u32>,
+    /// Timeout of a request (e.g. "120s"); timed out requests are retried, then failed over
+    #[serde(default)]
+    pub timeout: Option<String>,
+    /// Delay before the first retry, doubled on every attempt (e.g. "500ms")
+    #[serde(default)]
+    pub initial_backoff: Option<String>,
+    /// Upper bound of the delay between two attempts (e.g. "30s")
+    #[serde(default)]
+    pub max_backoff: Option<String>,
+}
+
+/// HTTP transport shared by the whole process, set once at startup from the task given on the
+/// command line; unset values fall back to the `KHEISH_HTTP_*` environment variables, then the
+/// defaults
+#[derive(Debug, Deserialize, Default, Clone, Serialize, PartialEq)]
+pub struct HttpConfig {
+    /// Timeout of the connection to a server (e.g. "10s")
+    #[serde(default)]
+    pub connect_timeout: Option<String>,
+    /// Timeout of each read of a response, streamed chunks included (e.g. "60s")
+    #[serde(default)]
+    pub read_timeout: Option<String>,
+    /// HTTP(S) proxy of every request; `HTTPS_PROXY`, `HTTP_PROXY` and `NO_PROXY` apply if unset
+    #[serde(default)]
+    pub proxy: Option<String>,
+    /// Comma-separated hosts and domains reached without the proxy
+    #[serde(default)]
+    pub no_proxy: Option<String>,
+    /// PEM files of extra root certificates, e.g. an internal CA
+    #[serde(default)]
+    pub ca_certs: Vec<String>,
+    /// User agent of the requests, defaults to `kheish/<version>`
+    #[serde(default)]
+    pub user_agent: Option<String>,
+    /// Time after which an idle pooled connection is closed (e.g. "90s")
+    #[serde(default)]
+    pub pool_idle_timeout: Option<String>,
+    /// Maximum number of idle pooled connections per host
+    #[serde(default)]
+    pub pool_max_idle_per_host: Option<usize>,
+}
+
+/// Hard budgets bounding the execution of a task; unset limits are not enforced
+#[derive(Debug, Deserialize, Default, Clone, Serialize)]
+pub struct LimitsConfig {
+    /// Maximum number of revisions requested by reviewers and validators; N allows N revisions
+    #[serde(default)]
+    pub max_revisions: Option<usize>,
+    /// Maximum number of module calls, all modules included; N allows N calls
+    #[serde(default)]
+    pub max_module_calls: Option<usize>,
+    /// Maximum number of LLM calls, all roles included; N allows N calls, checked between
+    /// steps so that a role already running may finish its calls
+    #[serde(default)]
+    pub max_llm_calls: Option<usize>,
+    /// Maximum wall-clock duration of a run (e.g. "15m"); no step starts once it has elapsed
+    #[serde(default)]
+    pub max_duration: Option<String>,
+    /// What happens when a limit is hit
+    #[serde(default)]
+    pub on_limit: LimitPolicy,
+}
+
+/// Policy applied when a task hits one of its limits
+#[derive(Debug, Deserialize, Clone, Copy, Serialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LimitPolicy {
+    /// The task fails
+    #[default]
+    Fail,
+    /// The formatter is run on the best proposal so far
+    Format,
+}
+
+impl ParametersConfig {
+    /// Returns the task-level provider
+    pub fn provider(&self) -> Option<&str> {
+        self.llm_provider.as_ref().and_then(OneOrMany::first)
+    }
+
+    /// Returns the task-level model
+    pub fn model(&self) -> Option<&str> {
+        self.llm_model.as_ref().and_then(OneOrMany::first)
+    }
+
+    /// Returns the provider/model pairs of the task, in fallback order
+    ///
+    /// A single provider is paired with every model and a single model with every provider;
+    /// otherwise the lists are paired in order.
+    pub fn llm_chain(&self) -> Vec<(String, String)> {
+        let providers = self.llm_provider.as_ref().map(OneOrMany::values);
+        let models = self.llm_model.as_ref().map(OneOrMany::values);
+        let (Some(providers), Some(models)) = (providers, models) else {
+            return Vec::new();
+        };
+        let pick = |values: &[&str], index: usize| match values {
+            [value] => Some(value.to_string()),
+            values => values.get(index).map(|value| value.to_string()),
+        };
+        (0..providers.len().max(models.len()))
+            .map_while(|index| Some((pick(&providers, index)?, pick(&models, index)?)))
+            .collect()
+    }
+
+    /// Returns the task-level LLM settings
+    pub fn llm_settings(&self) -> LlmSettings {
+        LlmSettings {
+            provider: self.provider().map(str::to_string),
+            model: self.model().map(str::to_string),
+            generation: self.generation.clone(),
+            retry: self.llm_retry.clone(),
+            endpoint: self.llm_endpoint.clone(),
+            fallbacks: self.llm_chain().into_iter().skip(1).collect(),
+            mock: self.mock.clone(),
+            cache: self.llm_cache.clone(),
+            compaction: self.compaction.clone(),
+        }
+    }
+
+    /// Returns the LLM settings of a role, falling back to the task-level parameters
+    /// for everything the role does not set
+    ///
+    /// The task endpoint is only inherited by roles using the task provider, and the
+    /// fallback chain by roles setting neither a provider nor a model.
+    ///
+    /// # Arguments
+    /// * `agent` - Configuration of the role
+    pub fn llm_settings_for(&self, agent: &AgentConfig) -> LlmSettings {
+        let provider = agent
+            .llm_provider
+            .clone()
+            .or(self.provider().map(str::to_string));
+        let endpoint = match &agent.llm_endpoint {
+            Some(endpoint) => endpoint.clone(),
+            None if provider.as_deref() == self.provider() => self.llm_endpoint.clone(),
+            None => EndpointConfig::default(),
+        };
+        let fallbacks = if agent.llm_provider.is_none() && agent.llm_model.is_none() {
+            self.llm_chain().into_iter().skip(1).collect()
+        } else {
+            Vec::new()
+        };
+        LlmSettings {
+            provider,
+            model: agent.llm_model.clone().or(self.model().map(str::to_string)),
+            generation: agent.generation.or(&self.generation),
+            retry: self.llm_retry.clone(),
+            endpoint,
+            fallbacks,
+            mock: self.mock.clone(),
+            cache: self.llm_cache.clone(),
+            compaction: self.compaction.clone(),
+        }
+    }
+}
+
+/// Output configuration: the sinks the validated proposal is delivered to
+///
+/// Accepts either a single sink (`output: { format, file }`) or a list of sinks.
 #[derive(Debug, Deserialize, Default, Clone, Serialize)]
+#[serde(from = "OutputSinks", into = "Vec<OutputSink>")]
 pub struct OutputConfig {
-    /// Format of the output
+    /// Sinks, each formatted and delivered independently
+    pub sinks: Vec<OutputSink>,
+}
+
+/// Either form accepted for the `output` key
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum OutputSinks {
+    Many(Vec<OutputSink>),
+    One(OutputSink),
+}
+
+impl From<OutputSinks> for OutputConfig {
+    fn from(sinks: OutputSinks) -> Self {
+        let sinks = match sinks {
+            OutputSinks::Many(sinks) => sinks,
+            OutputSinks::One(sink) => vec![sink],
+        };
+        OutputConfig { sinks }
+    }
+}
+
+impl From<OutputConfig> for Vec<OutputSink> {
+    fn from(output: OutputConfig) -> Self {
+        output.sinks
+    }
+}
+
+/// A single output target
+#[derive(Debug, Deserialize, Clone, Serialize)]
+pub struct OutputSink {
+    /// Name of the sink, used in logs and run records; defaults to the format
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Format of the output (e.g. "markdown", "json", "csv")
     pub format: String,
-    /// File path for the output
-    pub file: String,
+    /// Additional formatter instructions for this sink
+    #[serde(default)]
+    pub prompt: Option<String>,
+    /// JSON schema the output must match, overriding the formatter schema
+    #[serde(default)]
+    pub schema: Option<String>,
+    /// Where the output is delivered
+    #[serde(default)]
+    pub destination: SinkDestination,
+    /// File path for the `file` destination
+    #[serde(default)]
+    pub file: Option<String>,
+    /// URL the output is POSTed to for the `webhook` destination
+    #[serde(default)]
+    pub url: Option<String>,
+}
+
+impl OutputSink {
+    /// Returns the name of the sink, or its format when unnamed
+    pub fn label(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.format)
+    }
+}
+
+/// Destination of an output sink
+#[derive(Debug, Deserialize, Clone, Copy, Serialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SinkDestination {
+    /// Written to `file`
+    #[default]
+    File,
+    /// Printed on the standard output
+    Stdout,
+    /// Only stored with the task outputs in the database
+    Database,
+    /// POSTed to `url`
+    Webhook,
 }
 
 /// Embedder configuration
 #[derive(Debug, Deserialize, Clone, Default, Serialize)]
 pub struct EmbedderConfig {
+    /// Embedder to use: "openai" (the default) or "fake", a deterministic offline embedder
+    #[serde(default)]
+    pub provider: Option<String>,
     /// Name/identifier of the embedder
     pub model: Option<String>,
 }
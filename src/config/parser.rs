@@ -1,11 +1,29 @@
 use super::TaskConfig;
+use serde_yaml::{Mapping, Value};
 use std::error::Error;
 use std::fs;
+use std::path::{Path, PathBuf};
 
 use tracing::debug;
 
+/// Key naming the base configuration(s) a task file inherits from
+const EXTENDS_KEY: &str = "extends";
+
+/// YAML tag replacing a node with the content of another file
+const INCLUDE_TAG: &str = "include";
+
+/// Lists merged by the `name` of their items instead of being replaced
+const MERGED_BY_NAME: &[&str] = &["modules", "workflow.parallel"];
+
+/// Lists whose inherited items are kept, with the overriding items appended
+const APPENDED: &[&str] = &["context"];
+
 /// Loads and parses a task configuration from a YAML file
 ///
+/// The file may inherit from one or more base files with `extends: base.yaml` (or a
+/// list of paths, applied in order), and any node may be replaced by the content of
+/// another file with `!include path`. Paths are relative to the file declaring them.
+///
 /// # Arguments
 ///
 /// * `file_path` - Path to the YAML configuration file
@@ -17,11 +35,202 @@ use tracing::debug;
 /// # Errors
 ///
 /// Returns an error if:
-/// * The file cannot be read
-/// * The YAML content cannot be parsed into a TaskConfig
+/// * The file, one of its bases or one of its includes cannot be read
+/// * The files include or extend each other in a cycle
+/// * The merged YAML content cannot be parsed into a TaskConfig
 pub fn load_task_config(file_path: &str) -> Result<TaskConfig, Box<dyn Error>> {
-    let yaml_str = fs::read_to_string(file_path)?;
-    let task_config: TaskConfig = serde_yaml::from_str(&yaml_str)?;
+    let value = load_task_value(file_path)?;
+    let task_config: TaskConfig = serde_yaml::from_value(value)?;
     debug!("Loaded task configuration: {:?}", task_config.name);
     Ok(task_config)
 }
+
+/// Loads a task file as a YAML value with its includes resolved and its bases merged
+///
+/// # Arguments
+///
+/// * `file_path` - Path to the YAML configuration file
+///
+/// # Returns
+///
+/// * `Result<Value, Box<dyn Error>>` - The fully merged YAML document
+pub fn load_task_value(file_path: &str) -> Result<Value, Box<dyn Error>> {
+    load_file(Path::new(file_path), &mut vec![])
+}
+
+/// Loads a YAML file, resolving includes and `extends` recursively
+///
+/// # Arguments
+///
+/// * `path` - Path of the file to load
+/// * `stack` - Canonical paths of the files currently being loaded, used to detect cycles
+fn load_file(path: &Path, stack: &mut Vec<PathBuf>) -> Result<Value, Box<dyn Error>> {
+    let canonical = enter(path, stack)?;
+    let content =
+        fs::read_to_string(path).map_err(|e| format!("cannot read '{}': {}", path.display(), e))?;
+    let value: Value = serde_yaml::from_str(&content)
+        .map_err(|e| format!("invalid YAML in '{}': {}", path.display(), e))?;
+    let dir = base_dir(path);
+
+    let mut value = resolve_includes(value, &dir, stack)?;
+
+    let bases = match &mut value {
+        Value::Mapping(map) => map.remove(EXTENDS_KEY),
+        _ => None,
+    };
+    let bases = match bases {
+        None | Some(Value::Null) => vec![],
+        Some(Value::String(base)) => vec![base],
+        Some(Value::Sequence(bases)) => bases
+            .into_iter()
+            .map(|base| match base {
+                Value::String(base) => Ok(base),
+                _ => Err(format!("'{}' entries must be paths", EXTENDS_KEY)),
+            })
+            .collect::<Result<_, _>>()?,
+        Some(_) => {
+            return Err(format!("'{}' must be a path or a list of paths", EXTENDS_KEY).into())
+        }
+    };
+
+    let mut merged = Value::Mapping(Mapping::new());
+    for base in bases {
+        debug!("{} extends {}", path.display(), base);
+        let base = load_file(&dir.join(base), stack)?;
+        merged = merge(merged, base, "");
+    }
+    let merged = merge(merged, value, "");
+
+    stack.retain(|p| p != &canonical);
+    Ok(merged)
+}
+
+/// Pushes a file on the loading stack, failing if it is already being loaded
+fn enter(path: &Path, stack: &mut Vec<PathBuf>) -> Result<PathBuf, Box<dyn Error>> {
+    let canonical = path
+        .canonicalize()
+        .map_err(|e| format!("cannot read '{}': {}", path.display(), e))?;
+    if stack.contains(&canonical) {
+        let chain = stack
+            .iter()
+            .chain(std::iter::once(&canonical))
+            .map(|p| p.display().to_string())
+            .collect::<Vec<_>>()
+            .join(" -> ");
+        return Err(format!("cyclic extends/include: {}", chain).into());
+    }
+    stack.push(canonical.clone());
+    Ok(canonical)
+}
+
+/// Returns the directory relative paths declared in a file are resolved against
+fn base_dir(path: &Path) -> PathBuf {
+    path.parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+/// Replaces every `!include path` node with the content of the referenced file
+///
+/// YAML files (`.yaml` / `.yml`) are parsed and may themselves use `extends` and
+/// `!include`; any other file is included as a plain string, which is handy for prompts.
+fn resolve_includes(
+    value: Value,
+    dir: &Path,
+    stack: &mut Vec<PathBuf>,
+) -> Result<Value, Box<dyn Error>> {
+    match value {
+        Value::Tagged(tagged) if tagged.tag == INCLUDE_TAG => {
+            let Value::String(relative) = tagged.value else {
+                return Err("!include expects a file path".into());
+            };
+            let path = dir.join(relative);
+            let is_yaml = matches!(
+                path.extension().and_then(|ext| ext.to_str()),
+                Some("yaml") | Some("yml")
+            );
+            if is_yaml {
+                load_file(&path, stack)
+            } else {
+                let canonical = enter(&path, stack)?;
+                let content = fs::read_to_string(&path)
+                    .map_err(|e| format!("cannot read '{}': {}", path.display(), e))?;
+                stack.retain(|p| p != &canonical);
+                Ok(Value::String(content))
+            }
+        }
+        Value::Mapping(map) => {
+            let mut resolved = Mapping::new();
+            for (key, item) in map {
+                resolved.insert(key, resolve_includes(item, dir, stack)?);
+            }
+            Ok(Value::Mapping(resolved))
+        }
+        Value::Sequence(items) => Ok(Value::Sequence(
+            items
+                .into_iter()
+                .map(|item| resolve_includes(item, dir, stack))
+                .collect::<Result<_, _>>()?,
+        )),
+        other => Ok(other),
+    }
+}
+
+/// Merges an overriding YAML value into a base value
+///
+/// Override rules:
+/// * maps are merged key by key, recursively; a `null` value removes the inherited key
+/// * `modules` and `workflow.parallel` are merged by item `name`: an item with the same
+///   name as an inherited one is merged into it, other items are appended
+/// * `context` items are appended to the inherited ones
+/// * any other list (e.g. `workflow.steps`) and any scalar replaces the inherited value
+///
+/// # Arguments
+///
+/// * `base` - The inherited value
+/// * `overlay` - The overriding value
+/// * `path` - Dotted path of the values being merged, empty at the root
+fn merge(base: Value, overlay: Value, path: &str) -> Value {
+    match (base, overlay) {
+        (Value::Mapping(mut base), Value::Mapping(overlay)) => {
+            for (key, item) in overlay {
+                if item.is_null() {
+                    base.remove(&key);
+                    continue;
+                }
+                let child_path = match key.as_str() {
+                    Some(name) if path.is_empty() => name.to_string(),
+                    Some(name) => format!("{}.{}", path, name),
+                    None => path.to_string(),
+                };
+                let merged = match base.remove(&key) {
+                    Some(inherited) => merge(inherited, item, &child_path),
+                    None => item,
+                };
+                base.insert(key, merged);
+            }
+            Value::Mapping(base)
+        }
+        (Value::Sequence(mut base), Value::Sequence(overlay)) if MERGED_BY_NAME.contains(&path) => {
+            for item in overlay {
+                let name = item.get("name").cloned();
+                match base
+                    .iter()
+                    .position(|inherited| name.is_some() && inherited.get("name") == name.as_ref())
+                {
+                    Some(index) => {
+                        let inherited = base.remove(index);
+                        base.insert(index, merge(inherited, item, path));
+                    }
+                    None => base.push(item),
+                }
+            }
+            Value::Sequence(base)
+        }
+        (Value::Sequence(mut base), Value::Sequence(overlay)) if APPENDED.contains(&path) => {
+            base.extend(overlay);
+            Value::Sequence(base)
+        }
+        (_, overlay) => overlay,
+    }
+}
@@ -0,0 +1,1362 @@
+use super::apply_inputs;
+use super::parser::load_task_value;
+use super::{
+    AgentConfig, CompactionStrategy, GenerationConfig, LimitPolicy, LlmSettings, MockMode,
+    SinkDestination, TaskConfig,
+};
+use crate::agents::AgentOutcome;
+use crate::core::guard::GuardExpr;
+use crate::core::{build_globset, COMPLETED_ROLE, CONTEXT_KINDS, START_ROLE};
+use crate::llm::{
+    build_http_client, build_validator, parse_size, unsupported_generation_options,
+    SUPPORTED_PROVIDERS,
+};
+use crate::modules::AVAILABLE_MODULES;
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+/// Severity of a problem found while validating a task configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The task will fail or misbehave at runtime
+    Error,
+    /// The task can run but probably not as intended
+    Warning,
+}
+
+/// A single problem found in a task configuration
+#[derive(Debug, Clone)]
+pub struct ValidationIssue {
+    /// How serious the problem is
+    pub severity: Severity,
+    /// 1-based line number in the YAML file, when it could be located
+    pub line: Option<usize>,
+    /// Human readable description of the problem
+    pub message: String,
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        match self.line {
+            Some(line) => write!(f, "{} (line {}): {}", severity, line, self.message),
+            None => write!(f, "{}: {}", severity, self.message),
+        }
+    }
+}
+
+/// Line lookup helper over the raw YAML source
+///
+/// serde_yaml does not keep positions once a document is deserialized, so problems
+/// are located by scanning the source for the keys that produced them.
+struct SourceMap<'a> {
+    /// Lines of the YAML source
+    lines: Vec<&'a str>,
+}
+
+impl<'a> SourceMap<'a> {
+    fn new(source: &'a str) -> Self {
+        Self {
+            lines: source.lines().collect(),
+        }
+    }
+
+    /// Returns the key of a line, ignoring indentation and list markers
+    fn key_of(line: &str) -> Option<&str> {
+        let trimmed = line.trim_start().trim_start_matches("- ").trim_start();
+        if trimmed.starts_with('#') {
+            return None;
+        }
+        trimmed.split_once(':').map(|(key, _)| key.trim())
+    }
+
+    /// Returns the 0-based index of a top-level section such as `workflow:`
+    fn section_start(&self, section: &str) -> Option<usize> {
+        self.lines.iter().position(|line| {
+            !line.starts_with(char::is_whitespace) && Self::key_of(line) == Some(section)
+        })
+    }
+
+    /// Returns the 0-based index of the line following the end of a top-level section
+    fn section_end(&self, start: usize) -> usize {
+        self.lines
+            .iter()
+            .enumerate()
+            .skip(start + 1)
+            .find(|(_, line)| {
+                !line.is_empty()
+                    && !line.starts_with(char::is_whitespace)
+                    && !line.starts_with('-')
+                    && !line.starts_with('#')
+            })
+            .map(|(i, _)| i)
+            .unwrap_or(self.lines.len())
+    }
+
+    /// Returns the 0-based indices of all lines using `key` inside a top-level section
+    fn key_lines(&self, section: &str, key: &str) -> Vec<usize> {
+        let Some(start) = self.section_start(section) else {
+            return vec![];
+        };
+        let end = self.section_end(start);
+        (start + 1..end)
+            .filter(|&i| Self::key_of(self.lines[i]) == Some(key))
+            .collect()
+    }
+
+    /// Returns the 1-based line of the n-th `key` inside a top-level section
+    fn nth_key(&self, section: &str, key: &str, n: usize) -> Option<usize> {
+        self.key_lines(section, key).get(n).map(|i| i + 1)
+    }
+
+    /// Returns the 1-based line of `key` inside the n-th list item keyed by `item_key`
+    fn item_key(&self, section: &str, item_key: &str, n: usize, key: &str) -> Option<usize> {
+        let items = self.key_lines(section, item_key);
+        let start = *items.get(n)?;
+        let end = items
+            .get(n + 1)
+            .copied()
+            .unwrap_or_else(|| self.section_end(self.section_start(section).unwrap_or(0)));
+        (start..end)
+            .find(|&i| Self::key_of(self.lines[i]) == Some(key))
+            .map(|i| i + 1)
+    }
+
+    /// Returns the 1-based line of a top-level key
+    fn top_level(&self, key: &str) -> Option<usize> {
+        self.section_start(key).map(|i| i + 1)
+    }
+}
+
+/// Collects validation issues for a task configuration
+struct Validator<'a> {
+    config: &'a TaskConfig,
+    source: SourceMap<'a>,
+    issues: Vec<ValidationIssue>,
+}
+
+impl<'a> Validator<'a> {
+    fn error(&mut self, line: Option<usize>, message: String) {
+        self.issues.push(ValidationIssue {
+            severity: Severity::Error,
+            line,
+            message,
+        });
+    }
+
+    fn warning(&mut self, line: Option<usize>, message: String) {
+        self.issues.push(ValidationIssue {
+            severity: Severity::Warning,
+            line,
+            message,
+        });
+    }
+
+    /// Returns the conditions a role can produce, or None for unknown roles
+    fn role_conditions(&self, role: &str) -> Option<Vec<String>> {
+        if self.is_parallel_group(role) {
+            return Some(vec![
+                "approved".to_string(),
+                "revision_requested".to_string(),
+            ]);
+        }
+        if let Some(conditions) = AgentOutcome::conditions_for_role(role) {
+            return Some(conditions.iter().map(|c| c.to_string()).collect());
+        }
+        self.config.agents.custom.get(role).map(|agent| {
+            let mut conditions: Vec<String> = vec![];
+            for rule in &agent.protocol {
+                if !conditions.contains(&rule.condition) {
+                    conditions.push(rule.condition.clone());
+                }
+            }
+            conditions
+        })
+    }
+
+    /// Returns whether a name designates a parallel group
+    fn is_parallel_group(&self, name: &str) -> bool {
+        self.config
+            .workflow
+            .parallel
+            .iter()
+            .any(|group| group.name == name)
+    }
+
+    /// Returns whether a name designates a role backed by an agent
+    fn is_agent_role(&self, name: &str) -> bool {
+        AgentOutcome::conditions_for_role(name).is_some()
+            || self.config.agents.custom.contains_key(name)
+    }
+
+    fn check_parallel(&mut self) {
+        let mut names = HashSet::new();
+        for (i, group) in self.config.workflow.parallel.iter().enumerate() {
+            let line = self.source.nth_key("workflow", "name", i);
+            if !names.insert(group.name.as_str()) {
+                self.error(
+                    line,
+                    format!("parallel group '{}' is declared more than once", group.name),
+                );
+            }
+            if self.is_agent_role(&group.name) || group.name == COMPLETED_ROLE {
+                self.error(
+                    line,
+                    format!(
+                        "parallel group '{}' has the same name as a role",
+                        group.name
+                    ),
+                );
+            }
+            match group.branches.len() {
+                0 => self.error(
+                    line,
+                    format!("parallel group '{}' has no branches", group.name),
+                ),
+                1 => self.warning(
+                    line,
+                    format!(
+                        "parallel group '{}' has a single branch; use a plain step instead",
+                        group.name
+                    ),
+                ),
+                _ => {}
+            }
+            let mut branches = HashSet::new();
+            for branch in &group.branches {
+                if !branches.insert(branch.as_str()) {
+                    self.error(
+                        line,
+                        format!(
+                            "parallel group '{}' lists branch '{}' more than once",
+                            group.name, branch
+                        ),
+                    );
+                } else if self.is_parallel_group(branch) {
+                    self.error(
+                        line,
+                        format!(
+                            "parallel group '{}' cannot nest group '{}' as a branch",
+                            group.name, branch
+                        ),
+                    );
+                } else if !self.is_agent_role(branch) {
+                    self.error(
+                        line,
+                        format!(
+                            "parallel group '{}' has unknown branch role '{}'",
+                            group.name, branch
+                        ),
+                    );
+                }
+            }
+        }
+    }
+
+    fn check_custom_roles(&mut self) {
+        for (role, agent) in &self.config.agents.custom {
+            let line = self.source.nth_key("agents", role, 0);
+            if agent.protocol.is_empty() {
+                self.error(
+                    line,
+                    format!(
+                        "role '{}' has no response protocol; declare at least one prefix and condition",
+                        role
+                    ),
+                );
+            }
+            let mut prefixes = HashSet::new();
+            for rule in &agent.protocol {
+                if rule.prefix.trim().is_empty() {
+                    self.error(
+                        line,
+                        format!("role '{}' has a protocol rule with an empty prefix", role),
+                    );
+                } else if !prefixes.insert(rule.prefix.to_lowercase()) {
+                    self.warning(
+                        line,
+                        format!(
+                            "role '{}' declares prefix '{}' more than once; only the first rule is used",
+                            role, rule.prefix
+                        ),
+                    );
+                }
+            }
+        }
+    }
+
+    fn check_parameters(&mut self) {
+        let parameters = &self.config.parameters;
+        match &parameters.llm_provider {
+            None => self.error(
+                self.source.top_level("parameters"),
+                "parameters.llm_provider is required".to_string(),
+            ),
+            Some(providers) => {
+                for provider in providers.values() {
+                    if !SUPPORTED_PROVIDERS.contains(&provider) {
+                        self.error(
+                            self.source.nth_key("parameters", "llm_provider", 0),
+                            format!(
+                                "unknown LLM provider '{}' (supported: {})",
+                                provider,
+                                SUPPORTED_PROVIDERS.join(", ")
+                            ),
+                        );
+                    }
+                }
+            }
+        }
+        match &parameters.llm_model {
+            None => self.error(
+                self.source.top_level("parameters"),
+                "parameters.llm_model is required".to_string(),
+            ),
+            Some(models) => {
+                let providers = parameters
+                    .llm_provider
+                    .as_ref()
+                    .map_or(1, |providers| providers.values().len());
+                let models = models.values().len();
+                if providers > 1 && models > 1 && providers != models {
+                    self.error(
+                        self.source.nth_key("parameters", "llm_model", 0),
+                        format!(
+                            "parameters.llm_provider lists {} providers but llm_model lists {} models; \
+                             use a single value or lists of the same length",
+                            providers, models
+                        ),
+                    );
+                }
+            }
+        }
+        let generation_line = self.source.nth_key("parameters", "generation", 0);
+        self.check_generation(
+            "parameters.generation",
+            &parameters.generation,
+            generation_line,
+        );
+        self.check_generation_support(
+            "parameters.generation",
+            &parameters.llm_settings(),
+            generation_line,
+        );
+        self.check_endpoint(
+            "parameters",
+            &parameters.llm_settings(),
+            self.source
+                .nth_key("parameters", "llm_endpoint", 0)
+                .or(self.source.top_level("parameters")),
+        );
+
+        for (role, agent) in self.agents() {
+            let line = self.source.nth_key("agents", role, 0);
+            if let Some(provider) = agent.llm_provider.as_deref() {
+                if !SUPPORTED_PROVIDERS.contains(&provider) {
+                    self.error(
+                        line,
+                        format!(
+                            "{}: unknown LLM provider '{}' (supported: {})",
+                            role,
+                            provider,
+                            SUPPORTED_PROVIDERS.join(", ")
+                        ),
+                    );
+                } else if agent.llm_model.is_none() && parameters.provider() != Some(provider) {
+                    self.warning(
+                        line,
+                        format!(
+                            "{}: llm_provider '{}' is set without llm_model; the task model is used",
+                            role, provider
+                        ),
+                    );
+                }
+            }
+            self.check_generation(&format!("{}.generation", role), &agent.generation, line);
+            if agent.generation != GenerationConfig::default() || agent.llm_provider.is_some() {
+                self.check_generation_support(
+                    &format!("{}.generation", role),
+                    &parameters.llm_settings_for(agent),
+                    line,
+                );
+            }
+            if agent.llm_endpoint.is_some() || agent.llm_provider.is_some() {
+                self.check_endpoint(role, &parameters.llm_settings_for(agent), line);
+            }
+        }
+    }
+
+    fn check_endpoint(&mut self, name: &str, settings: &LlmSettings, line: Option<usize>) {
+        let endpoint = &settings.endpoint;
+        if let Some(base_url) = &endpoint.base_url {
+            if let Err(e) = reqwest::Url::parse(base_url) {
+                self.error(
+                    line,
+                    format!(
+                        "{}: invalid llm_endpoint.base_url '{}': {}",
+                        name, base_url, e
+                    ),
+                );
+            }
+        }
+        for (header, value) in &endpoint.headers {
+            if reqwest::header::HeaderName::from_bytes(header.as_bytes()).is_err()
+                || reqwest::header::HeaderValue::from_str(value).is_err()
+            {
+                self.error(
+                    line,
+                    format!("{}: invalid llm_endpoint header '{}'", name, header),
+                );
+            }
+        }
+        if let Some(header) = &endpoint.auth_header {
+            if reqwest::header::HeaderName::from_bytes(header.as_bytes()).is_err() {
+                self.error(
+                    line,
+                    format!("{}: invalid llm_endpoint.auth_header '{}'", name, header),
+                );
+            }
+        }
+        if settings.provider.as_deref() == Some("openai_compatible")
+            && endpoint.base_url.is_none()
+            && std::env::var_os("OPENAI_COMPATIBLE_BASE_URL").is_none()
+        {
+            self.warning(
+                line,
+                format!(
+                    "{}: provider 'openai_compatible' has no llm_endpoint.base_url and \
+                     OPENAI_COMPATIBLE_BASE_URL is not set",
+                    name
+                ),
+            );
+        }
+        if settings.provider.as_deref() == Some("azure_openai")
+            && endpoint.base_url.is_none()
+            && std::env::var_os("AZURE_OPENAI_BASE_URL").is_none()
+        {
+            self.warning(
+                line,
+                format!(
+                    "{}: provider 'azure_openai' has no llm_endpoint.base_url and \
+                     AZURE_OPENAI_BASE_URL is not set",
+                    name
+                ),
+            );
+        }
+    }
+
+    fn check_generation(&mut self, name: &str, generation: &GenerationConfig, line: Option<usize>) {
+        if let Some(temperature) = generation.temperature {
+            if !(0.0..=2.0).contains(&temperature) {
+                self.error(
+                    line,
+                    format!("{}: temperature must be between 0 and 2", name),
+                );
+            }
+        }
+        if let Some(top_p) = generation.top_p {
+            if !(0.0..=1.0).contains(&top_p) {
+                self.error(line, format!("{}: top_p must be between 0 and 1", name));
+            }
+        }
+        if generation.stop.iter().any(String::is_empty) {
+            self.error(line, format!("{}: stop sequences must not be empty", name));
+        }
+        if generation.max_tokens == Some(0) {
+            self.error(line, format!("{}: max_tokens must be positive", name));
+        }
+        if generation.context_window == Some(0) {
+            self.error(line, format!("{}: context_window must be positive", name));
+        }
+        if let (Some(max_tokens), Some(context_window)) =
+            (generation.max_tokens, generation.context_window)
+        {
+            if max_tokens as usize >= context_window {
+                self.error(
+                    line,
+                    format!("{}: max_tokens must be lower than context_window", name),
+                );
+            }
+        }
+    }
+
+    fn check_generation_support(
+        &mut self,
+        name: &str,
+        settings: &LlmSettings,
+        line: Option<usize>,
+    ) {
+        let providers = settings
+            .provider
+            .iter()
+            .chain(settings.fallbacks.iter().map(|(provider, _)| provider));
+        let mut seen = HashSet::new();
+        for provider in providers {
+            if !seen.insert(provider) {
+                continue;
+            }
+            for option in unsupported_generation_options(provider, &settings.generation) {
+                self.warning(
+                    line,
+                    format!(
+                        "{}: {} is not supported by provider '{}' and is ignored",
+                        name, option, provider
+                    ),
+                );
+            }
+        }
+    }
+
+    fn check_workflow(&mut self) {
+        let steps = &self.config.workflow.steps;
+        if steps.is_empty() {
+            self.error(
+                self.source.top_level("workflow"),
+                "workflow has no steps".to_string(),
+            );
+            return;
+        }
+
+        let mut seen = HashSet::new();
+        for (i, step) in steps.iter().enumerate() {
+            let from_line = self.source.item_key("workflow", "from", i, "from");
+            let to_line = self.source.item_key("workflow", "from", i, "to");
+            let condition_line = self.source.item_key("workflow", "from", i, "condition");
+
+            match self.role_conditions(&step.from) {
+                None => self.error(
+                    from_line,
+                    format!("step {}: unknown role '{}' in 'from'", i + 1, step.from),
+                ),
+                Some(conditions) if !conditions.contains(&step.condition) => self.error(
+                    condition_line,
+                    format!(
+                        "step {}: role '{}' never produces condition '{}' (it produces: {})",
+                        i + 1,
+                        step.from,
+                        step.condition,
+                        conditions.join(", ")
+                    ),
+                ),
+                Some(_) => {}
+            }
+
+            if step.to != COMPLETED_ROLE && self.role_conditions(&step.to).is_none() {
+                self.error(
+                    to_line,
+                    format!("step {}: unknown role '{}' in 'to'", i + 1, step.to),
+                );
+            }
+
+            if let Some(guard) = &step.guard {
+                if let Err(e) = GuardExpr::parse(guard) {
+                    self.error(
+                        self.source.item_key("workflow", "from", i, "guard"),
+                        format!("step {}: invalid guard '{}': {}", i + 1, guard, e),
+                    );
+                }
+            }
+
+            let key = (step.from.clone(), step.condition.clone());
+            if seen.contains(&key) {
+                self.warning(
+                    from_line,
+                    format!(
+                        "step {}: transition from '{}' on '{}' is shadowed by an earlier step and never used",
+                        i + 1,
+                        step.from,
+                        step.condition
+                    ),
+                );
+            } else if step.guard.is_none() {
+                seen.insert(key);
+            }
+        }
+
+        let reachable = self.reachable_roles();
+        let completing = self.roles_reaching_completion();
+
+        if !steps.iter().any(|step| step.from == START_ROLE) {
+            self.error(
+                self.source.top_level("workflow"),
+                format!(
+                    "workflow has no step leaving the start role '{}'",
+                    START_ROLE
+                ),
+            );
+        }
+
+        for (i, step) in steps.iter().enumerate() {
+            if !reachable.contains(&step.from) {
+                self.warning(
+                    self.source.item_key("workflow", "from", i, "from"),
+                    format!(
+                        "step {}: role '{}' is unreachable from '{}'",
+                        i + 1,
+                        step.from,
+                        START_ROLE
+                    ),
+                );
+            }
+        }
+
+        let mut reported = HashSet::new();
+        for role in &reachable {
+            if role == COMPLETED_ROLE || !reported.insert(role.clone()) {
+                continue;
+            }
+            let Some(conditions) = self.role_conditions(role) else {
+                continue;
+            };
+            let line = steps
+                .iter()
+                .position(|step| &step.to == role || &step.from == role)
+                .and_then(|i| self.source.item_key("workflow", "from", i, "from"));
+
+            if !completing.contains(role) {
+                self.error(
+                    line,
+                    format!("role '{}' has no path to '{}'", role, COMPLETED_ROLE),
+                );
+            }
+
+            for condition in conditions {
+                let handlers: Vec<_> = steps
+                    .iter()
+                    .filter(|step| &step.from == role && step.condition == condition)
+                    .collect();
+                if handlers.is_empty() {
+                    self.error(
+                        line,
+                        format!(
+                            "dead end: role '{}' can produce '{}' but no step handles it",
+                            role, condition
+                        ),
+                    );
+                } else if handlers.iter().all(|step| step.guard.is_some()) {
+                    self.warning(
+                        line,
+                        format!(
+                            "possible dead end: every step handling '{}' from '{}' is guarded, add an unguarded fallback",
+                            condition, role
+                        ),
+                    );
+                }
+            }
+        }
+    }
+
+    /// Returns every role reachable from the start role, including the start role
+    fn reachable_roles(&self) -> Vec<String> {
+        let mut visited = vec![START_ROLE.to_string()];
+        let mut queue = VecDeque::from([START_ROLE.to_string()]);
+        while let Some(role) = queue.pop_front() {
+            for step in self.config.workflow.steps.iter().filter(|s| s.from == role) {
+                if !visited.contains(&step.to) {
+                    visited.push(step.to.clone());
+                    queue.push_back(step.to.clone());
+                }
+            }
+        }
+        visited
+    }
+
+    /// Returns every role from which the completed state can be reached
+    fn roles_reaching_completion(&self) -> HashSet<String> {
+        let mut visited = HashSet::from([COMPLETED_ROLE.to_string()]);
+        let mut queue = VecDeque::from([COMPLETED_ROLE.to_string()]);
+        while let Some(role) = queue.pop_front() {
+            for step in self.config.workflow.steps.iter().filter(|s| s.to == role) {
+                if visited.insert(step.from.clone()) {
+                    queue.push_back(step.from.clone());
+                }
+            }
+        }
+        visited
+    }
+
+    fn check_modules(&mut self) {
+        let mut seen = HashSet::new();
+        for (i, module) in self.config.modules.iter().enumerate() {
+            let line = self.source.nth_key("modules", "name", i);
+            if !AVAILABLE_MODULES.contains(&module.name.as_str()) {
+                self.error(
+                    line,
+                    format!(
+                        "unknown module '{}' (available: {})",
+                        module.name,
+                        AVAILABLE_MODULES.join(", ")
+                    ),
+                );
+            }
+            if !seen.insert(module.name.clone()) {
+                self.warning(line, format!("module '{}' is declared twice", module.name));
+            }
+            if module.name == "sh" {
+                match &module.config {
+                    None => self.error(
+                        line,
+                        "module 'sh' has no config and will not be loaded; declare config.allowed_commands".to_string(),
+                    ),
+                    Some(conf) if conf.get("allowed_commands").is_none() => self.warning(
+                        line,
+                        "module 'sh' has no allowed_commands, every command will be allowed"
+                            .to_string(),
+                    ),
+                    Some(_) => {}
+                }
+            }
+        }
+    }
+
+    /// Returns every configured role, built-in roles first
+    fn agents(&self) -> Vec<(&'a str, &'a AgentConfig)> {
+        let agents = &self.config.agents;
+        let mut all = vec![
+            ("proposer", &agents.proposer),
+            ("reviewer", &agents.reviewer),
+            ("validator", &agents.validator),
+            ("formatter", &agents.formatter),
+        ];
+        all.extend(
+            agents
+                .custom
+                .iter()
+                .map(|(role, agent)| (role.as_str(), agent)),
+        );
+        all
+    }
+
+    fn check_schemas(&mut self) {
+        let mut problems = vec![];
+        for (role, agent) in self.agents() {
+            let Some(schema) = &agent.schema else {
+                continue;
+            };
+            let line = self.source.nth_key("agents", role, 0);
+            let content = match schema.strip_prefix("file://") {
+                Some(path) => match fs::read_to_string(path) {
+                    Ok(content) => content,
+                    Err(e) => {
+                        problems.push((
+                            line,
+                            format!("{}: cannot read schema '{}': {}", role, path, e),
+                        ));
+                        continue;
+                    }
+                },
+                None => schema.clone(),
+            };
+            if let Err(e) = build_validator(&content) {
+                problems.push((line, format!("{}: invalid JSON schema: {}", role, e)));
+            }
+        }
+        for (line, message) in problems {
+            self.error(line, message);
+        }
+    }
+
+    fn check_limits(&mut self) {
+        let limits = &self.config.parameters.limits;
+        let line = self
+            .source
+            .nth_key("parameters", "limits", 0)
+            .or(self.source.top_level("parameters"));
+        if let Some(duration) = &limits.max_duration {
+            if let Err(e) = humantime::parse_duration(duration) {
+                self.error(
+                    line,
+                    format!("invalid limits.max_duration '{}': {}", duration, e),
+                );
+            }
+        }
+        let uses_formatter = self
+            .config
+            .workflow
+            .steps
+            .iter()
+            .any(|step| step.to == "formatter");
+        if limits.on_limit == LimitPolicy::Format && !uses_formatter {
+            self.error(
+                line,
+                "limits.on_limit 'format' requires the formatter in the workflow".to_string(),
+            );
+        }
+        let unbounded = limits.max_revisions.is_none()
+            && limits.max_llm_calls.is_none()
+            && limits.max_duration.is_none();
+        let loops = self
+            .config
+            .workflow
+            .steps
+            .iter()
+            .any(|step| step.condition == "revision_requested");
+        if loops && unbounded && self.config.interval.is_none() {
+            self.warning(
+                self.source.top_level("workflow"),
+                "the workflow has revision loops but no limits.max_revisions, max_llm_calls or max_duration; a task may loop forever".to_string(),
+            );
+        }
+    }
+
+    fn check_llm_retry(&mut self) {
+        let retry = &self.config.parameters.llm_retry;
+        let line = self
+            .source
+            .nth_key("parameters", "llm_retry", 0)
+            .or(self.source.top_level("parameters"));
+        if retry.max_attempts == Some(0) {
+            self.error(line, "llm_retry.max_attempts must be positive".to_string());
+        }
+        for (name, duration) in [
+            ("timeout", &retry.timeout),
+            ("initial_backoff", &retry.initial_backoff),
+            ("max_backoff", &retry.max_backoff),
+        ] {
+            if let Some(duration) = duration {
+                if let Err(e) = humantime::parse_duration(duration) {
+                    self.error(
+                        line,
+                        format!("invalid llm_retry.{} '{}': {}", name, duration, e),
+                    );
+                }
+            }
+        }
+    }
+
+    fn check_pricing(&mut self) {
+        let line = self
+            .source
+            .nth_key("parameters", "pricing", 0)
+            .or(self.source.top_level("parameters"));
+        for (model, price) in &self.config.parameters.pricing {
+            if price.input_per_mtok < 0.0 || price.output_per_mtok < 0.0 {
+                self.error(
+                    line,
+                    format!("pricing.{}: prices must not be negative", model),
+                );
+            }
+        }
+    }
+
+    fn check_llm_cache(&mut self) {
+        let cache = &self.config.parameters.llm_cache;
+        let line = self
+            .source
+            .nth_key("parameters", "llm_cache", 0)
+            .or(self.source.top_level("parameters"));
+        if let Some(ttl) = &cache.ttl {
+            if let Err(e) = humantime::parse_duration(ttl) {
+                self.error(line, format!("invalid llm_cache.ttl '{}': {}", ttl, e));
+            }
+        }
+        if let Some(max_size) = &cache.max_size {
+            if let Err(e) = parse_size(max_size) {
+                self.error(line, format!("llm_cache.max_size: {}", e));
+            }
+        }
+    }
+
+    fn check_http(&mut self) {
+        let line = self
+            .source
+            .nth_key("parameters", "http", 0)
+            .or(self.source.top_level("parameters"));
+        if let Err(e) = build_http_client(&self.config.parameters.http) {
+            self.error(line, e.to_string());
+        }
+    }
+
+    fn check_compaction(&mut self) {
+        let compaction = &self.config.parameters.compaction;
+        let line = self
+            .source
+            .nth_key("parameters", "compaction", 0)
+            .or(self.source.top_level("parameters"));
+        if let Some(threshold) = compaction.threshold {
+            if !(threshold > 0.0 && threshold <= 1.0) {
+                self.error(
+                    line,
+                    format!(
+                        "compaction.threshold must be above 0 and at most 1, got {}",
+                        threshold
+                    ),
+                );
+            }
+        }
+        let tuned = compaction.threshold.is_some() || compaction.keep_recent.is_some();
+        if compaction.strategy == CompactionStrategy::Truncate && tuned {
+            self.warning(
+                line,
+                "compaction.threshold and keep_recent only apply to the 'summarize' strategy"
+                    .to_string(),
+            );
+        }
+    }
+
+    fn check_mock(&mut self) {
+        let parameters = &self.config.parameters;
+        let line = self
+            .source
+            .nth_key("parameters", "mock", 0)
+            .or(self.source.top_level("parameters"));
+        let mut files: Vec<(String, String)> = Vec::new();
+        for (provider, model) in parameters.llm_chain() {
+            if provider == "mock" {
+                files.push(("parameters".to_string(), model));
+            }
+        }
+        for (role, agent) in self.agents() {
+            let settings = parameters.llm_settings_for(agent);
+            if settings.provider.as_deref() == Some("mock") {
+                if let Some(model) = settings.model {
+                    files.push((role.to_string(), model));
+                }
+            }
+        }
+        if files.is_empty() {
+            return;
+        }
+
+        let mock = &parameters.mock;
+        match mock.mode {
+            MockMode::Record => match (mock.provider.as_deref(), &mock.model) {
+                (Some(provider), Some(_))
+                    if provider != "mock" && SUPPORTED_PROVIDERS.contains(&provider) => {}
+                (Some(provider), Some(_)) => self.error(
+                    line,
+                    format!("mock.provider: cannot record provider '{}'", provider),
+                ),
+                _ => self.error(
+                    line,
+                    "mock.mode 'record' requires mock.provider and mock.model".to_string(),
+                ),
+            },
+            MockMode::Script | MockMode::Replay => {
+                let kind = if mock.mode == MockMode::Script {
+                    "script"
+                } else {
+                    "cassette"
+                };
+                for (name, file) in files {
+                    if !Path::new(&file).exists() {
+                        self.warning(
+                            line,
+                            format!("{}: mock {} '{}' not found", name, kind, file),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    fn check_embedder(&mut self) {
+        let Some(embedder) = &self.config.parameters.embedder else {
+            return;
+        };
+        if let Some(provider) = embedder.provider.as_deref() {
+            if provider != "openai" && provider != "fake" {
+                self.error(
+                    self.source
+                        .nth_key("parameters", "embedder", 0)
+                        .or(self.source.top_level("parameters")),
+                    format!(
+                        "unknown embedder provider '{}' (supported: openai, fake)",
+                        provider
+                    ),
+                );
+            }
+        }
+    }
+
+    fn check_interval(&mut self) {
+        if let Some(interval) = &self.config.interval {
+            if let Err(e) = humantime::parse_duration(interval) {
+                self.error(
+                    self.source.top_level("interval"),
+                    format!("invalid interval '{}': {}", interval, e),
+                );
+            }
+        }
+    }
+
+    fn check_context(&mut self) {
+        for (i, item) in self.config.context.iter().enumerate() {
+            let line = self.source.nth_key("context", "kind", i);
+            let problem = match item.kind.as_str() {
+                "file" => match &item.path {
+                    None => {
+                        self.error(line, "context item of kind 'file' has no path".to_string());
+                        None
+                    }
+                    Some(path) if !Path::new(path).is_file() => {
+                        Some(format!("context file '{}' does not exist", path))
+                    }
+                    Some(_) => None,
+                },
+                "directory" => match &item.path {
+                    None => Some("context item of kind 'directory' has no path".to_string()),
+                    Some(path) if !Path::new(path).is_dir() => {
+                        Some(format!("context directory '{}' does not exist", path))
+                    }
+                    Some(_) => None,
+                },
+                "glob" => match &item.path {
+                    None => {
+                        Some("context item of kind 'glob' has no pattern in 'path'".to_string())
+                    }
+                    Some(pattern) => build_globset(std::slice::from_ref(pattern))
+                        .err()
+                        .map(|e| format!("context glob: {}", e)),
+                },
+                "url" => match item.url.as_deref().map(url::Url::parse) {
+                    None => Some("context item of kind 'url' has no url".to_string()),
+                    Some(Err(e)) => Some(format!("context url is invalid: {}", e)),
+                    Some(Ok(_)) => None,
+                },
+                "command" if item.command.is_none() => {
+                    Some("context item of kind 'command' has no command".to_string())
+                }
+                "json" | "yaml" => match &item.path {
+                    None => Some(format!("context item of kind '{}' has no path", item.kind)),
+                    Some(path) if !Path::new(path).is_file() => {
+                        Some(format!("context file '{}' does not exist", path))
+                    }
+                    Some(_) => None,
+                },
+                "text" if item.content.is_none() => {
+                    self.warning(
+                        line,
+                        "context item of kind 'text' has no content".to_string(),
+                    );
+                    None
+                }
+                kind if !CONTEXT_KINDS.contains(&kind) => {
+                    self.error(
+                        line,
+                        format!(
+                            "unknown context kind '{}' (supported: {})",
+                            kind,
+                            CONTEXT_KINDS.join(", ")
+                        ),
+                    );
+                    None
+                }
+                _ => None,
+            };
+
+            let patterns = build_globset(&item.include).and(build_globset(&item.exclude));
+            if let Err(e) = patterns {
+                self.error(line, format!("context {}: {}", item.kind, e));
+            }
+
+            match problem {
+                Some(message) if item.optional => {
+                    self.warning(line, format!("{} (optional, skipped)", message))
+                }
+                Some(message) => self.error(line, message),
+                None => {}
+            }
+        }
+    }
+
+    fn check_output(&mut self) {
+        let uses_formatter = self
+            .config
+            .workflow
+            .steps
+            .iter()
+            .any(|step| step.to == "formatter");
+        let sinks = &self.config.output.sinks;
+        if uses_formatter && sinks.is_empty() {
+            self.error(
+                self.source.top_level("output"),
+                "output is required when the workflow uses the formatter".to_string(),
+            );
+        }
+
+        let mut names = HashSet::new();
+        for (i, sink) in sinks.iter().enumerate() {
+            let line = self
+                .source
+                .nth_key("output", "format", i)
+                .or(self.source.top_level("output"));
+            if !names.insert(sink.label()) {
+                self.warning(
+                    line,
+                    format!(
+                        "output '{}' is declared more than once; give the sinks distinct names",
+                        sink.label()
+                    ),
+                );
+            }
+            match sink.destination {
+                SinkDestination::File
+                    if sink.file.as_deref().unwrap_or_default().trim().is_empty() =>
+                {
+                    self.error(
+                        line,
+                        format!(
+                            "output '{}': the file destination requires a file",
+                            sink.label()
+                        ),
+                    )
+                }
+                SinkDestination::Webhook => match sink.url.as_deref().map(url::Url::parse) {
+                    None => self.error(
+                        line,
+                        format!(
+                            "output '{}': the webhook destination requires a url",
+                            sink.label()
+                        ),
+                    ),
+                    Some(Err(e)) => self.error(
+                        line,
+                        format!("output '{}': invalid webhook url: {}", sink.label(), e),
+                    ),
+                    Some(Ok(_)) => {}
+                },
+                _ => {}
+            }
+
+            let Some(schema) = &sink.schema else {
+                continue;
+            };
+            let content = match schema.strip_prefix("file://") {
+                Some(path) => match fs::read_to_string(path) {
+                    Ok(content) => content,
+                    Err(e) => {
+                        self.error(
+                            line,
+                            format!(
+                                "output '{}': cannot read schema '{}': {}",
+                                sink.label(),
+                                path,
+                                e
+                            ),
+                        );
+                        continue;
+                    }
+                },
+                None => schema.clone(),
+            };
+            if let Err(e) = build_validator(&content) {
+                self.error(
+                    line,
+                    format!("output '{}': invalid JSON schema: {}", sink.label(), e),
+                );
+            }
+        }
+    }
+}
+
+/// Validates a task configuration file without running it
+///
+/// Loads the YAML file with its bases and includes, substitutes its inputs and statically checks the workflow graph,
+/// roles, conditions, modules, schemas, limits, retry policy, pricing, interval and context sources. No LLM is called.
+///
+/// # Arguments
+/// * `file_path` - Path to the YAML configuration file
+/// * `inputs` - Input values supplied by the caller
+///
+/// # Returns
+/// * `Vec<ValidationIssue>` - Every problem found, sorted by line number
+pub fn validate_task_file(
+    file_path: &str,
+    inputs: &BTreeMap<String, String>,
+) -> Vec<ValidationIssue> {
+    let source = match fs::read_to_string(file_path) {
+        Ok(source) => source,
+        Err(e) => {
+            return vec![ValidationIssue {
+                severity: Severity::Error,
+                line: None,
+                message: format!("cannot read '{}': {}", file_path, e),
+            }]
+        }
+    };
+
+    if let Err(e) = serde_yaml::from_str::<serde_yaml::Value>(&source) {
+        return vec![ValidationIssue {
+            severity: Severity::Error,
+            line: e.location().map(|l| l.line()),
+            message: format!("invalid YAML: {}", e),
+        }];
+    }
+
+    let config = load_task_value(file_path)
+        .and_then(|value| serde_yaml::from_value::<TaskConfig>(value).map_err(|e| e.into()));
+    let config = match config {
+        Ok(config) => config,
+        Err(e) => {
+            return vec![ValidationIssue {
+                severity: Severity::Error,
+                line: None,
+                message: format!("invalid task configuration: {}", e),
+            }]
+        }
+    };
+
+    let config = match apply_inputs(config, inputs) {
+        Ok(config) => config,
+        Err(e) => {
+            return vec![ValidationIssue {
+                severity: Severity::Error,
+                line: SourceMap::new(&source).top_level("inputs"),
+                message: format!("cannot interpolate task configuration: {}", e),
+            }]
+        }
+    };
+
+    let mut validator = Validator {
+        config: &config,
+        source: SourceMap::new(&source),
+        issues: vec![],
+    };
+    validator.check_parameters();
+    validator.check_custom_roles();
+    validator.check_parallel();
+    validator.check_workflow();
+    validator.check_modules();
+    validator.check_schemas();
+    validator.check_limits();
+    validator.check_llm_retry();
+    validator.check_pricing();
+    validator.check_llm_cache();
+    validator.check_compaction();
+    validator.check_http();
+    validator.check_mock();
+    validator.check_embedder();
+    validator.check_interval();
+    validator.check_context();
+    validator.check_output();
+
+    let mut issues = validator.issues;
+    issues.sort_by_key(|issue| issue.line.unwrap_or(0));
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TASK: &str = r#"name: test-task
+description: "Checks the validator"
+version: "1.0"
+
+agents:
+  proposer:
+    system_prompt: "Propose"
+    user_prompt: "Propose"
+  reviewer:
+    system_prompt: "Review"
+    user_prompt: "Review"
+
+workflow:
+  steps:
+    - from: "proposer"
+      to: "reviewer"
+      condition: "proposal_generated"
+    - from: "reviewer"
+      to: "proposer"
+      condition: "revision_requested"
+    - from: "reviewer"
+      to: "completed"
+      condition: "approved"
+
+parameters:
+  llm_provider: "openai"
+  llm_model: "gpt-4o"
+  limits:
+    max_revisions: 3
+"#;
+
+    fn validate(name: &str, source: &str) -> Vec<ValidationIssue> {
+        let path = std::env::temp_dir().join(format!(
+            "kheish-validation-{}-{}.yaml",
+            name,
+            std::process::id()
+        ));
+        fs::write(&path, source).unwrap();
+        let issues = validate_task_file(path.to_str().unwrap(), &BTreeMap::new());
+        let _ = fs::remove_file(&path);
+        issues
+    }
+
+    fn find<'i>(issues: &'i [ValidationIssue], text: &str) -> &'i ValidationIssue {
+        issues
+            .iter()
+            .find(|issue| issue.message.contains(text))
+            .unwrap_or_else(|| panic!("no issue containing '{}' in {:?}", text, issues))
+    }
+
+    #[test]
+    fn valid_task_has_no_issues() {
+        let issues = validate("valid", TASK);
+        assert!(issues.is_empty(), "{:?}", issues);
+    }
+
+    #[test]
+    fn unreachable_roles_are_reported() {
+        let source = TASK.replace(
+            "      condition: \"approved\"\n",
+            "      condition: \"approved\"\n    - from: \"validator\"\n      to: \"completed\"\n      condition: \"validated\"\n",
+        );
+        let issues = validate("unreachable", &source);
+        let issue = find(&issues, "role 'validator' is unreachable from 'proposer'");
+        assert_eq!(issue.severity, Severity::Warning);
+        assert_eq!(issue.line, Some(24));
+    }
+
+    #[test]
+    fn dead_end_steps_are_reported() {
+        let source = TASK.replace(
+            "    - from: \"reviewer\"\n      to: \"proposer\"\n      condition: \"revision_requested\"\n",
+            "",
+        );
+        let issues = validate("dead-end", &source);
+        let issue = find(
+            &issues,
+            "dead end: role 'reviewer' can produce 'revision_requested' but no step handles it",
+        );
+        assert_eq!(issue.severity, Severity::Error);
+        assert_eq!(issue.line, Some(15));
+    }
+
+    #[test]
+    fn unknown_modules_are_reported() {
+        let source = TASK.replace("workflow:\n", "modules:\n  - name: \"nope\"\n\nworkflow:\n");
+        let issues = validate("module", &source);
+        let issue = find(&issues, "unknown module 'nope'");
+        assert_eq!(issue.severity, Severity::Error);
+        assert_eq!(issue.line, Some(14));
+    }
+
+    #[test]
+    fn unknown_roles_are_reported() {
+        let source = TASK.replacen("to: \"reviewer\"", "to: \"auditor\"", 1);
+        let issues = validate("role", &source);
+        let issue = find(&issues, "step 1: unknown role 'auditor' in 'to'");
+        assert_eq!(issue.severity, Severity::Error);
+        assert_eq!(issue.line, Some(16));
+    }
+
+    #[test]
+    fn source_map_locates_keys() {
+        let source = SourceMap::new(TASK);
+        assert_eq!(source.top_level("workflow"), Some(13));
+        assert_eq!(source.nth_key("workflow", "from", 1), Some(18));
+        assert_eq!(
+            source.item_key("workflow", "from", 2, "condition"),
+            Some(23)
+        );
+        assert_eq!(source.nth_key("parameters", "max_revisions", 0), Some(29));
+        assert_eq!(source.nth_key("workflow", "max_revisions", 0), None);
+        assert_eq!(source.top_level("modules"), None);
+    }
+}
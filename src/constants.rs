@@ -41,6 +41,12 @@ pub const VALIDATOR_USER_PROMPT: &str = "Examine the final solution. If it perfe
 /// User prompt template for the formatter to convert content to specified format
 pub const FORMATTER_USER_PROMPT: &str = "You have access to the final solution and the original content. Your role is to convert the solution into the specified output format. Start your response with 'Output:' followed directly by the requested solution or content, without additional comments.";
 
+/// System prompt used for user-defined roles that do not declare their own
+pub const CUSTOM_ROLE_SYSTEM_PROMPT: &str = "You are a specialized assistant taking part in a multi-step workflow. Follow the instructions given for your role precisely and always answer using one of the response prefixes you are given.";
+
+/// User prompt template for user-defined roles that do not declare their own
+pub const CUSTOM_ROLE_USER_PROMPT: &str = "Examine the context and the current proposal, then carry out your role. No greetings and no explanations outside the expected response format.";
+
 /// Maximum number of feedback iterations allowed for the proposer
 pub const MAX_PROPOSER_FEEDBACK_COUNT: usize = 50;
 
@@ -0,0 +1,446 @@
+//! Guard expressions for conditional workflow transitions
+//!
+//! A guard is a small boolean expression attached to a workflow step, e.g.
+//! `revision_count >= 5 || elapsed_secs > 600`. It supports integer comparisons
+//! (`==`, `!=`, `<`, `<=`, `>`, `>=`), `&&`, `||`, `!`, parentheses and the
+//! literals `true` / `false`. A bare variable is true when it is non-zero.
+
+use std::collections::HashMap;
+
+/// Variables available to guard expressions
+pub const GUARD_VARIABLES: &[&str] = &[
+    "revision_count",
+    "retry_count",
+    "module_calls",
+    "elapsed_secs",
+    "proposal_length",
+];
+
+/// Prefix for per-module call counters, e.g. `module_calls.fs`
+const MODULE_CALLS_PREFIX: &str = "module_calls.";
+
+/// Snapshot of the task state a guard is evaluated against
+#[derive(Debug, Clone, Default)]
+pub struct GuardContext {
+    /// Number of revisions requested so far
+    pub revision_count: usize,
+    /// Number of consecutive retries after failures
+    pub retry_count: usize,
+    /// Number of module calls per module name
+    pub module_calls: HashMap<String, usize>,
+    /// Seconds elapsed since the worker started the task
+    pub elapsed_secs: u64,
+    /// Length in characters of the current proposal
+    pub proposal_length: usize,
+}
+
+impl GuardContext {
+    /// Resolves a variable name to its current value
+    ///
+    /// # Arguments
+    /// * `name` - Variable name as written in the guard
+    ///
+    /// # Returns
+    /// * `Option<u64>` - The value, or None if the variable is unknown
+    fn value(&self, name: &str) -> Option<u64> {
+        let value = match name {
+            "revision_count" => self.revision_count as u64,
+            "retry_count" => self.retry_count as u64,
+            "module_calls" => self.module_calls.values().sum::<usize>() as u64,
+            "elapsed_secs" => self.elapsed_secs,
+            "proposal_length" => self.proposal_length as u64,
+            _ => {
+                let module = name.strip_prefix(MODULE_CALLS_PREFIX)?;
+                self.module_calls.get(module).copied().unwrap_or(0) as u64
+            }
+        };
+        Some(value)
+    }
+}
+
+/// Comparison operators supported in guards
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CompareOp {
+    /// `==`
+    Eq,
+    /// `!=`
+    Ne,
+    /// `<`
+    Lt,
+    /// `<=`
+    Le,
+    /// `>`
+    Gt,
+    /// `>=`
+    Ge,
+}
+
+/// Operand of a comparison
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operand {
+    /// Named task state variable
+    Variable(String),
+    /// Integer literal
+    Number(u64),
+}
+
+/// Parsed guard expression
+#[derive(Debug, Clone, PartialEq)]
+pub enum GuardExpr {
+    /// Boolean literal
+    Literal(bool),
+    /// Operand evaluated as true when non-zero
+    Truthy(Operand),
+    /// Comparison between two operands
+    Compare(Operand, CompareOp, Operand),
+    /// Logical negation
+    Not(Box<GuardExpr>),
+    /// Logical conjunction
+    And(Box<GuardExpr>, Box<GuardExpr>),
+    /// Logical disjunction
+    Or(Box<GuardExpr>, Box<GuardExpr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(u64),
+    Op(CompareOp),
+    And,
+    Or,
+    Not,
+    LParen,
+    RParen,
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        match c {
+            c if c.is_whitespace() => i += 1,
+            '(' => {
+                tokens.push(Token::LParen);
+                i += 1;
+            }
+            ')' => {
+                tokens.push(Token::RParen);
+                i += 1;
+            }
+            '&' if next == Some('&') => {
+                tokens.push(Token::And);
+                i += 2;
+            }
+            '|' if next == Some('|') => {
+                tokens.push(Token::Or);
+                i += 2;
+            }
+            '=' if next == Some('=') => {
+                tokens.push(Token::Op(CompareOp::Eq));
+                i += 2;
+            }
+            '!' if next == Some('=') => {
+                tokens.push(Token::Op(CompareOp::Ne));
+                i += 2;
+            }
+            '!' => {
+                tokens.push(Token::Not);
+                i += 1;
+            }
+            '<' | '>' => {
+                let or_equal = next == Some('=');
+                let op = match (c, or_equal) {
+                    ('<', false) => CompareOp::Lt,
+                    ('<', true) => CompareOp::Le,
+                    ('>', false) => CompareOp::Gt,
+                    _ => CompareOp::Ge,
+                };
+                tokens.push(Token::Op(op));
+                i += if or_equal { 2 } else { 1 };
+            }
+            c if c.is_ascii_digit() => {
+                let start = i;
+                while i < chars.len() && chars[i].is_ascii_digit() {
+                    i += 1;
+                }
+                let literal: String = chars[start..i].iter().collect();
+                let number = literal
+                    .parse()
+                    .map_err(|_| format!("number '{}' is too large", literal))?;
+                tokens.push(Token::Number(number));
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let start = i;
+                while i < chars.len()
+                    && (chars[i].is_ascii_alphanumeric() || chars[i] == '_' || chars[i] == '.')
+                {
+                    i += 1;
+                }
+                tokens.push(Token::Ident(chars[start..i].iter().collect()));
+            }
+            _ => return Err(format!("unexpected character '{}' at position {}", c, i)),
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Recursive descent parser over guard tokens
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn advance(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn parse_or(&mut self) -> Result<GuardExpr, String> {
+        let mut left = self.parse_and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.advance();
+            let right = self.parse_and()?;
+            left = GuardExpr::Or(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<GuardExpr, String> {
+        let mut left = self.parse_unary()?;
+        while self.peek() == Some(&Token::And) {
+            self.advance();
+            let right = self.parse_unary()?;
+            left = GuardExpr::And(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self) -> Result<GuardExpr, String> {
+        if self.peek() == Some(&Token::Not) {
+            self.advance();
+            return Ok(GuardExpr::Not(Box::new(self.parse_unary()?)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<GuardExpr, String> {
+        if self.peek() == Some(&Token::LParen) {
+            self.advance();
+            let expr = self.parse_or()?;
+            return match self.advance() {
+                Some(Token::RParen) => Ok(expr),
+                _ => Err("missing closing parenthesis".to_string()),
+            };
+        }
+
+        if let Some(Token::Ident(name)) = self.peek() {
+            match name.as_str() {
+                "true" => {
+                    self.advance();
+                    return Ok(GuardExpr::Literal(true));
+                }
+                "false" => {
+                    self.advance();
+                    return Ok(GuardExpr::Literal(false));
+                }
+                _ => {}
+            }
+        }
+
+        let left = self.parse_operand()?;
+        if let Some(Token::Op(op)) = self.peek().cloned() {
+            self.advance();
+            let right = self.parse_operand()?;
+            return Ok(GuardExpr::Compare(left, op, right));
+        }
+        Ok(GuardExpr::Truthy(left))
+    }
+
+    fn parse_operand(&mut self) -> Result<Operand, String> {
+        match self.advance() {
+            Some(Token::Number(n)) => Ok(Operand::Number(n)),
+            Some(Token::Ident(name)) => {
+                let known = GUARD_VARIABLES.contains(&name.as_str())
+                    || name
+                        .strip_prefix(MODULE_CALLS_PREFIX)
+                        .is_some_and(|module| !module.is_empty());
+                if known {
+                    Ok(Operand::Variable(name))
+                } else {
+                    Err(format!(
+                        "unknown variable '{}' (available: {}, module_calls.<module>)",
+                        name,
+                        GUARD_VARIABLES.join(", ")
+                    ))
+                }
+            }
+            Some(token) => Err(format!("unexpected token {:?}", token)),
+            None => Err("unexpected end of expression".to_string()),
+        }
+    }
+}
+
+impl GuardExpr {
+    /// Parses a guard expression
+    ///
+    /// # Arguments
+    /// * `input` - The guard source, e.g. `revision_count >= 5`
+    ///
+    /// # Returns
+    /// * `Result<GuardExpr, String>` - The parsed expression or a description of the syntax error
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let mut parser = Parser {
+            tokens: tokenize(input)?,
+            pos: 0,
+        };
+        if parser.tokens.is_empty() {
+            return Err("empty expression".to_string());
+        }
+        let expr = parser.parse_or()?;
+        match parser.peek() {
+            None => Ok(expr),
+            Some(token) => Err(format!("unexpected token {:?}", token)),
+        }
+    }
+
+    /// Evaluates the expression against a task state snapshot
+    ///
+    /// # Arguments
+    /// * `ctx` - Values of the guard variables
+    ///
+    /// # Returns
+    /// * `bool` - Whether the guard holds
+    pub fn evaluate(&self, ctx: &GuardContext) -> bool {
+        let resolve = |operand: &Operand| match operand {
+            Operand::Number(n) => *n,
+            Operand::Variable(name) => ctx.value(name).unwrap_or(0),
+        };
+        match self {
+            GuardExpr::Literal(value) => *value,
+            GuardExpr::Truthy(operand) => resolve(operand) != 0,
+            GuardExpr::Compare(left, op, right) => {
+                let (left, right) = (resolve(left), resolve(right));
+                match op {
+                    CompareOp::Eq => left == right,
+                    CompareOp::Ne => left != right,
+                    CompareOp::Lt => left < right,
+                    CompareOp::Le => left <= right,
+                    CompareOp::Gt => left > right,
+                    CompareOp::Ge => left >= right,
+                }
+            }
+            GuardExpr::Not(inner) => !inner.evaluate(ctx),
+            GuardExpr::And(left, right) => left.evaluate(ctx) && right.evaluate(ctx),
+            GuardExpr::Or(left, right) => left.evaluate(ctx) || right.evaluate(ctx),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses and evaluates a guard against a context
+    fn eval(input: &str, ctx: &GuardContext) -> bool {
+        GuardExpr::parse(input).unwrap().evaluate(ctx)
+    }
+
+    fn context() -> GuardContext {
+        GuardContext {
+            revision_count: 3,
+            retry_count: 0,
+            module_calls: HashMap::from([("fs".to_string(), 2)]),
+            elapsed_secs: 700,
+            proposal_length: 120,
+        }
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        let ctx = context();
+        assert!(eval("true || false && false", &ctx));
+        assert!(!eval("(true || false) && false", &ctx));
+        assert!(eval(
+            "revision_count > 5 || elapsed_secs > 600 && retry_count == 0",
+            &ctx
+        ));
+        assert!(!eval("!true || false", &ctx));
+        assert!(eval("!(revision_count > 5)", &ctx));
+    }
+
+    #[test]
+    fn parentheses_nest() {
+        let ctx = context();
+        assert!(eval("((revision_count == 3))", &ctx));
+        assert!(eval(
+            "(module_calls.fs >= 2 && (retry_count < 1 || false))",
+            &ctx
+        ));
+        assert!(!eval("!(module_calls == 2)", &ctx));
+    }
+
+    #[test]
+    fn number_literals_and_variables_compare() {
+        let ctx = context();
+        assert!(eval("proposal_length >= 120", &ctx));
+        assert!(eval("proposal_length != 119", &ctx));
+        assert!(eval("10 > revision_count", &ctx));
+        assert!(eval("revision_count", &ctx));
+        assert!(!eval("retry_count", &ctx));
+        assert!(!eval("module_calls.web", &ctx));
+        assert_eq!(
+            GuardExpr::parse("retry_count <= 007"),
+            Ok(GuardExpr::Compare(
+                Operand::Variable("retry_count".to_string()),
+                CompareOp::Le,
+                Operand::Number(7)
+            ))
+        );
+    }
+
+    #[test]
+    fn string_literals_are_rejected() {
+        assert!(GuardExpr::parse("revision_count == \"3\"").is_err());
+        assert!(GuardExpr::parse("'approved'").is_err());
+    }
+
+    #[test]
+    fn unknown_identifiers_are_rejected() {
+        let error = GuardExpr::parse("revisions > 2").unwrap_err();
+        assert!(error.contains("unknown variable 'revisions'"), "{}", error);
+        assert!(GuardExpr::parse("module_calls. > 0").is_err());
+    }
+
+    #[test]
+    fn malformed_input_is_an_error() {
+        for input in [
+            "",
+            "   ",
+            "revision_count >",
+            "> 3",
+            "revision_count >= 5 ||",
+            "&& true",
+            "(revision_count > 1",
+            "revision_count > 1)",
+            "revision_count 3",
+            "!",
+            "true == 1",
+            "revision_count = 3",
+            "revision_count & retry_count",
+            "99999999999999999999999 > 1",
+        ] {
+            assert!(GuardExpr::parse(input).is_err(), "'{}' was accepted", input);
+        }
+    }
+}
@@ -1,34 +1,78 @@
-use crate::config::TaskConfig;
+use crate::config::{ContextItem, TaskConfig};
 use crate::core::task_context::TaskContext;
+use crate::errors::Error;
+use crate::llm::{http_client, ContentPart};
+use globset::{Glob, GlobBuilder, GlobSet, GlobSetBuilder};
 use std::fs;
 use std::io::{self, Write};
-use tracing::error;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{debug, error, warn};
+use walkdir::WalkDir;
+
+/// Context kinds understood by `process_task_context`
+pub const CONTEXT_KINDS: &[&str] = &[
+    "file",
+    "text",
+    "user_input",
+    "directory",
+    "glob",
+    "url",
+    "command",
+    "json",
+    "yaml",
+];
+
+/// Default size limit of a single file loaded by `directory` / `glob` items
+const DEFAULT_MAX_FILE_SIZE: u64 = 256 * 1024;
+
+/// Default size limit of all files loaded by one `directory` / `glob` item
+const DEFAULT_MAX_TOTAL_SIZE: u64 = 2 * 1024 * 1024;
+
+/// Directories never walked by `directory` / `glob` items
+const SKIPPED_DIRS: &[&str] = &[".git"];
+
+/// Time allowed to fetch the document of a `url` item
+const URL_FETCH_TIMEOUT: Duration = Duration::from_secs(30);
 
 /// Processes the task context configuration to build a TaskContext object
 ///
 /// Takes a TaskConfig and processes each context item based on its kind:
-/// - "file": Reads content from specified file path
+/// - "file": Reads content from specified file path, attaching images and PDF documents
 /// - "user_input": Gets input from user or uses provided content
 /// - "text": Uses provided text content directly
+/// - "directory": Reads the files of a directory tree, filtered by include/exclude patterns
+/// - "glob": Reads the files matching a glob pattern
+/// - "url": Fetches a document over HTTP
+/// - "command": Captures the standard output of a local shell command
+/// - "json" / "yaml": Picks a value out of a structured file by dotted path
+///
+/// A source that cannot be loaded is skipped with a warning when the item is `optional`.
 ///
 /// # Arguments
 /// * `config` - Reference to TaskConfig containing context configuration
 ///
 /// # Returns
-/// * `TaskContext` - Constructed task context with processed content
-pub fn process_task_context(config: &TaskConfig) -> TaskContext {
+/// * `Result<TaskContext, Error>` - Constructed task context with processed content, or an
+///   error if a source that is not `optional` cannot be loaded
+pub fn process_task_context(config: &TaskConfig) -> Result<TaskContext, Error> {
     let mut ctx = TaskContext::new();
 
     for item in &config.context {
-        match item.kind.as_str() {
-            "file" => {
-                if let Some(path) = &item.path {
-                    let content = fs::read_to_string(path)
-                        .unwrap_or_else(|_| panic!("Failed to read file: {}", path));
+        let loaded = match item.kind.as_str() {
+            "file" => match &item.path {
+                Some(path) => {
                     let alias = item.alias.clone().unwrap_or_else(|| path.clone());
-                    ctx.files.push((alias, content));
+                    match ContentPart::attachment(path) {
+                        Some(part) => attach_file(path).map(|_| {
+                            ctx.attachments.push((alias, part));
+                            vec![]
+                        }),
+                        None => read_file(path).map(|content| vec![(alias, content)]),
+                    }
                 }
-            }
+                None => Ok(vec![]),
+            },
             "user_input" => {
                 if let Some(content) = &item.content {
                     ctx.text.push_str(content);
@@ -42,18 +86,302 @@ pub fn process_task_context(config: &TaskConfig) -> TaskContext {
                         .expect("Failed to read user input");
                     ctx.text.push_str(&input);
                 }
+                Ok(vec![])
             }
             "text" => {
                 if let Some(content) = &item.content {
                     ctx.text.push_str(content);
                     ctx.text.push('\n');
                 }
+                Ok(vec![])
             }
+            "directory" => load_directory(item),
+            "glob" => load_glob(item),
+            "url" => load_url(item),
+            "command" => load_command(item),
+            "json" | "yaml" => load_structured(item),
             kind => {
                 error!("Unknown context kind: {}", kind);
+                Ok(vec![])
+            }
+        };
+
+        match loaded {
+            Ok(files) => ctx.files.extend(files),
+            Err(e) if item.optional => {
+                warn!("Skipping optional {} context: {}", item.kind, e);
+            }
+            Err(e) => {
+                return Err(Error::Context(format!(
+                    "failed to load {} context: {}",
+                    item.kind, e
+                )))
             }
         }
     }
 
-    ctx
+    Ok(ctx)
+}
+
+/// Builds a matcher from a list of glob patterns
+///
+/// # Arguments
+/// * `patterns` - Glob patterns such as `src/**/*.rs`
+///
+/// # Returns
+/// * `Result<GlobSet, String>` - The compiled matcher or a description of the invalid pattern
+pub fn build_globset(patterns: &[String]) -> Result<GlobSet, String> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder
+            .add(Glob::new(pattern).map_err(|e| format!("invalid pattern '{}': {}", pattern, e))?);
+    }
+    builder.build().map_err(|e| e.to_string())
+}
+
+/// Reads a text file
+fn read_file(path: &str) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|e| format!("cannot read '{}': {}", path, e))
+}
+
+/// Checks that an image or document can be attached, its content being read when sent
+fn attach_file(path: &str) -> Result<(), String> {
+    fs::metadata(path)
+        .map(|_| ())
+        .map_err(|e| format!("cannot read '{}': {}", path, e))
+}
+
+/// Loads the files of a directory tree, honoring include/exclude patterns and size limits
+fn load_directory(item: &ContextItem) -> Result<Vec<(String, String)>, String> {
+    let root = item
+        .path
+        .as_deref()
+        .ok_or("'directory' context requires a path")?;
+    if !Path::new(root).is_dir() {
+        return Err(format!("'{}' is not a directory", root));
+    }
+    let include = build_globset(&item.include)?;
+    walk_files(item, Path::new(root), None, |relative| {
+        item.include.is_empty() || include.is_match(relative)
+    })
+}
+
+/// Loads the files matching a glob pattern, honoring exclude patterns and size limits
+///
+/// As in a shell, `*` does not cross directory separators; use `**` to match any depth.
+fn load_glob(item: &ContextItem) -> Result<Vec<(String, String)>, String> {
+    let pattern = item
+        .path
+        .as_deref()
+        .ok_or("'glob' context requires a pattern in 'path'")?;
+    let matcher = GlobBuilder::new(pattern)
+        .literal_separator(true)
+        .build()
+        .map_err(|e| format!("invalid pattern '{}': {}", pattern, e))?
+        .compile_matcher();
+    let prefix = glob_prefix(pattern);
+    let root = if prefix.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        prefix.as_path()
+    };
+    if !root.is_dir() {
+        return Err(format!("'{}' is not a directory", root.display()));
+    }
+    let max_depth = if pattern.contains("**") {
+        None
+    } else {
+        Some(Path::new(pattern).components().count() - prefix.components().count())
+    };
+    let files = walk_files(item, root, max_depth, |relative| {
+        matcher.is_match(prefix.join(relative))
+    })?;
+    if files.is_empty() {
+        return Err(format!("no file matches '{}'", pattern));
+    }
+    Ok(files)
+}
+
+/// Returns the leading components of a glob pattern that contain no wildcard,
+/// i.e. the directory the pattern starts from (empty for the current directory)
+fn glob_prefix(pattern: &str) -> PathBuf {
+    let mut prefix = PathBuf::new();
+    for component in Path::new(pattern).components() {
+        let text = component.as_os_str().to_string_lossy();
+        if text.contains(['*', '?', '[', '{']) {
+            break;
+        }
+        prefix.push(component);
+    }
+    if prefix == Path::new(pattern) {
+        prefix.pop();
+    }
+    prefix
+}
+
+/// Walks a directory and reads every text file accepted by `accept`
+///
+/// Files matching an `exclude` pattern, larger than `max_file_size` or that are not
+/// valid UTF-8 are skipped. Walking stops once `max_total_size` bytes have been read.
+///
+/// # Arguments
+/// * `item` - The context item providing the filters and limits
+/// * `root` - Directory to walk
+/// * `max_depth` - Maximum depth of the files relative to `root`, unlimited when `None`
+/// * `accept` - Predicate on the path of a file relative to `root`
+///
+/// # Returns
+/// * `Result<Vec<(String, String)>, String>` - `(alias, content)` pairs in path order
+fn walk_files(
+    item: &ContextItem,
+    root: &Path,
+    max_depth: Option<usize>,
+    accept: impl Fn(&Path) -> bool,
+) -> Result<Vec<(String, String)>, String> {
+    let exclude = build_globset(&item.exclude)?;
+    let max_file_size = item.max_file_size.unwrap_or(DEFAULT_MAX_FILE_SIZE);
+    let max_total_size = item.max_total_size.unwrap_or(DEFAULT_MAX_TOTAL_SIZE);
+    let mut total = 0;
+    let mut files = vec![];
+
+    let entries = WalkDir::new(root)
+        .sort_by_file_name()
+        .max_depth(max_depth.unwrap_or(usize::MAX))
+        .into_iter()
+        .filter_entry(|entry| {
+            !(entry.file_type().is_dir()
+                && SKIPPED_DIRS.contains(&entry.file_name().to_string_lossy().as_ref()))
+        });
+    for entry in entries {
+        let entry = entry.map_err(|e| e.to_string())?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let relative = entry.path().strip_prefix(root).unwrap_or(entry.path());
+        if exclude.is_match(relative) || !accept(relative) {
+            continue;
+        }
+
+        let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+        if size > max_file_size {
+            debug!("Skipping {} ({} bytes)", entry.path().display(), size);
+            continue;
+        }
+        if total + size > max_total_size {
+            warn!(
+                "Context '{}' reached its size limit of {} bytes, remaining files are skipped",
+                root.display(),
+                max_total_size
+            );
+            break;
+        }
+        let Ok(content) = fs::read_to_string(entry.path()) else {
+            debug!("Skipping non-text file {}", entry.path().display());
+            continue;
+        };
+
+        total += size;
+        let alias = match &item.alias {
+            Some(alias) => format!("{}/{}", alias, relative.display()),
+            None => entry.path().display().to_string(),
+        };
+        files.push((alias, content));
+    }
+
+    Ok(files)
+}
+
+/// Fetches a document over HTTP, giving up after `URL_FETCH_TIMEOUT`
+fn load_url(item: &ContextItem) -> Result<Vec<(String, String)>, String> {
+    let url = item.url.as_deref().ok_or("'url' context requires a url")?;
+    let fetch = async {
+        http_client()
+            .get(url)
+            .timeout(URL_FETCH_TIMEOUT)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await
+    };
+    let content = match tokio::runtime::Handle::try_current() {
+        Ok(handle) => tokio::task::block_in_place(|| handle.block_on(fetch)),
+        Err(_) => tokio::runtime::Runtime::new()
+            .map_err(|e| e.to_string())?
+            .block_on(fetch),
+    }
+    .map_err(|e| format!("cannot fetch '{}': {}", url, e))?;
+
+    Ok(vec![(
+        item.alias.clone().unwrap_or_else(|| url.to_string()),
+        content,
+    )])
+}
+
+/// Captures the standard output of a shell command
+fn load_command(item: &ContextItem) -> Result<Vec<(String, String)>, String> {
+    let command = item
+        .command
+        .as_deref()
+        .ok_or("'command' context requires a command")?;
+    let output = std::process::Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .output()
+        .map_err(|e| format!("cannot run '{}': {}", command, e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "'{}' exited with {}: {}",
+            command,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(vec![(
+        item.alias.clone().unwrap_or_else(|| command.to_string()),
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+    )])
+}
+
+/// Picks a value out of a JSON or YAML file by dotted path
+fn load_structured(item: &ContextItem) -> Result<Vec<(String, String)>, String> {
+    let path = item
+        .path
+        .as_deref()
+        .ok_or_else(|| format!("'{}' context requires a path", item.kind))?;
+    let content = read_file(path)?;
+    let document: serde_json::Value = if item.kind == "json" {
+        serde_json::from_str(&content).map_err(|e| format!("invalid JSON in '{}': {}", path, e))?
+    } else {
+        serde_yaml::from_str(&content).map_err(|e| format!("invalid YAML in '{}': {}", path, e))?
+    };
+
+    let mut value = &document;
+    for key in item.query.iter().flat_map(|query| query.split('.')) {
+        let next = match value {
+            serde_json::Value::Array(items) => key.parse::<usize>().ok().and_then(|i| items.get(i)),
+            _ => value.get(key),
+        };
+        value = next.ok_or_else(|| {
+            format!(
+                "'{}' not found in '{}'",
+                item.query.as_deref().unwrap_or_default(),
+                path
+            )
+        })?;
+    }
+
+    let rendered = match value {
+        serde_json::Value::String(text) => text.clone(),
+        other if item.kind == "json" => {
+            serde_json::to_string_pretty(other).map_err(|e| e.to_string())?
+        }
+        other => serde_yaml::to_string(other).map_err(|e| e.to_string())?,
+    };
+    let alias = item.alias.clone().unwrap_or_else(|| match &item.query {
+        Some(query) => format!("{}#{}", path, query),
+        None => path.to_string(),
+    });
+    Ok(vec![(alias, rendered)])
 }
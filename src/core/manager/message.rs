@@ -1,7 +1,11 @@
 use crate::core::manager::utils::pause_and_update;
 use crate::core::TaskManager;
+use crate::db::TaskRepository;
 use crate::errors::Error;
 
+/// Maximum number of characters of a streamed response shown in the spinner
+const PROGRESS_PREVIEW_LEN: usize = 80;
+
 impl TaskManager {
     /// Handles a new message event by displaying it with the task ID
     ///
@@ -16,4 +20,36 @@ impl TaskManager {
         pause_and_update(&self.spinner, &format!("{}: {}", task_id, message)).await;
         Ok(())
     }
+
+    /// Handles the partial text of a streamed LLM response
+    ///
+    /// Shows the tail of the text in the spinner, or stores it on the task so the
+    /// API can return it while the response is in progress.
+    ///
+    /// # Arguments
+    /// * `task_id` - The ID of the task the response belongs to
+    /// * `role` - The role of the agent streaming the response
+    /// * `partial` - The text received so far
+    pub async fn handle_llm_progress(
+        &mut self,
+        task_id: String,
+        role: String,
+        partial: String,
+    ) -> Result<(), Error> {
+        if self.api_enabled {
+            TaskRepository::new(&mut self.database.get_conn())
+                .update_task_progress(&task_id, &partial)?;
+            return Ok(());
+        }
+
+        let last_line = partial.trim_end().lines().last().unwrap_or_default();
+        let skip = last_line
+            .chars()
+            .count()
+            .saturating_sub(PROGRESS_PREVIEW_LEN);
+        let preview: String = last_line.chars().skip(skip).collect();
+        self.spinner
+            .set_message(format!("{}: {} is writing... {}", task_id, role, preview));
+        Ok(())
+    }
 }
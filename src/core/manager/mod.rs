@@ -12,18 +12,18 @@ use crate::{
         workflow::Workflow,
     },
     db::Database,
+    errors::Error,
     event::Event,
-    llm::{ChatMessage, LlmClient, OpenAIEmbedder},
+    llm::{transport_applies, ChatMessage, LlmClient, TaskEmbedder},
     modules::ModulesManager,
     utils::generate_system_instructions,
 };
 use colored::*;
-pub use context::process_task_context;
+pub use context::{build_globset, process_task_context, CONTEXT_KINDS};
 use dialoguer::{theme::ColorfulTheme, Input};
 use indicatif::ProgressBar;
 use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
-
-const DEFAULT_EMBEDDER_MODEL: &str = "text-embedding-3-small";
+use tracing::warn;
 
 /// Main task manager struct responsible for coordinating task execution and agent interactions
 #[derive(Debug)]
@@ -42,18 +42,27 @@ pub struct TaskManager {
     pub llm_client: LlmClient,
     /// API enabled
     pub api_enabled: bool,
+    /// Whether the LLM response cache is disabled for every task (`--no-cache`)
+    pub no_cache: bool,
 }
 
 impl TaskManager {
     /// Creates a new TaskManager instance without a predefined task
-    pub async fn new_without_task_creation(api_enabled: bool) -> (Self, Vec<TaskWorker>) {
+    ///
+    /// # Returns
+    /// * `Result<(Self, Vec<TaskWorker>), Error>` - The manager and the worker of the generated
+    ///   task, or an error if its context cannot be loaded
+    pub async fn new_without_task_creation(
+        api_enabled: bool,
+        no_cache: bool,
+    ) -> Result<(Self, Vec<TaskWorker>), Error> {
         Self::display_welcome_message();
 
         let user_input = Self::get_user_input("📝 Your task");
         Self::clear_screen();
 
         Self::display_processing_message();
-        std::thread::sleep(std::time::Duration::from_secs(1));
+        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
         Self::clear_screen();
 
         let llm_provider = Self::get_user_input("LLM provider (e.g openai)");
@@ -63,7 +72,7 @@ impl TaskManager {
             LlmClient::new(&llm_provider, &llm_model).expect("Failed to create LLM client");
 
         let config = generate_task_config_from_user(&user_input, &llm_client).await;
-        Self::from_config(&config, api_enabled)
+        Self::from_config(&config, api_enabled, no_cache)
     }
 
     /// Creates a new TaskManager instance without any predefined tasks
@@ -77,6 +86,7 @@ impl TaskManager {
         llm_provider: &str,
         llm_model: &str,
         api_enabled: bool,
+        no_cache: bool,
     ) -> (Self, Vec<TaskWorker>) {
         let without_task = true;
         let (self_tx, self_rx) = tokio::sync::mpsc::unbounded_channel();
@@ -92,6 +102,7 @@ impl TaskManager {
                 without_task,
                 llm_client,
                 api_enabled,
+                no_cache,
             },
             Vec::new(),
         )
@@ -128,34 +139,52 @@ impl TaskManager {
     }
 
     /// Creates a new TaskManager instance from a config
-    pub fn new(config: &TaskConfig, api_enabled: bool) -> (Self, Vec<TaskWorker>) {
-        Self::from_config(config, api_enabled)
+    ///
+    /// # Returns
+    /// * `Result<(Self, Vec<TaskWorker>), Error>` - The manager and the worker of the task, or
+    ///   an error if its context cannot be loaded
+    pub fn new(
+        config: &TaskConfig,
+        api_enabled: bool,
+        no_cache: bool,
+    ) -> Result<(Self, Vec<TaskWorker>), Error> {
+        Self::from_config(config, api_enabled, no_cache)
     }
 
     /// Internal constructor to create TaskManager from config
-    fn from_config(config: &TaskConfig, api_enabled: bool) -> (Self, Vec<TaskWorker>) {
+    fn from_config(
+        config: &TaskConfig,
+        api_enabled: bool,
+        no_cache: bool,
+    ) -> Result<(Self, Vec<TaskWorker>), Error> {
+        let mut config = config.clone();
+        if no_cache {
+            config.parameters.llm_cache.enabled = false;
+        }
+        let config = &config;
+        if !transport_applies(&config.parameters.http) {
+            warn!(
+                "parameters.http of task '{}' ignored: the HTTP transport is configured once per process",
+                config.name
+            );
+        }
         let vector_store = Self::initialize_vector_store(config);
-        let task = Self::create_task(config);
+        let task = Self::create_task(config)?;
         let (self_tx, self_rx) = tokio::sync::mpsc::unbounded_channel();
         let database =
             Database::new(&std::env::var("DATABASE_PATH").unwrap_or("kheish.db".to_string()));
         let without_task = false;
-        let llm_provider = config
-            .parameters
-            .llm_provider
-            .as_deref()
-            .expect("LLM provider is required");
-        let llm_model = config
-            .parameters
-            .llm_model
-            .as_deref()
-            .expect("LLM model is required");
-        let llm_client =
-            LlmClient::new(llm_provider, llm_model).expect("Failed to create LLM client");
+        let llm_client = LlmClient::from_settings(&config.parameters.llm_settings())
+            .expect("Failed to create LLM client");
+        let workflow = Workflow::new(
+            config.workflow.steps.clone(),
+            config.workflow.parallel.clone(),
+        )
+        .map_err(Error::Workflow)?;
         let task_worker = TaskWorker::new(
             task.task_id.clone(),
             task,
-            Workflow::new(config.workflow.steps.clone()),
+            workflow,
             config.clone(),
             vector_store,
             self_tx.clone(),
@@ -172,40 +201,35 @@ impl TaskManager {
             without_task,
             llm_client,
             api_enabled,
+            no_cache,
         };
 
         manager.init_spinner();
-        (manager, workers)
+        Ok((manager, workers))
     }
 
     /// Extracts LLM provider and model from config
     pub fn extract_llm_config(config: &TaskConfig) -> (&str, &str) {
         let llm_provider = config
             .parameters
-            .llm_provider
-            .as_deref()
+            .provider()
             .expect("LLM provider is required");
-        let llm_model = config
-            .parameters
-            .llm_model
-            .as_deref()
-            .expect("LLM model is required");
+        let llm_model = config.parameters.model().expect("LLM model is required");
         (llm_provider, llm_model)
     }
 
     /// Initializes the vector store with embedder configuration
-    fn initialize_vector_store(config: &TaskConfig) -> InMemoryVectorStore<OpenAIEmbedder> {
+    fn initialize_vector_store(config: &TaskConfig) -> InMemoryVectorStore<TaskEmbedder> {
         let embedder_config = config.parameters.embedder.clone().unwrap_or_default();
-        let embedder_model = embedder_config
-            .model
-            .unwrap_or(DEFAULT_EMBEDDER_MODEL.to_string());
-        InMemoryVectorStore::new(OpenAIEmbedder::new(&embedder_model).unwrap())
+        InMemoryVectorStore::new(
+            TaskEmbedder::from_config(&embedder_config).expect("Failed to create embedder"),
+        )
     }
 
     /// Creates a new task with context and system instructions
-    fn create_task(config: &TaskConfig) -> Task {
+    fn create_task(config: &TaskConfig) -> Result<Task, Error> {
         let task_id = uuid::Uuid::new_v4().to_string();
-        let context = process_task_context(config);
+        let context = process_task_context(config)?;
         let mut task = Task::new(
             task_id,
             config.name.clone(),
@@ -221,6 +245,6 @@ impl TaskManager {
         task.conversation
             .push(ChatMessage::new("system", &system_instructions));
 
-        task
+        Ok(task)
     }
 }
@@ -20,6 +20,10 @@ impl TaskManager {
     ///   - `NewMessage`: Handles task messages and logging
     ///   - `TaskStateUpdated`: Updates task states in the system
     ///   - `CreateTask`: Creates and initializes new tasks
+    ///   - `LlmProgress`: Shows or stores the partial text of streamed responses
+    ///   - `LlmUsage`: Adds the tokens of an LLM call to the usage of the task
+    ///   - `LlmFallback`: Records an LLM call failing over to another provider/model
+    ///   - `ConversationCompacted`: Records the turns of a conversation replaced by a summary
     ///   - `NewOutput`: Processes task outputs and results
     /// - Runs periodic checks for new and ready tasks on configured intervals
     pub async fn run(&mut self, workers: Vec<TaskWorker>) {
@@ -53,9 +57,29 @@ impl TaskManager {
                                 error!("Error creating task: {}", e);
                             }
                         }
-                        Event::NewOutput(task_id, output) => {
-                            info!("New output: {}", output);
-                            if let Err(e) = self.handle_new_output(task_id, output).await {
+                        Event::LlmProgress(task_id, role, partial) => {
+                            if let Err(e) = self.handle_llm_progress(task_id, role, partial).await {
+                                error!("Error handling LLM progress: {}", e);
+                            }
+                        }
+                        Event::LlmUsage(task_id, entry) => {
+                            if let Err(e) = self.handle_llm_usage(task_id, entry).await {
+                                error!("Error recording LLM usage: {}", e);
+                            }
+                        }
+                        Event::LlmFallback(task_id, entry) => {
+                            if let Err(e) = self.handle_llm_fallback(task_id, entry).await {
+                                error!("Error recording LLM fallback: {}", e);
+                            }
+                        }
+                        Event::ConversationCompacted(task_id, entry) => {
+                            if let Err(e) = self.handle_conversation_compacted(task_id, entry).await {
+                                error!("Error recording conversation compaction: {}", e);
+                            }
+                        }
+                        Event::NewOutput(task_id, result) => {
+                            info!("New output: {} {}", result.sink, result.status);
+                            if let Err(e) = self.handle_new_output(task_id, result).await {
                                 error!("Error handling new output: {}", e);
                             }
                         }
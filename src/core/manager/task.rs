@@ -1,13 +1,15 @@
 use super::TaskManager;
+use crate::config::{apply_inputs, unreferenced_inputs};
 use crate::core::manager::utils;
 use crate::core::task_generation::generate_task_config_from_task;
 use crate::core::TaskState;
-use crate::core::{Task, TaskWorker, Workflow};
+use crate::core::{SinkResult, Task, TaskWorker, Workflow};
 use crate::db::TaskRepository;
 use crate::errors::Error;
 use crate::event::Event;
-use serde_json::Value;
-use tracing::{error, info};
+use crate::llm::{transport_applies, CompactionEntry, FallbackEntry, UsageEntry};
+use std::collections::BTreeMap;
+use tracing::{error, info, warn};
 
 impl TaskManager {
     /// Updates the state of a task in the database and displays a status message
@@ -20,12 +22,98 @@ impl TaskManager {
         task_id: String,
         state: TaskState,
     ) -> Result<(), Error> {
-        TaskRepository::new(&mut self.database.get_conn()).update_task_state(&task_id, &state)?;
+        let mut conn = self.database.get_conn();
+        let mut task_repo = TaskRepository::new(&mut conn);
+        task_repo.update_task_state(&task_id, &state)?;
 
         let message = format!("Task {} state updated: {}", task_id, state.to_string());
-        if let Err(e) = self.self_tx.send(Event::NewMessage(task_id, message)) {
+        if let Err(e) = self
+            .self_tx
+            .send(Event::NewMessage(task_id.clone(), message))
+        {
             error!("Error sending event: {}", e);
         }
+
+        if matches!(
+            state,
+            TaskState::Completed
+                | TaskState::WaitingWakeUp
+                | TaskState::LimitReached(_)
+                | TaskState::Failed(_)
+        ) {
+            if let Some(summary) = utils::usage_summary(&task_repo.get_task_usage(&task_id)?) {
+                if let Err(e) = self.self_tx.send(Event::NewMessage(task_id, summary)) {
+                    error!("Error sending event: {}", e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds the token usage of an LLM call to the totals of the task in the database
+    ///
+    /// # Arguments
+    /// * `task_id` - The ID of the task the call belongs to
+    /// * `entry` - The usage of the call, with its role, model and cost
+    pub async fn handle_llm_usage(
+        &mut self,
+        task_id: String,
+        entry: UsageEntry,
+    ) -> Result<(), Error> {
+        TaskRepository::new(&mut self.database.get_conn()).record_task_usage(&task_id, &entry)?;
+        Ok(())
+    }
+
+    /// Records an LLM call that failed over to another provider/model in the task events
+    ///
+    /// # Arguments
+    /// * `task_id` - The ID of the task the call belongs to
+    /// * `entry` - The fallback, with its role, providers/models and reason
+    pub async fn handle_llm_fallback(
+        &mut self,
+        task_id: String,
+        entry: FallbackEntry,
+    ) -> Result<(), Error> {
+        warn!(
+            "Task {}: {} fell back from {} to {}: {}",
+            task_id, entry.role, entry.from, entry.to, entry.reason
+        );
+        let content = serde_json::to_string(&entry)?;
+        TaskRepository::new(&mut self.database.get_conn()).insert_task_event(
+            &task_id,
+            Some(&entry.role),
+            "llm_fallback",
+            Some(&content),
+        )?;
+        Ok(())
+    }
+
+    /// Records the turns of a conversation replaced by a summary in the task events, so that
+    /// the original messages stay available for auditing
+    ///
+    /// # Arguments
+    /// * `task_id` - The ID of the task the conversation belongs to
+    /// * `entry` - The compaction, with its role, replaced messages and summary
+    pub async fn handle_conversation_compacted(
+        &mut self,
+        task_id: String,
+        entry: CompactionEntry,
+    ) -> Result<(), Error> {
+        info!(
+            "Task {}: {} conversation compacted, {} messages summarized ({} -> {} tokens)",
+            task_id,
+            entry.role,
+            entry.messages.len(),
+            entry.tokens_before,
+            entry.tokens_after
+        );
+        let content = serde_json::to_string(&entry)?;
+        TaskRepository::new(&mut self.database.get_conn()).insert_task_event(
+            &task_id,
+            Some(&entry.role),
+            "conversation_compacted",
+            Some(&content),
+        )?;
         Ok(())
     }
 
@@ -63,6 +151,7 @@ impl TaskManager {
             Some(conversation),
             last_run_at,
             task.interval,
+            None,
         )?;
 
         let message = format!("Task {} created with id {}", name, db_task_id);
@@ -73,41 +162,22 @@ impl TaskManager {
         Ok(())
     }
 
-    /// Creates a new output for a task in the database and displays a status message
+    /// Records the result of an output sink in the database and displays a status message
     ///
     /// # Arguments
-    /// * `task_id` - The ID of the task to create output for
-    /// * `output` - The output value to create for the task as a JSON Value
-    pub async fn handle_create_task_output(
+    /// * `task_id` - The ID of the task to update
+    /// * `result` - The result of the sink, with its content and status
+    pub async fn handle_new_output(
         &mut self,
         task_id: String,
-        output: Value,
+        result: SinkResult,
     ) -> Result<(), Error> {
-        let output_str = output.to_string();
-        TaskRepository::new(&mut self.database.get_conn())
-            .update_task_output(&task_id, &output_str)?;
-
-        let message = format!("Task {} output updated", task_id);
-        if let Err(e) = self
-            .self_tx
-            .send(Event::NewMessage(task_id.clone(), message))
-        {
-            error!("Error sending event: {}", e);
-        }
-        Ok(())
-    }
-
-    /// Updates the output of a task in the database and displays a status message
-    ///
-    /// # Arguments
-    /// * `task_id` - The ID of the task to update
-    /// * `output` - The new output value to set for the task as a JSON Value
-    pub async fn handle_new_output(&mut self, task_id: String, output: Value) -> Result<(), Error> {
-        let output_str = output.to_string();
-        info!("New output inserting: {}", output_str);
-        TaskRepository::new(&mut self.database.get_conn())
-            .update_task_output(&task_id, &output_str)?;
-        let message = format!("Task {} output updated", task_id);
+        info!("New output inserting: {} ({})", result.sink, result.status);
+        TaskRepository::new(&mut self.database.get_conn()).insert_sink_output(&task_id, &result)?;
+        let message = format!(
+            "Task {} output '{}' {}",
+            task_id, result.sink, result.status
+        );
         if let Err(e) = self
             .self_tx
             .send(Event::NewMessage(task_id.clone(), message))
@@ -125,11 +195,63 @@ impl TaskManager {
         let mut task_repo: TaskRepository<'_> = TaskRepository::new(&mut conn);
         if let Ok(tasks) = task_repo.get_tasks_by_state(&TaskState::New) {
             for task in tasks {
-                if let Ok(task_config) =
-                    generate_task_config_from_task(&task, &self.llm_client).await
-                {
-                    task_repo.update_task_config(&task.task_id, &task_config)?;
-                    task_repo.update_task_state(&task.task_id, &TaskState::Ready)?;
+                let mut task_config =
+                    match generate_task_config_from_task(&task, &self.llm_client).await {
+                        Ok(task_config) => task_config,
+                        Err(e) => {
+                            warn!(
+                                "Failed to generate the configuration of task {}: {}",
+                                task.task_id, e
+                            );
+                            task_repo.update_task_state(
+                                &task.task_id,
+                                &TaskState::Failed(format!(
+                                    "Task configuration generation failed: {}",
+                                    e
+                                )),
+                            )?;
+                            continue;
+                        }
+                    };
+
+                let inputs: BTreeMap<String, String> = task
+                    .inputs
+                    .as_deref()
+                    .and_then(|inputs| serde_json::from_str(inputs).ok())
+                    .unwrap_or_default();
+                for name in unreferenced_inputs(&task_config, &inputs) {
+                    warn!(
+                        "Input '{}' of task {} is not used by its generated configuration",
+                        name, task.task_id
+                    );
+                }
+                for name in inputs.keys() {
+                    task_config.inputs.entry(name.clone()).or_default();
+                }
+
+                match apply_inputs(task_config, &inputs) {
+                    Ok(task_config) => {
+                        match task_repo.update_task_config(&task.task_id, &task_config) {
+                            Ok(()) => {
+                                task_repo.update_task_state(&task.task_id, &TaskState::Ready)?
+                            }
+                            Err(Error::Context(e)) => {
+                                error!("Invalid context for task {}: {}", task.task_id, e);
+                                task_repo.update_task_state(
+                                    &task.task_id,
+                                    &TaskState::Failed(format!("Invalid task context: {}", e)),
+                                )?;
+                            }
+                            Err(e) => return Err(e),
+                        }
+                    }
+                    Err(e) => {
+                        error!("Invalid inputs for task {}: {}", task.task_id, e);
+                        task_repo.update_task_state(
+                            &task.task_id,
+                            &TaskState::Failed(format!("Invalid task inputs: {}", e)),
+                        )?;
+                    }
                 }
             }
         }
@@ -167,10 +289,38 @@ impl TaskManager {
                 }
 
                 match task_repo.get_task_config(&task.task_id) {
-                    Ok(task_config) => {
+                    Ok(mut task_config) => {
+                        if self.no_cache {
+                            task_config.parameters.llm_cache.enabled = false;
+                        }
+                        if !transport_applies(&task_config.parameters.http) {
+                            warn!(
+                                "Task {}: parameters.http ignored, the HTTP transport is configured once per process",
+                                task_id
+                            );
+                        }
                         let vector_store = Self::initialize_vector_store(&task_config);
-                        let manager_task = Task::from((task, task_config.clone()));
-                        let workflow = Workflow::new(task_config.workflow.steps.clone());
+                        let prepared =
+                            Task::try_from((task, task_config.clone())).and_then(|manager_task| {
+                                let workflow = Workflow::new(
+                                    task_config.workflow.steps.clone(),
+                                    task_config.workflow.parallel.clone(),
+                                )
+                                .map_err(Error::Workflow)?;
+                                Ok((manager_task, workflow))
+                            });
+                        let (manager_task, workflow) = match prepared {
+                            Ok(prepared) => prepared,
+                            Err(e) => {
+                                error!("Task {}: {}", task_id, e);
+                                if let Err(e) = task_repo
+                                    .update_task_state(&task_id, &TaskState::Failed(e.to_string()))
+                                {
+                                    error!("Error updating task state: {}", e);
+                                }
+                                return None;
+                            }
+                        };
                         let task_worker = TaskWorker::new(
                             task_id.clone(),
                             manager_task,
@@ -193,5 +343,4 @@ impl TaskManager {
 
         Ok(())
     }
-
 }
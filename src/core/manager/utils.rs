@@ -1,4 +1,5 @@
 use crate::core::TaskManager;
+use crate::db::TaskUsage;
 use chrono::{Duration as ChronoDuration, NaiveDateTime, Utc};
 use indicatif::ProgressBar;
 use indicatif::ProgressStyle;
@@ -87,3 +88,59 @@ pub fn check_should_execute_now(interval_str: &str, last_run_at: NaiveDateTime)
         Err(_) => false,
     }
 }
+
+/// Formats the token usage and cost of a task for the completion summary
+///
+/// # Arguments
+///
+/// * `usage` - Usage rows of the task, per role and model
+///
+/// # Returns
+///
+/// The summary with the totals and one line per role and model, or None without LLM calls
+pub fn usage_summary(usage: &[TaskUsage]) -> Option<String> {
+    if usage.is_empty() {
+        return None;
+    }
+    let format_cost = |cost: Option<f64>| match cost {
+        Some(cost) => format!("{:.4}", cost),
+        None => "unpriced".to_string(),
+    };
+
+    let calls: i64 = usage.iter().map(|row| row.calls).sum();
+    let cache_hits: i64 = usage.iter().map(|row| row.cache_hits).sum();
+    let input_tokens: i64 = usage.iter().map(|row| row.input_tokens).sum();
+    let output_tokens: i64 = usage.iter().map(|row| row.output_tokens).sum();
+    let priced: Vec<f64> = usage.iter().filter_map(|row| row.cost).collect();
+    let mut total_cost = format_cost((!priced.is_empty()).then(|| priced.iter().sum()));
+    if !priced.is_empty() && priced.len() < usage.len() {
+        total_cost.push_str(" (some models unpriced)");
+    }
+
+    let cached = |hits: i64| match hits {
+        0 => String::new(),
+        hits => format!(" ({} cached)", hits),
+    };
+
+    let mut summary = format!(
+        "📊 Token usage: {} calls{}, {} input / {} output tokens, cost {}",
+        calls,
+        cached(cache_hits),
+        input_tokens,
+        output_tokens,
+        total_cost
+    );
+    for row in usage {
+        summary.push_str(&format!(
+            "\n   {} ({}): {} calls{}, {} input / {} output tokens, cost {}",
+            row.role,
+            row.model,
+            row.calls,
+            cached(row.cache_hits),
+            row.input_tokens,
+            row.output_tokens,
+            format_cost(row.cost)
+        ));
+    }
+    Some(summary)
+}
@@ -2,11 +2,15 @@
 //!
 //! This module contains:
 //! - Task management and execution
+//! - Delivery of task outputs to their sinks
 //! - Worker implementation for processing tasks
 //! - Workflow definitions and state management
+//! - Guard expressions for conditional workflow transitions
 //! - RAG (Retrieval Augmented Generation) functionality
 
+pub mod guard;
 mod manager;
+mod output;
 pub mod rag;
 mod task;
 mod task_context;
@@ -16,6 +20,7 @@ mod worker;
 mod workflow;
 
 pub use manager::*;
+pub use output::*;
 pub use task::*;
 pub use task_state::*;
 pub use worker::*;
@@ -0,0 +1,135 @@
+use crate::config::{OutputSink, SinkDestination};
+use crate::llm::http_client;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use tracing::info;
+
+/// Status of an output sink for one run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SinkStatus {
+    /// The output was formatted and delivered
+    Succeeded,
+    /// Formatting or delivery failed
+    Failed,
+}
+
+impl fmt::Display for SinkStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SinkStatus::Succeeded => write!(f, "succeeded"),
+            SinkStatus::Failed => write!(f, "failed"),
+        }
+    }
+}
+
+/// Result of formatting and delivering one output sink
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SinkResult {
+    /// Name of the sink
+    pub sink: String,
+    /// Destination of the sink
+    pub destination: SinkDestination,
+    /// Whether the sink succeeded
+    pub status: SinkStatus,
+    /// Formatted content, if formatting succeeded
+    pub content: Option<String>,
+    /// Reason of the failure, if any
+    pub error: Option<String>,
+    /// Model that formatted the content, if formatting reached the LLM
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+impl SinkResult {
+    /// Creates the result of a sink that was delivered
+    ///
+    /// # Arguments
+    /// * `sink` - The delivered sink
+    /// * `content` - The formatted content
+    pub fn succeeded(sink: &OutputSink, content: String) -> Self {
+        Self {
+            sink: sink.label().to_string(),
+            destination: sink.destination,
+            status: SinkStatus::Succeeded,
+            content: Some(content),
+            error: None,
+            model: None,
+        }
+    }
+
+    /// Creates the result of a sink that failed
+    ///
+    /// # Arguments
+    /// * `sink` - The failed sink
+    /// * `content` - The formatted content, if formatting succeeded
+    /// * `error` - Reason of the failure
+    pub fn failed(sink: &OutputSink, content: Option<String>, error: String) -> Self {
+        Self {
+            sink: sink.label().to_string(),
+            destination: sink.destination,
+            status: SinkStatus::Failed,
+            content,
+            error: Some(error),
+            model: None,
+        }
+    }
+
+    /// Records the model that formatted the content
+    ///
+    /// # Arguments
+    /// * `model` - Name of the model
+    pub fn with_model(mut self, model: String) -> Self {
+        self.model = Some(model);
+        self
+    }
+}
+
+/// Delivers formatted content to the destination of a sink
+///
+/// The `database` destination has nothing to deliver here: every sink result is
+/// stored with the task outputs by the task manager.
+///
+/// # Arguments
+/// * `sink` - The sink to deliver
+/// * `content` - The formatted content
+///
+/// # Returns
+/// * `Result<(), String>` - Ok if delivered, or a description of the failure
+pub async fn deliver(sink: &OutputSink, content: &str) -> Result<(), String> {
+    match sink.destination {
+        SinkDestination::File => {
+            let file = sink
+                .file
+                .as_deref()
+                .ok_or("the file destination requires a file")?;
+            fs::write(file, content.as_bytes())
+                .map_err(|e| format!("Failed to write output file: {}", e))?;
+            info!("Output '{}' written to file {}", sink.label(), file);
+        }
+        SinkDestination::Stdout => println!("{}", content),
+        SinkDestination::Database => {}
+        SinkDestination::Webhook => {
+            let url = sink
+                .url
+                .as_deref()
+                .ok_or("the webhook destination requires a url")?;
+            let content_type = if sink.format.eq_ignore_ascii_case("json") {
+                "application/json"
+            } else {
+                "text/plain; charset=utf-8"
+            };
+            http_client()
+                .post(url)
+                .header(reqwest::header::CONTENT_TYPE, content_type)
+                .body(content.to_string())
+                .send()
+                .await
+                .and_then(|res| res.error_for_status())
+                .map_err(|e| format!("Failed to call webhook {}: {}", url, e))?;
+            info!("Output '{}' sent to webhook {}", sink.label(), url);
+        }
+    }
+    Ok(())
+}
@@ -1,10 +1,12 @@
 use super::task_context::TaskContext;
 use super::task_state::TaskState;
+use super::SinkResult;
 use crate::config::TaskConfig;
 use crate::constants::MAX_PROPOSER_FEEDBACK_COUNT;
 use crate::core::process_task_context;
 use crate::db::Task as DbTask;
-use crate::llm::ChatMessage;
+use crate::errors::Error;
+use crate::llm::{ChatMessage, ContentPart};
 use serde_json::Value;
 use std::str::FromStr;
 
@@ -28,6 +30,8 @@ pub struct Task {
     pub current_proposal: Option<String>,
     /// Final output of the task
     pub final_output: Option<Value>,
+    /// Result of every output sink for the last formatting run
+    pub sink_results: Vec<SinkResult>,
     /// Feedback history
     pub feedback_history: Vec<String>,
     /// Module execution history
@@ -63,6 +67,7 @@ impl Task {
             proposal_history: Vec::new(),
             current_proposal: None,
             final_output: None,
+            sink_results: Vec::new(),
             feedback_history: Vec::new(),
             module_execution_history: Vec::new(),
             conversation: Vec::new(),
@@ -103,6 +108,22 @@ impl Task {
         self.feedback_history.join("\n")
     }
 
+    /// Returns the attachments of the context not yet sent in the conversation
+    ///
+    /// The attachments go with the first user message only, so that they are not sent
+    /// again on every turn.
+    ///
+    /// # Returns
+    ///
+    /// The images and documents to send with the next prompt, empty once they were sent
+    pub fn unsent_attachment_parts(&self) -> Vec<ContentPart> {
+        self.context
+            .attachment_parts()
+            .into_iter()
+            .filter(|part| !self.conversation.iter().any(|msg| msg.parts.contains(part)))
+            .collect()
+    }
+
     /// Adds a new module execution to the task's history
     ///
     /// # Arguments
@@ -133,6 +154,7 @@ impl From<DbTask> for Task {
             proposal_history: Vec::new(),
             current_proposal: None,
             final_output: None,
+            sink_results: Vec::new(),
             feedback_history: Vec::new(),
             module_execution_history: Vec::new(),
             conversation: Vec::new(),
@@ -142,10 +164,12 @@ impl From<DbTask> for Task {
     }
 }
 
-impl From<(DbTask, TaskConfig)> for Task {
-    fn from((db_task, task_config): (DbTask, TaskConfig)) -> Self {
-        let context = process_task_context(&task_config);
-        Self {
+impl TryFrom<(DbTask, TaskConfig)> for Task {
+    type Error = Error;
+
+    fn try_from((db_task, task_config): (DbTask, TaskConfig)) -> Result<Self, Error> {
+        let context = process_task_context(&task_config)?;
+        Ok(Self {
             task_id: db_task.task_id,
             name: db_task.name.unwrap_or("".to_string()),
             description: db_task.description.unwrap_or("".to_string()),
@@ -154,11 +178,12 @@ impl From<(DbTask, TaskConfig)> for Task {
             proposal_history: Vec::new(),
             current_proposal: None,
             final_output: None,
+            sink_results: Vec::new(),
             feedback_history: Vec::new(),
             module_execution_history: Vec::new(),
             conversation: Vec::new(),
             interval: db_task.interval,
             last_run_at: db_task.last_run_at,
-        }
+        })
     }
 }
@@ -1,3 +1,4 @@
+use crate::llm::ContentPart;
 use serde::{Deserialize, Serialize};
 
 /// Represents the context for a task, containing both file-based and text-based content
@@ -7,6 +8,9 @@ pub struct TaskContext {
     pub files: Vec<(String, String)>,
     /// Raw text content for the task
     pub text: String,
+    /// Images and documents attached to the prompts, with their names
+    #[serde(default)]
+    pub attachments: Vec<(String, ContentPart)>,
 }
 
 impl TaskContext {
@@ -18,6 +22,7 @@ impl TaskContext {
         Self {
             files: vec![],
             text: String::new(),
+            attachments: vec![],
         }
     }
 
@@ -25,12 +30,13 @@ impl TaskContext {
     ///
     /// # Description
     /// Merges both text content and file contents into a formatted string,
-    /// with clear section headers for each type of content.
+    /// with clear section headers for each type of content. Attachments are
+    /// listed by name, their content being sent as message parts.
     ///
     /// # Returns
     /// * A String containing the combined context, or empty string if no content exists
     pub fn combined_context(&self) -> String {
-        if self.text.trim().is_empty() && self.files.is_empty() {
+        if self.text.trim().is_empty() && self.files.is_empty() && self.attachments.is_empty() {
             return String::new();
         }
 
@@ -60,6 +66,26 @@ impl TaskContext {
             }
         }
 
+        if !self.attachments.is_empty() {
+            combined.push_str("Attached files:\n");
+            for (alias, _) in &self.attachments {
+                combined.push_str("- '");
+                combined.push_str(alias);
+                combined.push_str("'\n");
+            }
+        }
+
         combined
     }
+
+    /// Returns the message parts of the attachments
+    ///
+    /// # Returns
+    /// * `Vec<ContentPart>` - The images and documents to send with a prompt
+    pub fn attachment_parts(&self) -> Vec<ContentPart> {
+        self.attachments
+            .iter()
+            .map(|(_, part)| part.clone())
+            .collect()
+    }
 }
@@ -166,6 +166,36 @@ fn extract_and_validate_yaml(response: &str) -> Option<(String, bool)> {
     Some((yaml_content, true))
 }
 
+/// Describes the inputs supplied with a task so the generated configuration can reference them
+///
+/// # Arguments
+/// * `task_model` - The database task holding the JSON serialized inputs
+///
+/// # Returns
+/// * `String` - A prompt fragment listing the input placeholders, empty if there are no inputs
+fn inputs_hint(task_model: &Task) -> String {
+    let names: Vec<String> = task_model
+        .inputs
+        .as_deref()
+        .and_then(|inputs| {
+            serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(inputs).ok()
+        })
+        .map(|inputs| {
+            inputs
+                .keys()
+                .map(|name| format!("{{{{ inputs.{} }}}}", name))
+                .collect()
+        })
+        .unwrap_or_default();
+    if names.is_empty() {
+        return String::new();
+    }
+    format!(
+        "\n\nThe following input placeholders are available and must be used verbatim where their values are needed: {}",
+        names.join(", ")
+    )
+}
+
 /// Generates a task configuration from a task model without user interaction
 ///
 /// # Arguments
@@ -194,8 +224,9 @@ pub async fn generate_task_config_from_task(
             "user",
             &format!(
                 "Using the task model details below, generate a complete and valid task configuration. \
-                Ensure all values are appropriate for the task requirements:\n{}",
-                task_model.description.clone().unwrap_or_default()
+                Ensure all values are appropriate for the task requirements:\n{}{}",
+                task_model.description.clone().unwrap_or_default(),
+                inputs_hint(task_model)
             ),
         ),
     ];
@@ -206,7 +237,7 @@ pub async fn generate_task_config_from_task(
             Ok(response) => break response,
             Err(e) => {
                 if retries >= 3 {
-                    panic!("LLM API call failed after 3 retries: {}", e);
+                    return Err(format!("LLM API call failed after 3 retries: {}", e));
                 }
                 tokio::time::sleep(std::time::Duration::from_secs(1)).await;
                 retries += 1;
@@ -18,6 +18,8 @@ pub enum TaskState {
     InProgress,
     /// State when the task is waiting for a specific interval to pass
     WaitingWakeUp,
+    /// State when the task finished early because it hit one of its limits, includes the limit
+    LimitReached(String),
 }
 
 #[allow(clippy::to_string_trait_impl)]
@@ -32,6 +34,7 @@ impl ToString for TaskState {
             TaskState::Ready => "Ready".to_string(),
             TaskState::Configuring => "Configuring".to_string(),
             TaskState::WaitingWakeUp => "WaitingWakeUp".to_string(),
+            TaskState::LimitReached(reason) => format!("LimitReached: {}", reason),
         }
     }
 }
@@ -55,7 +58,11 @@ impl FromStr for TaskState {
             "Failed" => Ok(TaskState::Failed(String::new())),
             "Ready" => Ok(TaskState::Ready),
             "Configuring" => Ok(TaskState::Configuring),
-            _ => Err(()),
+            "LimitReached" => Ok(TaskState::LimitReached(String::new())),
+            _ => s
+                .strip_prefix("LimitReached: ")
+                .map(|reason| TaskState::LimitReached(reason.to_string()))
+                .ok_or(()),
         }
     }
 }
@@ -1,5 +1,6 @@
 use super::TaskWorker;
 use crate::agents::AgentOutcome;
+use crate::config::LimitPolicy;
 use crate::core::Task;
 use crate::event::Event;
 use tracing::debug;
@@ -7,6 +8,8 @@ use tracing::debug;
 impl TaskWorker {
     /// Handles the response from an agent by processing different outcome types
     ///
+    /// Outcomes of parallel branches abandoned when a limit was hit are dropped.
+    ///
     /// # Arguments
     /// * `current_role` - The role of the agent that generated the response
     /// * `agent_outcome` - The outcome returned by the agent
@@ -17,6 +20,10 @@ impl TaskWorker {
         agent_outcome: AgentOutcome,
         task: Task,
     ) {
+        if self.abandoned_branches.contains(&current_role) {
+            debug!("Dropping the outcome of abandoned branch {}", current_role);
+            return;
+        }
         match agent_outcome {
             AgentOutcome::ModuleRequest(module_name, action, params) => {
                 self.handle_module_request(module_name, action, params, &current_role, task)
@@ -26,7 +33,20 @@ impl TaskWorker {
                 self.handle_failed_outcome(error_message, &current_role, task)
                     .await;
             }
+            AgentOutcome::Fatal(error_message) => {
+                if let Some(group_name) = self.pending_group_for(&current_role) {
+                    self.handle_branch_failure(group_name, current_role, error_message)
+                        .await;
+                    return;
+                }
+                self.handle_fatal_outcome(error_message, &current_role, task);
+            }
             other_outcome => {
+                if let Some(group_name) = self.pending_group_for(&current_role) {
+                    self.handle_branch_outcome(group_name, current_role, other_outcome, task)
+                        .await;
+                    return;
+                }
                 let mut current_role = current_role.clone();
                 self.handle_standard_outcome(other_outcome, &mut current_role, task)
                     .await;
@@ -37,26 +57,49 @@ impl TaskWorker {
     /// Executes a specific role in the task workflow
     ///
     /// Sends appropriate events to notify the task manager and role handler about the execution.
+    /// If `role` names a parallel group, every branch of the group is started instead.
+    /// If the task has exceeded one of its limits, the `on_limit` policy is applied instead,
+    /// unless the `format` policy applies and the formatter was about to run anyway.
     ///
     /// # Arguments
     /// * `role` - The role to execute (e.g., "proposer", "reviewer")
     /// * `task` - The task to be processed by the role
     pub async fn execute_role(&mut self, role: &str, task: Task) {
+        let formats_on_limit = self.config.parameters.limits.on_limit == LimitPolicy::Format;
+        if self.limit_reached.is_none() && !(formats_on_limit && role == "formatter") {
+            if let Some(reason) = self.exceeded_limit() {
+                self.handle_limit_reached(reason, task).await;
+                return;
+            }
+        }
+        if let Some(group) = self.workflow.parallel_group(role).cloned() {
+            self.start_parallel_group(group, task);
+            return;
+        }
+        self.dispatch_role(role, task);
+    }
+
+    /// Sends the task to the agent playing a single role
+    ///
+    /// # Arguments
+    /// * `role` - The role to execute
+    /// * `task` - The task to be processed by the role
+    pub fn dispatch_role(&self, role: &str, task: Task) {
         debug!("Executing role {}", role);
 
         let human_message = match role {
-            "proposer" => "🤔 The proposer is preparing a new proposal...",
-            "reviewer" => "🔍 The reviewer is examining the proposal...",
-            "validator" => "✅ The validator is checking correctness...",
-            "formatter" => "✨ The formatter is refining the final output...",
-            _ => "❓ An unknown agent is acting...",
+            "proposer" => "🤔 The proposer is preparing a new proposal...".to_string(),
+            "reviewer" => "🔍 The reviewer is examining the proposal...".to_string(),
+            "validator" => "✅ The validator is checking correctness...".to_string(),
+            "formatter" => "✨ The formatter is refining the final output...".to_string(),
+            _ if self.custom_txs.contains_key(role) => {
+                format!("🧩 The {} is taking its turn...", role)
+            }
+            _ => "❓ An unknown agent is acting...".to_string(),
         };
 
         if let Some(tx) = self.get_manager_tx() {
-            let _ = tx.send(Event::NewMessage(
-                self.task_id.clone(),
-                human_message.to_string(),
-            ));
+            let _ = tx.send(Event::NewMessage(self.task_id.clone(), human_message));
             let _ = tx.send(Event::NewMessage(
                 self.task_id.clone(),
                 format!("🔄 {} is now working...", role),
@@ -16,6 +16,10 @@ impl TaskWorker {
     /// 2. Update the task state
     /// 3. Notify the task manager
     ///
+    /// Branches of a running parallel group keep their own retry count, and a branch
+    /// that fails permanently is recorded as a non-approving verdict instead of failing
+    /// the task.
+    ///
     /// # Arguments
     /// * `reason` - The error message explaining why the agent failed
     /// * `current_role` - The role that was executing when the failure occurred
@@ -26,7 +30,8 @@ impl TaskWorker {
         current_role: &str,
         mut task: Task,
     ) {
-        let next_attempt = self.retry_count + 1;
+        let branch_retries = self.branch_retry_count(current_role);
+        let next_attempt = branch_retries.unwrap_or(self.retry_count) + 1;
         if next_attempt <= self.max_retries {
             let message = format!(
                 "The agent encountered an error. Retrying... Attempt {}/{}",
@@ -37,7 +42,11 @@ impl TaskWorker {
                 let _ = manager_tx.send(Event::NewMessage(self.task_id.clone(), message));
             }
 
-            self.retry_count = next_attempt;
+            if branch_retries.is_some() {
+                self.set_branch_retry_count(current_role, next_attempt);
+            } else {
+                self.retry_count = next_attempt;
+            }
             if let Some(last_success) = task
                 .conversation
                 .iter()
@@ -50,6 +59,12 @@ impl TaskWorker {
             return;
         }
 
+        if let Some(group_name) = self.pending_group_for(current_role) {
+            self.handle_branch_failure(group_name, current_role.to_string(), reason)
+                .await;
+            return;
+        }
+
         let message = format!("The task failed permanently: {}", reason);
         if let Some(manager_tx) = self.get_manager_tx() {
             let _ = manager_tx.send(Event::NewMessage(self.task_id.clone(), message));
@@ -64,4 +79,27 @@ impl TaskWorker {
             let _ = manager_tx.send(Event::TaskStateUpdated(self.task_id.clone(), task.state));
         }
     }
+
+    /// Handles an agent failure that retrying cannot fix
+    ///
+    /// The role is not replayed: the task is marked as failed right away.
+    ///
+    /// # Arguments
+    /// * `reason` - The error message explaining why the agent failed
+    /// * `current_role` - The role that was executing when the failure occurred
+    /// * `task` - The task being processed
+    pub fn handle_fatal_outcome(&self, reason: String, current_role: &str, mut task: Task) {
+        let message = format!(
+            "The task failed permanently in role '{}' (not retryable): {}",
+            current_role, reason
+        );
+        if let Some(manager_tx) = self.get_manager_tx() {
+            let _ = manager_tx.send(Event::NewMessage(self.task_id.clone(), message));
+        }
+
+        task.state = TaskState::Failed(format!("Non-retryable error: {}", reason));
+        if let Some(manager_tx) = self.get_manager_tx() {
+            let _ = manager_tx.send(Event::TaskStateUpdated(self.task_id.clone(), task.state));
+        }
+    }
 }
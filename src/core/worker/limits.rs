@@ -0,0 +1,102 @@
+use crate::config::LimitPolicy;
+use crate::core::task_state::TaskState;
+use crate::core::Task;
+use crate::core::TaskWorker;
+use crate::event::Event;
+use std::sync::atomic::Ordering;
+
+impl TaskWorker {
+    /// Returns a description of the first limit the task has exceeded, if any
+    ///
+    /// A limit of N allows N revisions, module calls or LLM calls: every count includes
+    /// the action about to start, and the limit is exceeded once it goes past N. Limits
+    /// are checked between steps: a role already running may finish its LLM calls even
+    /// if they go past `max_llm_calls`.
+    ///
+    /// # Returns
+    /// * `Option<String>` - The exceeded limit, or None if the task is within its budget
+    pub fn exceeded_limit(&self) -> Option<String> {
+        let limits = &self.config.parameters.limits;
+
+        if let Some(max) = limits.max_revisions {
+            // The revision just requested is already counted
+            if self.revision_count > max {
+                return Some(format!("max_revisions ({}) exceeded", max));
+            }
+        }
+        if let Some(max) = limits.max_module_calls {
+            // The module call about to run is already counted
+            let calls: usize = self.module_call_counts.values().sum();
+            if calls > max {
+                return Some(format!("max_module_calls ({}) exceeded", max));
+            }
+        }
+        if let Some(max) = limits.max_llm_calls {
+            // The next step makes at least one more call
+            let calls = self.llm_calls.load(Ordering::Relaxed) + 1;
+            if calls > max {
+                return Some(format!("max_llm_calls ({}) exceeded", max));
+            }
+        }
+        if let Some(max) = self.max_duration {
+            if self.started_at.elapsed() > max {
+                return Some(format!(
+                    "max_duration ({}) exceeded",
+                    humantime::format_duration(max)
+                ));
+            }
+        }
+        None
+    }
+
+    /// Applies the `on_limit` policy once the task has exceeded one of its limits
+    ///
+    /// Depending on the policy the task fails or the formatter is run on the best
+    /// proposal so far. Parallel groups still running are abandoned, and the late
+    /// outcomes of their branches are dropped.
+    ///
+    /// # Arguments
+    /// * `reason` - Description of the exceeded limit
+    /// * `task` - The task being processed
+    pub async fn handle_limit_reached(&mut self, reason: String, mut task: Task) {
+        self.limit_reached = Some(reason.clone());
+        for (_, join) in self.pending_joins.drain() {
+            self.abandoned_branches.extend(join.remaining);
+        }
+
+        let policy = self.config.parameters.limits.on_limit;
+        if let Some(manager_tx) = self.get_manager_tx() {
+            let _ = manager_tx.send(Event::NewMessage(
+                self.task_id.clone(),
+                format!("⛔ Limit reached: {}", reason),
+            ));
+        }
+
+        let best_proposal = task
+            .current_proposal
+            .clone()
+            .or_else(|| task.proposal_history.last().cloned());
+        task.state = match (policy, best_proposal) {
+            (LimitPolicy::Format, Some(proposal)) => {
+                if let Some(manager_tx) = self.get_manager_tx() {
+                    let _ = manager_tx.send(Event::NewMessage(
+                        self.task_id.clone(),
+                        "📦 Formatting the best proposal so far...".to_string(),
+                    ));
+                }
+                task.current_proposal = Some(proposal);
+                self.abandoned_branches.remove("formatter");
+                self.dispatch_role("formatter", task);
+                return;
+            }
+            (LimitPolicy::Format, None) => {
+                TaskState::Failed(format!("Limit reached: {} (no proposal to format)", reason))
+            }
+            (LimitPolicy::Fail, _) => TaskState::Failed(format!("Limit reached: {}", reason)),
+        };
+
+        if let Some(manager_tx) = self.get_manager_tx() {
+            let _ = manager_tx.send(Event::TaskStateUpdated(self.task_id.clone(), task.state));
+        }
+    }
+}
@@ -2,24 +2,38 @@
 mod execution;
 /// Module for handling failed task outcomes
 mod failed_outcome;
+/// Module for enforcing task limits
+mod limits;
 /// Module for handling module requests
 mod module_request;
+/// Module for handling parallel fan-out and join of workflow roles
+mod parallel;
 /// Module for handling standard task outcomes
 mod standard_outcome;
 /// Module for handling task completion
 mod task_completion;
 
 use crate::{
-    agents::{FormatterAgent, ProposerAgent, ReviewerAgent, ValidatorAgent},
+    agents::{FormatterAgent, GenericAgent, ProposerAgent, ReviewerAgent, ValidatorAgent},
     config::TaskConfig,
-    core::{rag::InMemoryVectorStore, task::Task, workflow::Workflow},
+    core::{
+        guard::GuardContext,
+        rag::InMemoryVectorStore,
+        task::Task,
+        workflow::{Workflow, START_ROLE},
+    },
     event::Event,
     llm::ChatMessage,
-    llm::OpenAIEmbedder,
+    llm::TaskEmbedder,
+    llm::UsageReporter,
     modules::ModulesManager,
     utils,
 };
-use std::collections::HashMap;
+use parallel::PendingJoin;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::AtomicUsize;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
 use tracing::{error, info};
 /// Default maximum number of retries for failed tasks
@@ -43,11 +57,21 @@ pub struct TaskWorker {
     /// Counter for tracking task revisions
     pub revision_count: usize,
     /// Vector store for RAG functionality
-    pub vector_store: InMemoryVectorStore<OpenAIEmbedder>,
+    pub vector_store: InMemoryVectorStore<TaskEmbedder>,
     /// Counter for tracking retry attempts
     pub retry_count: usize,
     /// Maximum number of retries allowed
     pub max_retries: usize,
+    /// Number of module calls requested so far, per module name
+    pub module_call_counts: HashMap<String, usize>,
+    /// Instant the worker was created, used for elapsed time in workflow guards
+    pub started_at: Instant,
+    /// Number of LLM calls made by the agents of the task
+    pub llm_calls: Arc<AtomicUsize>,
+    /// Maximum wall-clock duration of the run, parsed from `parameters.limits`
+    pub max_duration: Option<Duration>,
+    /// Limit the task has hit, if any
+    pub limit_reached: Option<String>,
     /// Channel sender for proposer agent
     pub proposer_tx: Option<UnboundedSender<Event>>,
     /// Channel sender for reviewer agent
@@ -56,6 +80,12 @@ pub struct TaskWorker {
     pub validator_tx: Option<UnboundedSender<Event>>,
     /// Channel sender for formatter agent
     pub formatter_tx: Option<UnboundedSender<Event>>,
+    /// Channel senders for user-defined roles, keyed by role name
+    pub custom_txs: HashMap<String, UnboundedSender<Event>>,
+    /// Parallel groups waiting for their branches, keyed by group name
+    pub pending_joins: HashMap<String, PendingJoin>,
+    /// Branches whose group was abandoned, and whose late outcomes are dropped
+    pub abandoned_branches: HashSet<String>,
     /// Channel sender for manager
     pub manager_tx: Option<UnboundedSender<Event>>,
     /// Channel sender for self
@@ -79,10 +109,16 @@ impl TaskWorker {
         mut task: Task,
         workflow: Workflow,
         config: TaskConfig,
-        vector_store: InMemoryVectorStore<OpenAIEmbedder>,
+        vector_store: InMemoryVectorStore<TaskEmbedder>,
         manager_tx: UnboundedSender<Event>,
     ) -> Self {
         let max_retries = config.parameters.max_retries.unwrap_or(DEFAULT_MAX_RETRIES);
+        let max_duration = config
+            .parameters
+            .limits
+            .max_duration
+            .as_deref()
+            .and_then(|duration| humantime::parse_duration(duration).ok());
         let (self_tx, self_rx) = unbounded_channel();
         let modules_manager = ModulesManager::new(config.modules.clone());
         let system_prompt = utils::generate_system_instructions(&config.agents, &modules_manager);
@@ -103,11 +139,19 @@ impl TaskWorker {
             vector_store,
             retry_count: 0,
             max_retries,
+            module_call_counts: HashMap::new(),
+            started_at: Instant::now(),
+            llm_calls: Arc::new(AtomicUsize::new(0)),
+            max_duration,
+            limit_reached: None,
             revision_count: 0,
             proposer_tx: None,
             reviewer_tx: None,
             validator_tx: None,
             formatter_tx: None,
+            custom_txs: HashMap::new(),
+            pending_joins: HashMap::new(),
+            abandoned_branches: HashSet::new(),
             manager_tx: Some(manager_tx),
             self_tx,
             self_rx,
@@ -137,7 +181,8 @@ impl TaskWorker {
     /// Gets the channel sender for a specific agent role
     ///
     /// # Arguments
-    /// * `role` - The role of the agent ("proposer", "reviewer", "validator", or "formatter")
+    /// * `role` - The role of the agent ("proposer", "reviewer", "validator", "formatter"
+    ///   or a user-defined role)
     ///
     /// # Returns
     /// * `Option<UnboundedSender<Event>>` - The channel sender if the role exists
@@ -147,7 +192,7 @@ impl TaskWorker {
             "reviewer" => self.reviewer_tx.clone(),
             "validator" => self.validator_tx.clone(),
             "formatter" => self.formatter_tx.clone(),
-            _ => None,
+            _ => self.custom_txs.get(role).cloned(),
         }
     }
 
@@ -159,61 +204,141 @@ impl TaskWorker {
         self.manager_tx.clone()
     }
 
-    /// Extracts LLM configuration from task config
+    /// Creates a channel forwarding the partial text of streamed responses to the manager
     ///
     /// # Arguments
-    /// * `config` - The task configuration
+    /// * `role` - The role of the agent streaming the responses
     ///
     /// # Returns
-    /// * `(&str, &str)` - Tuple of (llm_provider, llm_model)
-    pub fn extract_llm_config(config: &TaskConfig) -> (&str, &str) {
-        let llm_provider = config
-            .parameters
-            .llm_provider
-            .as_deref()
-            .expect("LLM provider is required");
-        let llm_model = config
-            .parameters
-            .llm_model
-            .as_deref()
-            .expect("LLM model is required");
-        (llm_provider, llm_model)
+    /// * `Option<UnboundedSender<String>>` - The progress channel, or None without a manager
+    pub fn progress_sender(&self, role: &str) -> Option<UnboundedSender<String>> {
+        let manager_tx = self.get_manager_tx()?;
+        let (progress_tx, mut progress_rx) = unbounded_channel::<String>();
+        let task_id = self.task_id.clone();
+        let role = role.to_string();
+        tokio::spawn(async move {
+            while let Some(partial) = progress_rx.recv().await {
+                let event = Event::LlmProgress(task_id.clone(), role.clone(), partial);
+                if manager_tx.send(event).is_err() {
+                    break;
+                }
+            }
+        });
+        Some(progress_tx)
+    }
+
+    /// Creates the reporter of the token usage of a role's LLM calls
+    ///
+    /// # Arguments
+    /// * `role` - The role of the agent making the calls
+    ///
+    /// # Returns
+    /// * `Option<UsageReporter>` - The reporter, or None without a manager
+    pub fn usage_reporter(&self, role: &str) -> Option<UsageReporter> {
+        let manager_tx = self.get_manager_tx()?;
+        Some(UsageReporter::new(
+            &self.task_id,
+            role,
+            self.config.parameters.pricing.clone(),
+            manager_tx,
+        ))
+    }
+
+    /// Builds a snapshot of the task state for evaluating workflow guards
+    ///
+    /// # Arguments
+    /// * `task` - The task being processed
+    ///
+    /// # Returns
+    /// * `GuardContext` - Current counters, elapsed time and proposal length
+    pub fn guard_context(&self, task: &Task) -> GuardContext {
+        GuardContext {
+            revision_count: self.revision_count,
+            retry_count: self.retry_count,
+            module_calls: self.module_call_counts.clone(),
+            elapsed_secs: self.started_at.elapsed().as_secs(),
+            proposal_length: task
+                .current_proposal
+                .as_deref()
+                .map(|proposal| proposal.chars().count())
+                .unwrap_or(0),
+        }
     }
 
     /// Runs the task worker, managing the lifecycle of the task
     pub async fn run(mut self) {
         info!("Starting task {}, name: {}", self.task_id, self.task.name);
-        let (llm_provider, llm_model) = Self::extract_llm_config(&self.config);
-        let output_format = self.config.output.format.clone();
-        let output_file = self.config.output.file.clone();
+        let parameters = &self.config.parameters;
+        let sinks = self.config.output.sinks.clone();
 
         let proposer_config = self.config.agents.proposer.clone();
-        let (proposer, proposer_tx) = ProposerAgent::new(proposer_config, llm_provider, llm_model);
+        let (mut proposer, proposer_tx) = ProposerAgent::new(proposer_config, parameters);
 
         let reviewer_config = self.config.agents.reviewer.clone();
-        let (reviewer, reviewer_tx) = ReviewerAgent::new(reviewer_config, llm_provider, llm_model);
+        let (mut reviewer, reviewer_tx) = ReviewerAgent::new(reviewer_config, parameters);
 
         let validator_config = self.config.agents.validator.clone();
-        let (validator, validator_tx) =
-            ValidatorAgent::new(validator_config, llm_provider, llm_model);
+        let (mut validator, validator_tx) = ValidatorAgent::new(validator_config, parameters);
 
         let formatter_config = self.config.agents.formatter.clone();
-        let (formatter, formatter_tx) = FormatterAgent::new(
-            formatter_config,
-            llm_provider,
-            llm_model,
-            output_format,
-            output_file,
-        );
+        let (mut formatter, formatter_tx) =
+            FormatterAgent::new(formatter_config, parameters, sinks);
+
+        let tools = self.modules_manager.tool_definitions();
+        for llm_client in [
+            &mut proposer.llm_client,
+            &mut reviewer.llm_client,
+            &mut validator.llm_client,
+        ] {
+            llm_client.set_tools(tools.clone());
+        }
+
+        let mut custom_agents = Vec::new();
+        let mut custom_txs = HashMap::new();
+        for (role, agent_config) in &self.config.agents.custom {
+            let (mut agent, agent_tx) =
+                GenericAgent::new(role.clone(), agent_config.clone(), parameters);
+            agent.llm_client.set_call_counter(self.llm_calls.clone());
+            agent.llm_client.set_tools(tools.clone());
+            agent.llm_client.set_role(role);
+            if let Some(progress) = self.progress_sender(role) {
+                agent.llm_client.set_progress(progress);
+            }
+            if let Some(usage) = self.usage_reporter(role) {
+                agent.llm_client.set_usage(usage);
+            }
+            custom_agents.push(agent);
+            custom_txs.insert(role.clone(), agent_tx);
+        }
+
+        for (role, llm_client) in [
+            ("proposer", &mut proposer.llm_client),
+            ("reviewer", &mut reviewer.llm_client),
+            ("validator", &mut validator.llm_client),
+            ("formatter", &mut formatter.llm_client),
+        ] {
+            llm_client.set_call_counter(self.llm_calls.clone());
+            llm_client.set_role(role);
+            if let Some(progress) = self.progress_sender(role) {
+                llm_client.set_progress(progress);
+            }
+            if let Some(usage) = self.usage_reporter(role) {
+                llm_client.set_usage(usage);
+            }
+        }
 
         self.set_agent_channels(proposer_tx, reviewer_tx, validator_tx, formatter_tx);
+        self.custom_txs = custom_txs;
 
         tokio::spawn(proposer.run_loop(self.self_tx.clone()));
         tokio::spawn(reviewer.run_loop(self.self_tx.clone()));
         tokio::spawn(validator.run_loop(self.self_tx.clone()));
         tokio::spawn(formatter.run_loop(self.self_tx.clone()));
+        for agent in custom_agents {
+            tokio::spawn(agent.run_loop(self.self_tx.clone()));
+        }
 
-        self.execute_role("proposer", self.task.clone()).await;
+        self.execute_role(START_ROLE, self.task.clone()).await;
 
         if let Some(manager_tx) = self.manager_tx.clone() {
             if let Err(e) = manager_tx.send(Event::CreateTask(self.task.clone())) {
@@ -2,7 +2,7 @@ use crate::core::task_state::TaskState;
 use crate::core::Task;
 use crate::core::TaskWorker;
 use crate::event::Event;
-use crate::llm::ChatMessage;
+use crate::llm::{ChatMessage, ContentPart};
 use tracing::error;
 
 impl TaskWorker {
@@ -39,14 +39,33 @@ impl TaskWorker {
             ));
         }
 
-        self.retry_count = 0;
+        if self.branch_retry_count(current_role).is_some() {
+            self.set_branch_retry_count(current_role, 0);
+        } else {
+            self.retry_count = 0;
+        }
+        *self
+            .module_call_counts
+            .entry(module_name.clone())
+            .or_insert(0) += 1;
+        if self.limit_reached.is_none() {
+            if let Some(reason) = self.exceeded_limit() {
+                self.handle_limit_reached(reason, task).await;
+                return;
+            }
+        }
         let module_cache_key = (module_name.clone(), action.clone(), params.clone());
-        if self.module_results_cache.contains_key(&module_cache_key) {
+        if let Some(result) = self.module_results_cache.get(&module_cache_key) {
             let message = "♻️ Module result already known, proceeding...";
             if let Some(manager_tx) = self.get_manager_tx() {
                 let _ =
                     manager_tx.send(Event::NewMessage(self.task_id.clone(), message.to_string()));
             }
+            if let Some(call_id) = pending_tool_call(&task) {
+                let result = format!("Module '{}' provided a result:\n{}", module_name, result);
+                task.conversation
+                    .push(ChatMessage::tool_result(&call_id, &result));
+            }
             self.execute_role(current_role, task.clone()).await;
             return;
         }
@@ -67,6 +86,10 @@ impl TaskWorker {
             let action_result = module
                 .handle_action(&mut self.vector_store, &action, &params)
                 .await;
+            let attachments = match &action_result {
+                Ok(_) => module.attachments(&action, &params),
+                Err(_) => Vec::new(),
+            };
 
             let execution_message = match &action_result {
                 Ok(result) => {
@@ -111,8 +134,7 @@ Available actions: {}",
                 }
             };
 
-            task.conversation
-                .push(ChatMessage::new("user", &execution_message));
+            push_module_result(&mut task, "user", &execution_message, attachments);
 
             let message = "⚙️ Module execution finished. Returning to the agent...";
             if let Some(manager_tx) = self.get_manager_tx() {
@@ -150,10 +172,53 @@ Available actions: {}",
                     .join("; ")
             );
 
-            task.conversation
-                .push(ChatMessage::new("assistant", &err_msg));
+            push_module_result(&mut task, "assistant", &err_msg, Vec::new());
             task.state = TaskState::Failed(format!("Module {} not found", module_name));
             self.execute_role(current_role, task.clone()).await;
         }
     }
 }
+
+/// Returns the ID of the tool call the last message of the conversation is waiting on
+///
+/// # Arguments
+/// * `task` - Task whose conversation is inspected
+///
+/// # Returns
+/// * `Option<String>` - The ID if the agent requested the module through native tool calling
+fn pending_tool_call(task: &Task) -> Option<String> {
+    task.conversation
+        .last()
+        .and_then(|msg| msg.tool_calls.first())
+        .map(|call| call.id.clone())
+}
+
+/// Records the result of a module request in the conversation
+///
+/// Answers the pending tool call with a tool message, or adds a plain message with
+/// the given role for requests made through the `MODULE_REQUEST:` text protocol.
+/// Attachments follow a tool message in a user message, since not every provider
+/// accepts images in tool results.
+///
+/// # Arguments
+/// * `task` - Task whose conversation receives the result
+/// * `role` - Role of the plain message used without a pending tool call
+/// * `content` - The module result or error
+/// * `attachments` - Images and documents attached by the module
+fn push_module_result(task: &mut Task, role: &str, content: &str, attachments: Vec<ContentPart>) {
+    match pending_tool_call(task) {
+        Some(call_id) => {
+            task.conversation
+                .push(ChatMessage::tool_result(&call_id, content));
+            if !attachments.is_empty() {
+                task.conversation.push(
+                    ChatMessage::new("user", "Files attached by the module:")
+                        .with_parts(attachments),
+                );
+            }
+        }
+        None => task
+            .conversation
+            .push(ChatMessage::new(role, content).with_parts(attachments)),
+    }
+}
@@ -0,0 +1,284 @@
+use crate::agents::AgentOutcome;
+use crate::config::{JoinPolicy, ParallelGroup};
+use crate::core::task_state::TaskState;
+use crate::core::Task;
+use crate::core::TaskWorker;
+use crate::event::Event;
+use crate::llm::ChatMessage;
+use std::collections::{HashMap, HashSet};
+
+/// Verdict of one branch of a parallel group
+#[derive(Debug)]
+pub struct BranchResult {
+    /// Role that ran the branch
+    pub role: String,
+    /// Whether the branch approved (anything but a revision request)
+    pub approved: bool,
+    /// Feedback given by the branch, if any
+    pub feedback: Option<String>,
+    /// Messages the branch added to the conversation, summaries of its turns included
+    pub messages: Vec<ChatMessage>,
+}
+
+/// State of a parallel group waiting for its branches to finish
+#[derive(Debug)]
+pub struct PendingJoin {
+    /// The group being executed
+    pub group: ParallelGroup,
+    /// Task as it was when the group started
+    pub task: Task,
+    /// Branches that have not answered yet
+    pub remaining: HashSet<String>,
+    /// Verdicts received so far
+    pub results: Vec<BranchResult>,
+    /// Number of retries used by each branch after a failure
+    pub retry_counts: HashMap<String, usize>,
+}
+
+impl PendingJoin {
+    /// Combines the branch verdicts into a single outcome and task
+    ///
+    /// The messages added by every branch are appended to the base task in branch order,
+    /// and the feedback of all branches is merged into a single `feedback_history` entry.
+    ///
+    /// # Returns
+    /// * `(AgentOutcome, Task)` - Approved or RevisionRequested according to the join policy,
+    ///   and the merged task
+    pub fn merge(mut self) -> (AgentOutcome, Task) {
+        let order = &self.group.branches;
+        self.results.sort_by_key(|result| {
+            order
+                .iter()
+                .position(|role| role == &result.role)
+                .unwrap_or(usize::MAX)
+        });
+
+        let mut task = self.task;
+        let mut feedbacks = vec![];
+        for result in &mut self.results {
+            task.conversation.append(&mut result.messages);
+            if let Some(feedback) = result.feedback.as_deref().filter(|f| !f.is_empty()) {
+                feedbacks.push(format!("[{}] {}", result.role, feedback));
+            }
+        }
+
+        if feedbacks.is_empty() {
+            task.set_feedback(None);
+        } else {
+            task.set_feedback(Some(feedbacks.join("\n")));
+        }
+
+        let total = self.group.branches.len();
+        let approvals = self.results.iter().filter(|result| result.approved).count();
+        let approved = match self.group.join {
+            JoinPolicy::AllApproved => approvals == total,
+            JoinPolicy::AnyApproved => approvals > 0,
+            JoinPolicy::Majority => approvals * 2 > total,
+        };
+
+        let outcome = if approved {
+            AgentOutcome::Approved
+        } else {
+            AgentOutcome::RevisionRequested
+        };
+        (outcome, task)
+    }
+}
+
+impl TaskWorker {
+    /// Sends a copy of the task to every branch of a parallel group
+    ///
+    /// The messages of the copies are marked as inherited so that each branch only
+    /// contributes the messages it added, even once its conversation is compacted.
+    ///
+    /// # Arguments
+    /// * `group` - The parallel group to start
+    /// * `task` - The task to fan out
+    pub fn start_parallel_group(&mut self, group: ParallelGroup, mut task: Task) {
+        if group.branches.is_empty() {
+            let message = format!("Parallel group '{}' has no branches", group.name);
+            if let Some(manager_tx) = self.get_manager_tx() {
+                let _ = manager_tx.send(Event::NewMessage(self.task_id.clone(), message.clone()));
+                task.state = TaskState::Failed(message);
+                let _ = manager_tx.send(Event::TaskStateUpdated(self.task_id.clone(), task.state));
+            }
+            return;
+        }
+
+        if let Some(manager_tx) = self.get_manager_tx() {
+            let _ = manager_tx.send(Event::NewMessage(
+                self.task_id.clone(),
+                format!(
+                    "🔀 Running '{}' in parallel: {}",
+                    group.name,
+                    group.branches.join(", ")
+                ),
+            ));
+        }
+
+        let mut branch_task = task.clone();
+        for msg in &mut branch_task.conversation {
+            msg.inherited = true;
+        }
+        for branch in &group.branches {
+            self.dispatch_role(branch, branch_task.clone());
+        }
+
+        self.pending_joins.insert(
+            group.name.clone(),
+            PendingJoin {
+                remaining: group.branches.iter().cloned().collect(),
+                group,
+                task,
+                results: vec![],
+                retry_counts: HashMap::new(),
+            },
+        );
+    }
+
+    /// Returns the name of the pending parallel group waiting for a role, if any
+    ///
+    /// # Arguments
+    /// * `role` - The role that produced an outcome
+    pub fn pending_group_for(&self, role: &str) -> Option<String> {
+        self.pending_joins
+            .iter()
+            .find(|(_, join)| join.remaining.contains(role))
+            .map(|(name, _)| name.clone())
+    }
+
+    /// Records the verdict of a parallel branch and joins the group once all branches answered
+    ///
+    /// # Arguments
+    /// * `group_name` - Name of the pending group
+    /// * `role` - The branch role that finished
+    /// * `outcome` - The outcome returned by the branch
+    /// * `task` - The branch's copy of the task
+    pub async fn handle_branch_outcome(
+        &mut self,
+        group_name: String,
+        role: String,
+        outcome: AgentOutcome,
+        task: Task,
+    ) {
+        let approved = outcome.as_condition() != "revision_requested";
+        let feedback = if approved {
+            None
+        } else {
+            task.feedback_history.last().cloned()
+        };
+        let status = if approved {
+            "approved"
+        } else {
+            "requested a revision"
+        };
+        let messages = task
+            .conversation
+            .into_iter()
+            .filter(|msg| !msg.inherited)
+            .collect();
+        self.record_branch_result(group_name, role, status, approved, feedback, messages)
+            .await;
+    }
+
+    /// Records a branch that failed for good as a non-approving verdict
+    ///
+    /// The branch contributes no messages to the merged task, and the join policy
+    /// decides whether the group can still be approved by the other branches.
+    ///
+    /// # Arguments
+    /// * `group_name` - Name of the pending group
+    /// * `role` - The branch role that failed
+    /// * `reason` - The error that made the branch fail
+    pub async fn handle_branch_failure(
+        &mut self,
+        group_name: String,
+        role: String,
+        reason: String,
+    ) {
+        let status = format!("failed permanently: {}", reason);
+        self.record_branch_result(group_name, role, &status, false, None, vec![])
+            .await;
+    }
+
+    /// Returns the number of retries a branch has used, if the role is a pending branch
+    ///
+    /// # Arguments
+    /// * `role` - The role that failed
+    pub fn branch_retry_count(&self, role: &str) -> Option<usize> {
+        let group_name = self.pending_group_for(role)?;
+        let join = self.pending_joins.get(&group_name)?;
+        Some(join.retry_counts.get(role).copied().unwrap_or(0))
+    }
+
+    /// Sets the number of retries used by a pending branch
+    ///
+    /// # Arguments
+    /// * `role` - The branch role
+    /// * `count` - The number of retries used
+    pub fn set_branch_retry_count(&mut self, role: &str, count: usize) {
+        if let Some(join) = self
+            .pending_joins
+            .values_mut()
+            .find(|join| join.remaining.contains(role))
+        {
+            join.retry_counts.insert(role.to_string(), count);
+        }
+    }
+
+    /// Stores a branch verdict and merges the group once every branch has answered
+    ///
+    /// # Arguments
+    /// * `group_name` - Name of the pending group
+    /// * `role` - The branch role that answered
+    /// * `status` - Description of the verdict shown to the user
+    /// * `approved` - Whether the branch approved
+    /// * `feedback` - Feedback given by the branch, if any
+    /// * `messages` - Messages the branch added to the conversation
+    async fn record_branch_result(
+        &mut self,
+        group_name: String,
+        role: String,
+        status: &str,
+        approved: bool,
+        feedback: Option<String>,
+        messages: Vec<ChatMessage>,
+    ) {
+        let Some(join) = self.pending_joins.get_mut(&group_name) else {
+            return;
+        };
+
+        join.remaining.remove(&role);
+        join.retry_counts.remove(&role);
+        join.results.push(BranchResult {
+            role: role.clone(),
+            approved,
+            feedback,
+            messages,
+        });
+
+        let message = format!(
+            "🔀 Branch '{}' of '{}' {} ({}/{})",
+            role,
+            group_name,
+            status,
+            join.results.len(),
+            join.group.branches.len()
+        );
+        let done = join.remaining.is_empty();
+        if let Some(manager_tx) = self.get_manager_tx() {
+            let _ = manager_tx.send(Event::NewMessage(self.task_id.clone(), message));
+        }
+        if !done {
+            return;
+        }
+
+        let Some(join) = self.pending_joins.remove(&group_name) else {
+            return;
+        };
+        let (outcome, task) = join.merge();
+        let mut current_role = group_name;
+        self.handle_standard_outcome(outcome, &mut current_role, task)
+            .await;
+    }
+}
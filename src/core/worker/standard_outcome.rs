@@ -1,6 +1,7 @@
 use crate::agents::AgentOutcome;
 use crate::core::Task;
 use crate::core::TaskWorker;
+use crate::core::COMPLETED_ROLE;
 use crate::event::Event;
 use tracing::error;
 
@@ -28,7 +29,11 @@ impl TaskWorker {
     ) {
         self.retry_count = 0;
         let condition = outcome.as_condition();
-        match self.workflow.next_role(current_role, condition) {
+        let guard_context = self.guard_context(&task);
+        match self
+            .workflow
+            .next_role(current_role, condition, &guard_context)
+        {
             Some(next_role) => {
                 if condition == "revision_requested" {
                     let message = format!(
@@ -40,7 +45,6 @@ impl TaskWorker {
                             .send(Event::NewMessage(self.task_id.clone(), message.to_string()));
                     }
                     self.revision_count += 1;
-                    self.execute_role(&next_role, task.clone()).await;
                 }
 
                 if condition == "exported" || outcome == AgentOutcome::Exported {
@@ -51,14 +55,22 @@ impl TaskWorker {
                     if let Some(manager_tx) = self.get_manager_tx() {
                         let _ = manager_tx
                             .send(Event::NewMessage(self.task_id.clone(), message.to_string()));
-                        let _ = manager_tx.send(Event::NewOutput(
-                            self.task_id.clone(),
-                            task.final_output.clone().unwrap_or_default(),
-                        ));
+                        for result in &task.sink_results {
+                            let message = match &result.error {
+                                None => format!("📤 Output '{}' delivered", result.sink),
+                                Some(error) => {
+                                    format!("⚠️ Output '{}' failed: {}", result.sink, error)
+                                }
+                            };
+                            let _ =
+                                manager_tx.send(Event::NewMessage(self.task_id.clone(), message));
+                            let _ = manager_tx
+                                .send(Event::NewOutput(self.task_id.clone(), result.clone()));
+                        }
                     }
                 }
 
-                if next_role == "completed" {
+                if next_role == COMPLETED_ROLE {
                     self.handle_task_completion(task).await;
                     return;
                 }
@@ -9,7 +9,7 @@ impl TaskWorker {
     /// This function:
     /// 1. Sends completion notification message
     /// 2. Exports conversation history if configured
-    /// 3. Updates task state to completed
+    /// 3. Updates task state to completed, or to limit reached if the task hit one of its limits
     /// 4. Notifies task manager of completion
     /// 5. Sends task completed event
     ///
@@ -66,7 +66,9 @@ impl TaskWorker {
             // }
         }
 
-        if self.task.interval.is_some() {
+        if let Some(reason) = &self.limit_reached {
+            task.state = TaskState::LimitReached(reason.clone());
+        } else if self.task.interval.is_some() {
             task.state = TaskState::WaitingWakeUp;
         } else {
             task.state = TaskState::Completed;
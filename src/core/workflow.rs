@@ -1,10 +1,21 @@
-use crate::config::WorkflowStep;
+use crate::config::{ParallelGroup, WorkflowStep};
+use crate::core::guard::{GuardContext, GuardExpr};
+
+/// Role the workflow starts from
+pub const START_ROLE: &str = "proposer";
+
+/// Pseudo-role marking the end of the workflow
+pub const COMPLETED_ROLE: &str = "completed";
 
 /// Represents a workflow that defines transitions between different roles based on conditions
 #[derive(Debug, Clone)]
 pub struct Workflow {
     /// The list of workflow steps defining the transitions
     pub steps: Vec<WorkflowStep>,
+    /// Parsed guard of each step, None when the step is unconditional
+    guards: Vec<Option<GuardExpr>>,
+    /// Groups of roles executed in parallel
+    pub parallel: Vec<ParallelGroup>,
 }
 
 impl Workflow {
@@ -13,27 +24,69 @@ impl Workflow {
     /// # Arguments
     ///
     /// * `steps` - Vector of workflow steps defining the role transitions
+    /// * `parallel` - Groups of roles executed in parallel
     ///
     /// # Returns
     ///
-    /// A new Workflow instance initialized with the provided steps
-    pub fn new(steps: Vec<WorkflowStep>) -> Self {
-        Workflow { steps }
+    /// A new Workflow instance initialized with the provided steps, or a description of the
+    /// first invalid guard
+    pub fn new(steps: Vec<WorkflowStep>, parallel: Vec<ParallelGroup>) -> Result<Self, String> {
+        let guards = steps
+            .iter()
+            .map(|step| {
+                step.guard
+                    .as_deref()
+                    .map(|guard| {
+                        GuardExpr::parse(guard).map_err(|e| {
+                            format!(
+                                "invalid guard '{}' on step {} -> {}: {}",
+                                guard, step.from, step.to, e
+                            )
+                        })
+                    })
+                    .transpose()
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Workflow {
+            steps,
+            guards,
+            parallel,
+        })
+    }
+
+    /// Returns the parallel group with the given name
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name used as a role in workflow steps
+    ///
+    /// # Returns
+    ///
+    /// The group if `name` designates a parallel group, None otherwise
+    pub fn parallel_group(&self, name: &str) -> Option<&ParallelGroup> {
+        self.parallel.iter().find(|group| group.name == name)
     }
 
     /// Determines the next role based on the current role and condition
     ///
+    /// Steps are tried in declaration order and the first one whose `from`,
+    /// `condition` and guard all match wins.
+    ///
     /// # Arguments
     ///
     /// * `from` - The current role
     /// * `condition` - The condition that triggered the transition
+    /// * `ctx` - Task state used to evaluate step guards
     ///
     /// # Returns
     ///
     /// The next role as a String if a matching transition is found, None otherwise
-    pub fn next_role(&self, from: &str, condition: &str) -> Option<String> {
-        for step in &self.steps {
-            if step.from == from && step.condition == condition {
+    pub fn next_role(&self, from: &str, condition: &str, ctx: &GuardContext) -> Option<String> {
+        for (step, guard) in self.steps.iter().zip(&self.guards) {
+            if step.from == from
+                && step.condition == condition
+                && guard.as_ref().is_none_or(|guard| guard.evaluate(ctx))
+            {
                 return Some(step.to.clone());
             }
         }
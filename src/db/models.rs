@@ -1,4 +1,4 @@
-use crate::schema::{task_outputs, tasks};
+use crate::schema::{task_events, task_outputs, task_usage, tasks};
 use diesel::{AsChangeset, Identifiable, Insertable, Queryable};
 use serde::{Deserialize, Serialize};
 
@@ -40,6 +40,10 @@ pub struct Task {
     pub last_run_at: Option<chrono::NaiveDateTime>,
     /// Optional interval for the task
     pub interval: Option<String>,
+    /// Optional JSON serialized task input values
+    pub inputs: Option<String>,
+    /// Partial text of the LLM response currently being streamed
+    pub progress: Option<String>,
 }
 
 /// Represents the output of a task in the database
@@ -56,4 +60,64 @@ pub struct TaskOutput {
     pub created_at: String,
     /// Timestamp when the output was last updated
     pub updated_at: String,
+    /// Name of the output sink that produced the output
+    pub sink: Option<String>,
+    /// Whether the sink succeeded or failed ("succeeded" / "failed")
+    pub status: Option<String>,
+    /// Reason of the sink failure, if any
+    pub error: Option<String>,
+    /// Model that produced the output, which differs from the configured one after a fallback
+    pub model: Option<String>,
+}
+
+/// Represents an event of a task in the database
+#[derive(
+    Debug, Clone, Serialize, Deserialize, Queryable, Identifiable, AsChangeset, Insertable,
+)]
+#[diesel(table_name = task_events)]
+pub struct TaskEvent {
+    /// Optional unique identifier for the event
+    pub id: Option<String>,
+    /// Reference to the associated task
+    pub task_id: String,
+    /// Role the event relates to, if any
+    pub agent_role: Option<String>,
+    /// Kind of event (e.g. "llm_fallback")
+    pub event_type: String,
+    /// Optional JSON serialized details of the event
+    pub content: Option<String>,
+    /// Timestamp when the event was created
+    pub created_at: String,
+    /// Timestamp when the event was last updated
+    pub updated_at: String,
+}
+
+/// Represents the token usage of a task for one role and model in the database
+#[derive(
+    Debug, Clone, Serialize, Deserialize, Queryable, Identifiable, AsChangeset, Insertable,
+)]
+#[diesel(table_name = task_usage)]
+pub struct TaskUsage {
+    /// Optional unique identifier for the usage row
+    pub id: Option<String>,
+    /// Reference to the associated task
+    pub task_id: String,
+    /// Role that made the LLM calls
+    pub role: String,
+    /// Model that answered the LLM calls
+    pub model: String,
+    /// Number of LLM calls
+    pub calls: i64,
+    /// Total input tokens of the calls
+    pub input_tokens: i64,
+    /// Total output tokens of the calls
+    pub output_tokens: i64,
+    /// Cost of the calls, None if the model has no price
+    pub cost: Option<f64>,
+    /// Timestamp when the first call was recorded
+    pub created_at: String,
+    /// Timestamp when the last call was recorded
+    pub updated_at: String,
+    /// Number of calls answered from the response cache, included in `calls`
+    pub cache_hits: i64,
 }
@@ -1,7 +1,8 @@
 use crate::config::TaskConfig;
-use crate::core::{process_task_context, TaskState};
-use crate::db::models::{Task, TaskOutput};
+use crate::core::{process_task_context, SinkResult, TaskState};
+use crate::db::models::{Task, TaskEvent, TaskOutput, TaskUsage};
 use crate::errors::Error;
+use crate::llm::UsageEntry;
 use chrono::Utc;
 use diesel::prelude::*;
 use diesel::result::Error as DieselError;
@@ -42,6 +43,7 @@ impl<'a> TaskRepository<'a> {
     /// * `feedback_history` - Optional JSON serialized feedback history
     /// * `module_execution_history` - Optional JSON serialized module execution history
     /// * `conversation` - Optional JSON serialized conversation history
+    /// * `inputs` - Optional JSON serialized task input values
     ///
     /// # Returns
     ///
@@ -65,6 +67,7 @@ impl<'a> TaskRepository<'a> {
         conversation: Option<String>,
         last_run_at: Option<chrono::NaiveDateTime>,
         interval: Option<String>,
+        inputs: Option<String>,
     ) -> Result<String, Error> {
         use crate::schema::tasks;
 
@@ -97,6 +100,8 @@ impl<'a> TaskRepository<'a> {
             config: None,
             interval,
             last_run_at,
+            inputs,
+            progress: None,
         };
 
         diesel::insert_into(tasks::table)
@@ -145,7 +150,10 @@ impl<'a> TaskRepository<'a> {
     /// Returns an Error if database operations fail
     pub fn get_tasks_by_states(&mut self, filter_states: &[TaskState]) -> Result<Vec<Task>, Error> {
         use crate::schema::tasks::dsl::*;
-        let filter_states_str = filter_states.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+        let filter_states_str = filter_states
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>();
 
         let found_tasks = tasks
             .filter(state.eq_any(filter_states_str))
@@ -191,7 +199,7 @@ impl<'a> TaskRepository<'a> {
     ///
     /// # Errors
     ///
-    /// Returns an Error if database operations fail
+    /// Returns an Error if database operations fail or the context cannot be loaded
     pub fn update_task_config(
         &mut self,
         the_task_id: &str,
@@ -199,7 +207,7 @@ impl<'a> TaskRepository<'a> {
     ) -> Result<(), Error> {
         use crate::schema::tasks::dsl::{config as cfg, context, task_id, tasks};
 
-        let new_context = process_task_context(config);
+        let new_context = process_task_context(config)?;
         let combined_context = new_context.combined_context();
         let config_str = serde_json::to_string(config).unwrap_or_default();
 
@@ -263,12 +271,40 @@ impl<'a> TaskRepository<'a> {
         Ok(())
     }
 
-    /// Inserts a new row in `task_outputs` for the given logical `task_id`
+    /// Updates the partial text of the LLM response being streamed for a task
+    ///
+    /// # Arguments
+    ///
+    /// * `the_task_id` - The task ID to update
+    /// * `new_progress` - The text of the response received so far
+    ///
+    /// # Returns
+    ///
+    /// Unit type if successful
+    ///
+    /// # Errors
+    ///
+    /// Returns an Error if database operations fail
+    pub fn update_task_progress(
+        &mut self,
+        the_task_id: &str,
+        new_progress: &str,
+    ) -> Result<(), Error> {
+        use crate::schema::tasks::dsl::{progress, task_id, tasks, updated_at};
+        let now = Utc::now().to_rfc3339();
+
+        diesel::update(tasks.filter(task_id.eq(the_task_id)))
+            .set((progress.eq(new_progress), updated_at.eq(&now)))
+            .execute(self.conn)?;
+        Ok(())
+    }
+
+    /// Inserts a new row in `task_outputs` recording the result of an output sink
     ///
     /// # Arguments
     ///
     /// * `the_task_id` - The task ID to add output for
-    /// * `new_output` - The output content to add
+    /// * `result` - The result of the sink, with its content and status
     ///
     /// # Returns
     ///
@@ -277,10 +313,10 @@ impl<'a> TaskRepository<'a> {
     /// # Errors
     ///
     /// Returns a DieselError if database operations fail
-    pub fn update_task_output(
+    pub fn insert_sink_output(
         &mut self,
         the_task_id: &str,
-        new_output: &str,
+        result: &SinkResult,
     ) -> Result<(), DieselError> {
         use crate::schema::task_outputs::dsl::*;
         let now = Utc::now().to_rfc3339();
@@ -290,9 +326,13 @@ impl<'a> TaskRepository<'a> {
             .values((
                 id.eq(&row_id),
                 task_id.eq(the_task_id),
-                output.eq(new_output),
+                output.eq(result.content.as_deref().unwrap_or_default()),
                 created_at.eq(&now),
                 updated_at.eq(&now),
+                sink.eq(&result.sink),
+                status.eq(result.status.to_string()),
+                error.eq(result.error.as_deref()),
+                model.eq(result.model.as_deref()),
             ))
             .execute(self.conn)?;
 
@@ -347,6 +387,166 @@ impl<'a> TaskRepository<'a> {
         Ok(result)
     }
 
+    /// Adds the token usage of LLM calls to the totals of a task, per role and model
+    ///
+    /// # Arguments
+    ///
+    /// * `the_task_id` - The task ID the calls belong to
+    /// * `entry` - The usage of the calls, with their role, model and cost
+    ///
+    /// # Returns
+    ///
+    /// Unit type if successful
+    ///
+    /// # Errors
+    ///
+    /// Returns a DieselError if database operations fail
+    pub fn record_task_usage(
+        &mut self,
+        the_task_id: &str,
+        entry: &UsageEntry,
+    ) -> Result<(), DieselError> {
+        use crate::schema::task_usage::dsl::*;
+        let now = Utc::now().to_rfc3339();
+
+        let existing = task_usage
+            .filter(task_id.eq(the_task_id))
+            .filter(role.eq(&entry.role))
+            .filter(model.eq(&entry.model))
+            .first::<TaskUsage>(self.conn)
+            .optional()?;
+
+        match existing {
+            Some(found) => {
+                let new_cost = match (found.cost, entry.cost) {
+                    (None, None) => None,
+                    (found_cost, entry_cost) => {
+                        Some(found_cost.unwrap_or_default() + entry_cost.unwrap_or_default())
+                    }
+                };
+                diesel::update(task_usage.filter(id.eq(&found.id)))
+                    .set((
+                        calls.eq(found.calls + entry.calls as i64),
+                        cache_hits.eq(found.cache_hits + entry.cache_hits as i64),
+                        input_tokens.eq(found.input_tokens + entry.input_tokens as i64),
+                        output_tokens.eq(found.output_tokens + entry.output_tokens as i64),
+                        cost.eq(new_cost),
+                        updated_at.eq(&now),
+                    ))
+                    .execute(self.conn)?;
+            }
+            None => {
+                let new_usage = TaskUsage {
+                    id: Some(Uuid::new_v4().to_string()),
+                    task_id: the_task_id.to_string(),
+                    role: entry.role.clone(),
+                    model: entry.model.clone(),
+                    calls: entry.calls as i64,
+                    input_tokens: entry.input_tokens as i64,
+                    output_tokens: entry.output_tokens as i64,
+                    cost: entry.cost,
+                    created_at: now.clone(),
+                    updated_at: now,
+                    cache_hits: entry.cache_hits as i64,
+                };
+                diesel::insert_into(task_usage)
+                    .values(&new_usage)
+                    .execute(self.conn)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Retrieves the token usage of a task by `task_id`, per role and model
+    ///
+    /// # Arguments
+    ///
+    /// * `the_task_id` - The task ID to get the usage for
+    ///
+    /// # Returns
+    ///
+    /// A vector of usage rows, ordered by role and model
+    ///
+    /// # Errors
+    ///
+    /// Returns a DieselError if database operations fail
+    pub fn get_task_usage(&mut self, the_task_id: &str) -> Result<Vec<TaskUsage>, DieselError> {
+        use crate::schema::task_usage::dsl::*;
+        let result = task_usage
+            .filter(task_id.eq(the_task_id))
+            .order_by((role.asc(), model.asc()))
+            .load::<TaskUsage>(self.conn)?;
+        Ok(result)
+    }
+
+    /// Inserts a new row in `task_events` for the given logical `task_id`
+    ///
+    /// # Arguments
+    ///
+    /// * `the_task_id` - The task ID the event belongs to
+    /// * `role` - The role the event relates to, if any
+    /// * `kind` - The kind of event (e.g. "llm_fallback")
+    /// * `details` - The JSON serialized details of the event, if any
+    ///
+    /// # Returns
+    ///
+    /// Unit type if successful
+    ///
+    /// # Errors
+    ///
+    /// Returns a DieselError if database operations fail
+    pub fn insert_task_event(
+        &mut self,
+        the_task_id: &str,
+        role: Option<&str>,
+        kind: &str,
+        details: Option<&str>,
+    ) -> Result<(), DieselError> {
+        use crate::schema::task_events::dsl::*;
+        let now = Utc::now().to_rfc3339();
+        let new_event = TaskEvent {
+            id: Some(Uuid::new_v4().to_string()),
+            task_id: the_task_id.to_string(),
+            agent_role: role.map(str::to_string),
+            event_type: kind.to_string(),
+            content: details.map(str::to_string),
+            created_at: now.clone(),
+            updated_at: now,
+        };
+        diesel::insert_into(task_events)
+            .values(&new_event)
+            .execute(self.conn)?;
+        Ok(())
+    }
+
+    /// Retrieves the events of one kind of a task by `task_id`, oldest first
+    ///
+    /// # Arguments
+    ///
+    /// * `the_task_id` - The task ID to get the events for
+    /// * `kind` - The kind of event (e.g. "llm_fallback")
+    ///
+    /// # Returns
+    ///
+    /// A vector of events, ordered by creation time
+    ///
+    /// # Errors
+    ///
+    /// Returns a DieselError if database operations fail
+    pub fn get_task_events(
+        &mut self,
+        the_task_id: &str,
+        kind: &str,
+    ) -> Result<Vec<TaskEvent>, DieselError> {
+        use crate::schema::task_events::dsl::*;
+        let result = task_events
+            .filter(task_id.eq(the_task_id))
+            .filter(event_type.eq(kind))
+            .order_by(created_at.asc())
+            .load::<TaskEvent>(self.conn)?;
+        Ok(result)
+    }
+
     /// Retrieves a single task by DB primary key `id`
     ///
     /// # Arguments
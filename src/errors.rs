@@ -7,5 +7,9 @@ pub enum Error {
     #[error("Serde error: {0}")]
     Serde(#[from] serde_json::Error),
     #[error("Jsonschema error: {0}")]
-    Jsonschema(#[from] jsonschema::ValidationError<'static>),
+    Jsonschema(#[from] Box<jsonschema::ValidationError<'static>>),
+    #[error("Context error: {0}")]
+    Context(String),
+    #[error("Workflow error: {0}")]
+    Workflow(String),
 }
@@ -1,6 +1,9 @@
 use crate::core::TaskState;
-use crate::{agents::AgentOutcome, core::Task};
-use serde_json::Value;
+use crate::{
+    agents::AgentOutcome,
+    core::{SinkResult, Task},
+    llm::{CompactionEntry, FallbackEntry, UsageEntry},
+};
 
 /// Represents different events that can occur in the system
 #[derive(Debug, Clone)]
@@ -20,8 +23,20 @@ pub enum Event {
     /// The state of a task is updated
     TaskStateUpdated(String, TaskState),
 
-    /// New output data is added to a task
-    NewOutput(String, Value),
+    /// An output sink of a task was formatted and delivered, or failed
+    NewOutput(String, SinkResult),
+
+    /// Partial text of a response being streamed by an agent, with task ID and role
+    LlmProgress(String, String, String),
+
+    /// Tokens consumed by an LLM call of a task, with task ID and the usage of the call
+    LlmUsage(String, UsageEntry),
+
+    /// An LLM call of a task failed over to another provider/model, with task ID and the fallback
+    LlmFallback(String, FallbackEntry),
+
+    /// Older turns of a conversation were replaced by a summary, with task ID and the turns
+    ConversationCompacted(String, CompactionEntry),
 
     /// A new task is created
     CreateTask(Task),
@@ -0,0 +1,303 @@
+use crate::config::{CacheConfig, EndpointConfig, GenerationConfig};
+use crate::llm::providers::base_url;
+use crate::llm::{ChatMessage, LlmResponse, TokenUsage, ToolCall, ToolDefinition};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::error::Error;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+use tracing::warn;
+
+/// A response stored in the cache
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedResponse {
+    /// Text of the response
+    content: String,
+    /// Tools called by the response
+    #[serde(default)]
+    tool_calls: Vec<ToolCall>,
+    /// Tokens consumed by the original call
+    #[serde(default)]
+    usage: Option<TokenUsage>,
+}
+
+/// Content-addressed on-disk cache of the responses of one provider and model
+#[derive(Debug)]
+pub struct ResponseCache {
+    /// Directory holding one JSON file per response
+    dir: PathBuf,
+    /// Provider, endpoint, model and generation settings hashed into every key
+    scope: String,
+    /// Age after which a cached response is ignored
+    ttl: Option<Duration>,
+    /// Size in bytes above which the oldest responses are evicted
+    max_size: Option<u64>,
+}
+
+impl ResponseCache {
+    /// Creates the cache of a client, if enabled
+    ///
+    /// # Arguments
+    /// * `config` - Cache settings of the task
+    /// * `provider` - Name of the LLM provider
+    /// * `endpoint` - Endpoint of the provider, whose base URL scopes the responses
+    /// * `model` - Name of the model
+    /// * `generation` - Generation settings sent with every request
+    ///
+    /// # Returns
+    /// * `Result<Option<Self>, Box<dyn Error>>` - The cache, None if disabled, or an error if
+    ///   the TTL or size is invalid or the directory cannot be created
+    pub fn from_config(
+        config: &CacheConfig,
+        provider: &str,
+        endpoint: &EndpointConfig,
+        model: &str,
+        generation: &GenerationConfig,
+    ) -> Result<Option<Self>, Box<dyn Error>> {
+        if !config.enabled {
+            return Ok(None);
+        }
+        let dir = match &config.dir {
+            Some(dir) => PathBuf::from(dir),
+            None => dirs::cache_dir()
+                .unwrap_or_else(|| PathBuf::from(".cache"))
+                .join("kheish")
+                .join("llm"),
+        };
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create LLM cache '{}': {}", dir.display(), e))?;
+        let ttl = config
+            .ttl
+            .as_deref()
+            .map(humantime::parse_duration)
+            .transpose()?;
+        let max_size = config.max_size.as_deref().map(parse_size).transpose()?;
+        // Empty when the provider uses its default URL
+        let base_url = base_url(
+            endpoint,
+            &format!("{}_BASE_URL", provider.to_uppercase()),
+            "",
+        );
+
+        Ok(Some(ResponseCache {
+            dir,
+            scope: json!({
+                "provider": provider,
+                "base_url": base_url,
+                "model": model,
+                "generation": generation,
+            })
+            .to_string(),
+            ttl,
+            max_size,
+        }))
+    }
+
+    /// Computes the key of a request
+    ///
+    /// # Arguments
+    /// * `messages` - Messages of the request
+    /// * `tools` - Tools exposed with the request, may be empty
+    /// * `schema` - JSON schema constraining the response, if any
+    ///
+    /// # Returns
+    /// * `String` - SHA-256 of the scope, the messages, the tools and the schema, in hexadecimal
+    pub fn key(
+        &self,
+        messages: &[ChatMessage],
+        tools: &[ToolDefinition],
+        schema: Option<&Value>,
+    ) -> String {
+        let tools: Vec<_> = tools
+            .iter()
+            .map(|tool| json!([tool.name, tool.description, tool.parameters]))
+            .collect();
+        let mut hasher = Sha256::new();
+        hasher.update(self.scope.as_bytes());
+        hasher.update(json!(messages).to_string().as_bytes());
+        hasher.update(json!(tools).to_string().as_bytes());
+        hasher.update(json!(schema).to_string().as_bytes());
+        hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    /// Returns the cached response of a request, unless missing or expired
+    ///
+    /// # Arguments
+    /// * `key` - Key of the request
+    ///
+    /// # Returns
+    /// * `Option<LlmResponse>` - The cached response
+    pub fn get(&self, key: &str) -> Option<LlmResponse> {
+        let path = self.path(key);
+        let modified = std::fs::metadata(&path).ok()?.modified().ok()?;
+        if let Some(ttl) = self.ttl {
+            if modified.elapsed().unwrap_or_default() > ttl {
+                let _ = std::fs::remove_file(&path);
+                return None;
+            }
+        }
+        let cached: CachedResponse = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|text| serde_json::from_str(&text).ok())?;
+        Some(LlmResponse {
+            content: cached.content,
+            tool_calls: cached.tool_calls,
+            usage: cached.usage,
+        })
+    }
+
+    /// Stores the response of a request, then evicts the oldest responses above the max size
+    ///
+    /// Failures are logged; a call never fails because of the cache.
+    ///
+    /// # Arguments
+    /// * `key` - Key of the request
+    /// * `response` - Response of the provider
+    pub fn put(&self, key: &str, response: &LlmResponse) {
+        let cached = CachedResponse {
+            content: response.content.clone(),
+            tool_calls: response.tool_calls.clone(),
+            usage: response.usage,
+        };
+        let result = serde_json::to_string(&cached)
+            .map_err(|e| e.to_string())
+            .and_then(|text| std::fs::write(self.path(key), text).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            warn!("Failed to cache LLM response: {}", e);
+            return;
+        }
+        if let Some(max_size) = self.max_size {
+            self.evict(max_size);
+        }
+    }
+
+    /// Removes the oldest responses until the cache fits the given size
+    ///
+    /// # Arguments
+    /// * `max_size` - Size of the cache in bytes
+    fn evict(&self, max_size: u64) {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return;
+        };
+        let mut files: Vec<(SystemTime, u64, PathBuf)> = entries
+            .filter_map(|entry| {
+                let entry = entry.ok()?;
+                let metadata = entry.metadata().ok()?;
+                Some((metadata.modified().ok()?, metadata.len(), entry.path()))
+            })
+            .filter(|(_, _, path)| path.extension().is_some_and(|ext| ext == "json"))
+            .collect();
+        let mut size: u64 = files.iter().map(|(_, len, _)| len).sum();
+        files.sort();
+        for (_, len, path) in files {
+            if size <= max_size {
+                break;
+            }
+            if std::fs::remove_file(&path).is_ok() {
+                size -= len;
+            }
+        }
+    }
+
+    /// Returns the file of a key
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+}
+
+/// Parses a size such as "500KB", "200MB" or "1GB" (powers of 1024); plain numbers are bytes
+///
+/// # Arguments
+/// * `size` - The size to parse
+///
+/// # Returns
+/// * `Result<u64, String>` - The size in bytes, or an error if the size is invalid
+pub fn parse_size(size: &str) -> Result<u64, String> {
+    let size = size.trim();
+    let split = size
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(size.len());
+    let (number, unit) = size.split_at(split);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid size '{}'", size))?;
+    let multiplier: u64 = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" | "KIB" => 1 << 10,
+        "M" | "MB" | "MIB" => 1 << 20,
+        "G" | "GB" | "GIB" => 1 << 30,
+        _ => return Err(format!("invalid size unit in '{}'", size)),
+    };
+    Ok(number * multiplier)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache(name: &str, ttl: Option<&str>, max_size: Option<&str>) -> ResponseCache {
+        let dir =
+            std::env::temp_dir().join(format!("kheish-cache-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let config = CacheConfig {
+            enabled: true,
+            dir: Some(dir.to_string_lossy().to_string()),
+            ttl: ttl.map(str::to_string),
+            max_size: max_size.map(str::to_string),
+        };
+        ResponseCache::from_config(
+            &config,
+            "openai",
+            &EndpointConfig::default(),
+            "gpt-4o",
+            &GenerationConfig::default(),
+        )
+        .unwrap()
+        .unwrap()
+    }
+
+    #[test]
+    fn parse_size_reads_units() {
+        assert_eq!(parse_size("512"), Ok(512));
+        assert_eq!(parse_size("10B"), Ok(10));
+        assert_eq!(parse_size("500KB"), Ok(500 << 10));
+        assert_eq!(parse_size("200 mb"), Ok(200 << 20));
+        assert_eq!(parse_size("1GiB"), Ok(1 << 30));
+        assert!(parse_size("MB").is_err());
+        assert!(parse_size("12TB").is_err());
+        assert!(parse_size("-1KB").is_err());
+    }
+
+    #[test]
+    fn expired_responses_are_ignored() {
+        let cache = cache("ttl", Some("50ms"), None);
+        cache.put("key", &LlmResponse::text("cached".to_string()));
+        assert_eq!(cache.get("key").unwrap().content, "cached");
+
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(cache.get("key").is_none());
+        assert!(!cache.path("key").exists());
+        let _ = std::fs::remove_dir_all(&cache.dir);
+    }
+
+    #[test]
+    fn oldest_responses_are_evicted_above_the_max_size() {
+        let cache = cache("evict", None, Some("200"));
+        // Every response takes about 75 bytes on disk
+        let response = LlmResponse::text("x".repeat(30));
+        for key in ["first", "second", "third"] {
+            cache.put(key, &response);
+            std::thread::sleep(Duration::from_millis(20));
+        }
+
+        assert!(cache.get("first").is_none());
+        assert!(cache.get("second").is_some());
+        assert!(cache.get("third").is_some());
+        let _ = std::fs::remove_dir_all(&cache.dir);
+    }
+}
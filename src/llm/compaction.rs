@@ -0,0 +1,173 @@
+use crate::llm::ChatMessage;
+use serde::Serialize;
+use std::ops::Range;
+
+/// Fraction of the input budget above which older turns are summarized
+pub const DEFAULT_COMPACTION_THRESHOLD: f32 = 0.8;
+
+/// Number of messages before the latest user turn that are never summarized
+pub const DEFAULT_KEEP_RECENT: usize = 4;
+
+/// Instructions of the LLM call summarizing the older turns of a conversation
+pub const COMPACTION_PROMPT: &str = "You compact the conversation of an agent working on a task. \
+Summarize the transcript below so that the agent can continue without it. Keep every key \
+fact and finding, file paths, URLs, identifiers, commands and their results, module \
+results that are still relevant, decisions taken and open questions. Drop greetings, \
+repetitions and format reminders. Answer with the summary only, as concise bullet points.";
+
+/// Prefix of the message replacing the summarized turns
+const SUMMARY_PREFIX: &str = "Summary of the earlier conversation:\n";
+
+/// Older turns of a conversation replaced by a summary, recorded for auditing
+#[derive(Debug, Clone, Serialize)]
+pub struct CompactionEntry {
+    /// Role whose conversation was compacted
+    pub role: String,
+    /// Messages replaced by the summary
+    pub messages: Vec<ChatMessage>,
+    /// Summary written by the LLM
+    pub summary: String,
+    /// Tokens of the conversation before the compaction
+    pub tokens_before: usize,
+    /// Tokens of the conversation after the compaction
+    pub tokens_after: usize,
+}
+
+/// Returns the range of the messages to replace by a summary
+///
+/// The range starts after the leading system messages and ends before the last
+/// `keep_recent` messages preceding the latest user turn, stopping at any other system
+/// message. It never separates a tool call from its results.
+///
+/// # Arguments
+/// * `messages` - The conversation
+/// * `keep_recent` - Number of messages before the latest user turn to keep verbatim
+///
+/// # Returns
+/// * `Option<Range<usize>>` - The messages to summarize, None if fewer than two
+pub fn compaction_range(messages: &[ChatMessage], keep_recent: usize) -> Option<Range<usize>> {
+    let start = messages.iter().position(|msg| msg.role != "system")?;
+    let latest_user_turn = messages
+        .iter()
+        .rposition(|msg| msg.role == "user")
+        .unwrap_or(messages.len());
+    let mut end = latest_user_turn.saturating_sub(keep_recent).max(start);
+    if let Some(system) = messages[start..end]
+        .iter()
+        .position(|msg| msg.role == "system")
+    {
+        end = start + system;
+    }
+    while end > start && messages.get(end).is_some_and(|msg| msg.role == "tool") {
+        end -= 1;
+    }
+    (end - start >= 2).then_some(start..end)
+}
+
+/// Renders messages as a plain transcript for the summarizer
+///
+/// # Arguments
+/// * `messages` - The messages to render
+///
+/// # Returns
+/// * `String` - One paragraph per message, prefixed with its role
+pub fn transcript(messages: &[ChatMessage]) -> String {
+    let mut transcript = String::new();
+    for msg in messages {
+        let text = msg.text_with_parts();
+        if !text.is_empty() {
+            transcript.push_str(&format!("[{}]\n{}\n\n", msg.role, text));
+        }
+        for call in &msg.tool_calls {
+            transcript.push_str(&format!(
+                "[{} calls {}]\n{}\n\n",
+                msg.role, call.name, call.arguments
+            ));
+        }
+    }
+    transcript
+}
+
+/// Creates the message replacing the summarized turns
+///
+/// # Arguments
+/// * `summary` - Summary written by the LLM
+///
+/// # Returns
+/// * `ChatMessage` - User message holding the summary
+pub fn summary_message(summary: &str) -> ChatMessage {
+    ChatMessage::new("user", &format!("{}{}", SUMMARY_PREFIX, summary))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conversation(roles: &[&str]) -> Vec<ChatMessage> {
+        roles
+            .iter()
+            .enumerate()
+            .map(|(index, role)| match *role {
+                "tool" => ChatMessage::tool_result(&index.to_string(), "result"),
+                role => ChatMessage::new(role, &format!("message {}", index)),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn range_skips_the_system_prefix() {
+        let messages = conversation(&["system", "system", "user", "assistant", "user"]);
+        assert_eq!(compaction_range(&messages, 0), Some(2..4));
+    }
+
+    #[test]
+    fn range_keeps_recent_messages_before_the_latest_user_turn() {
+        let messages = conversation(&[
+            "system",
+            "user",
+            "assistant",
+            "user",
+            "assistant",
+            "user",
+            "assistant",
+        ]);
+        assert_eq!(compaction_range(&messages, 0), Some(1..5));
+        assert_eq!(compaction_range(&messages, 2), Some(1..3));
+    }
+
+    #[test]
+    fn range_stops_at_a_later_system_message() {
+        let messages = conversation(&["system", "user", "assistant", "system", "user", "user"]);
+        assert_eq!(compaction_range(&messages, 0), Some(1..3));
+    }
+
+    #[test]
+    fn range_does_not_split_tool_calls_from_their_results() {
+        let messages = conversation(&[
+            "system",
+            "user",
+            "assistant",
+            "user",
+            "assistant",
+            "tool",
+            "tool",
+            "assistant",
+            "user",
+        ]);
+        // Keeping 3 messages would start the kept turns with a tool result
+        assert_eq!(compaction_range(&messages, 3), Some(1..4));
+        assert_eq!(messages[4].role, "assistant");
+    }
+
+    #[test]
+    fn range_needs_at_least_two_messages() {
+        assert_eq!(
+            compaction_range(&conversation(&["system", "user"]), 0),
+            None
+        );
+        assert_eq!(compaction_range(&conversation(&["system"]), 0), None);
+        let messages = conversation(&["system", "user", "assistant", "user"]);
+        assert_eq!(compaction_range(&messages, 1), None);
+        assert_eq!(compaction_range(&messages, 0), Some(1..3));
+    }
+}
@@ -0,0 +1,75 @@
+use super::Embedder;
+use async_trait::async_trait;
+use std::error::Error;
+
+/// Number of dimensions of the fake embeddings when none is given
+pub const DEFAULT_FAKE_DIMENSIONS: usize = 256;
+
+/// Deterministic offline embedder hashing the words of a text into a fixed-size vector
+///
+/// Texts sharing words get similar vectors, which is enough to exercise the RAG
+/// and memory modules without calling an embedding API.
+#[derive(Debug, Clone)]
+pub struct FakeEmbedder {
+    /// Number of dimensions of the embeddings
+    pub dimensions: usize,
+}
+
+impl FakeEmbedder {
+    /// Creates a new FakeEmbedder instance
+    ///
+    /// # Arguments
+    ///
+    /// * `dimensions` - Number of dimensions of the embeddings
+    ///
+    /// # Returns
+    ///
+    /// A new FakeEmbedder instance
+    pub fn new(dimensions: usize) -> Self {
+        Self {
+            dimensions: dimensions.max(1),
+        }
+    }
+}
+
+impl Default for FakeEmbedder {
+    fn default() -> Self {
+        Self::new(DEFAULT_FAKE_DIMENSIONS)
+    }
+}
+
+/// Hashes a word with 64-bit FNV-1a, stable across runs and platforms
+fn fnv1a(word: &str) -> u64 {
+    word.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[async_trait]
+impl Embedder for FakeEmbedder {
+    /// Embeds the given text as a normalized bag of hashed words
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The text to embed
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the vector of f32 values representing the embedding
+    async fn embed_text(&self, text: &str) -> Result<Vec<f32>, Box<dyn Error>> {
+        let mut embedding = vec![0.0f32; self.dimensions];
+        for word in text
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+        {
+            let hash = fnv1a(&word.to_lowercase());
+            let sign = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+            embedding[(hash % self.dimensions as u64) as usize] += sign;
+        }
+        let norm = embedding.iter().map(|x| x * x).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            embedding.iter_mut().for_each(|x| *x /= norm);
+        }
+        Ok(embedding)
+    }
+}
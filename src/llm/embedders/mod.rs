@@ -1,11 +1,18 @@
+/// Module for the deterministic offline embedder
+pub mod fake_embedder;
 /// Module for OpenAI embedder implementation
 pub mod openai_embedder;
 
+use crate::config::EmbedderConfig;
 use async_trait::async_trait;
 use std::error::Error;
 
+pub use fake_embedder::*;
 pub use openai_embedder::*;
 
+/// Default model of the OpenAI embedder
+pub const DEFAULT_EMBEDDER_MODEL: &str = "text-embedding-3-small";
+
 /// Trait defining interface for text embedding functionality
 #[async_trait]
 pub trait Embedder {
@@ -22,3 +29,45 @@ pub trait Embedder {
     /// * A boxed Error if embedding fails
     async fn embed_text(&self, text: &str) -> Result<Vec<f32>, Box<dyn Error>>;
 }
+
+/// Embedder selected by the `embedder` parameters of a task
+#[derive(Debug)]
+pub enum TaskEmbedder {
+    /// Embeddings computed by OpenAI's API
+    OpenAI(OpenAIEmbedder),
+    /// Deterministic offline embeddings
+    Fake(FakeEmbedder),
+}
+
+impl TaskEmbedder {
+    /// Creates the embedder of a task
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - Embedder configuration of the task
+    ///
+    /// # Returns
+    ///
+    /// A Result containing either:
+    /// * The configured embedder
+    /// * An error if the provider is unknown or its API key is not set
+    pub fn from_config(config: &EmbedderConfig) -> Result<Self, Box<dyn Error>> {
+        match config.provider.as_deref().unwrap_or("openai") {
+            "openai" => Ok(TaskEmbedder::OpenAI(OpenAIEmbedder::new(
+                config.model.as_deref().unwrap_or(DEFAULT_EMBEDDER_MODEL),
+            )?)),
+            "fake" => Ok(TaskEmbedder::Fake(FakeEmbedder::default())),
+            provider => Err(format!("Unknown embedder provider '{}'", provider).into()),
+        }
+    }
+}
+
+#[async_trait]
+impl Embedder for TaskEmbedder {
+    async fn embed_text(&self, text: &str) -> Result<Vec<f32>, Box<dyn Error>> {
+        match self {
+            TaskEmbedder::OpenAI(embedder) => embedder.embed_text(text).await,
+            TaskEmbedder::Fake(embedder) => embedder.embed_text(text).await,
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use super::Embedder;
+use crate::llm::http_client;
 use async_trait::async_trait;
-use reqwest::Client;
 use serde_json::json;
 use std::error::Error;
 
@@ -49,7 +49,7 @@ impl Embedder for OpenAIEmbedder {
     /// * A vector of f32 values representing the embedding
     /// * An error if the API call fails or returns invalid data
     async fn embed_text(&self, text: &str) -> Result<Vec<f32>, Box<dyn Error>> {
-        let client = Client::new();
+        let client = http_client();
         let body = json!({
             "input": text,
             "model": self.model
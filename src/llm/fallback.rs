@@ -0,0 +1,78 @@
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Consecutive failed calls after which the circuit of a provider/model opens
+const FAILURE_THRESHOLD: u32 = 3;
+/// Time during which an open circuit is skipped before the provider/model is tried again
+const OPEN_DURATION: Duration = Duration::from_secs(60);
+
+/// Circuits of every provider/model of the process, shared by the clients of all tasks
+static CIRCUITS: Lazy<Mutex<HashMap<String, Circuit>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Failure count of a provider/model and the time until which it is skipped
+#[derive(Debug, Default)]
+struct Circuit {
+    /// Consecutive failed calls
+    failures: u32,
+    /// Set while the circuit is open
+    open_until: Option<Instant>,
+}
+
+/// A failover of an LLM call from one provider/model to the next one of the chain
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FallbackEntry {
+    /// Role that made the call
+    pub role: String,
+    /// Provider/model that failed, e.g. "anthropic/claude-3-5-sonnet-20240620"
+    pub from: String,
+    /// Provider/model the call failed over to
+    pub to: String,
+    /// Error of the failed provider/model
+    pub reason: String,
+}
+
+/// Whether calls to a provider/model are currently skipped
+///
+/// # Arguments
+/// * `label` - The provider/model, e.g. "openai/gpt-4o"
+///
+/// # Returns
+/// * `bool` - True while the circuit is open
+pub fn circuit_open(label: &str) -> bool {
+    CIRCUITS
+        .lock()
+        .unwrap()
+        .get(label)
+        .and_then(|circuit| circuit.open_until)
+        .is_some_and(|until| Instant::now() < until)
+}
+
+/// Records a successful call, closing the circuit of the provider/model
+///
+/// # Arguments
+/// * `label` - The provider/model, e.g. "openai/gpt-4o"
+pub fn record_success(label: &str) {
+    CIRCUITS.lock().unwrap().remove(label);
+}
+
+/// Records a failed call; the circuit opens once the failures reach the threshold,
+/// and opens again on the first failure after it was retried
+///
+/// # Arguments
+/// * `label` - The provider/model, e.g. "openai/gpt-4o"
+///
+/// # Returns
+/// * `bool` - True if the circuit opened
+pub fn record_failure(label: &str) -> bool {
+    let mut circuits = CIRCUITS.lock().unwrap();
+    let circuit = circuits.entry(label.to_string()).or_default();
+    circuit.failures += 1;
+    if circuit.failures < FAILURE_THRESHOLD {
+        return false;
+    }
+    circuit.open_until = Some(Instant::now() + OPEN_DURATION);
+    true
+}
@@ -12,7 +12,7 @@ use crate::errors::Error;
 /// * `Result<Validator, Error>` - The compiled validator on success, or an error
 pub fn build_validator(schema_content: &str) -> Result<Validator, Error> {
     let schema = serde_json::from_str(schema_content)?;
-    Ok(jsonschema::validator_for(&schema)?)
+    Ok(jsonschema::validator_for(&schema).map_err(Box::new)?)
 }
 
 /// Validates a JSON string against a schema validator
@@ -1,36 +1,402 @@
-use crate::llm::providers::LlmProvider;
-use crate::llm::ChatMessage;
-use crate::utils::manage_token_count;
+use crate::config::{
+    CompactionConfig, CompactionStrategy, EndpointConfig, GenerationConfig, LlmSettings,
+    MockConfig, MockMode,
+};
+use crate::llm::providers::{LlmProvider, RetryPolicy, TokenSender};
+use crate::llm::{
+    circuit_open, compaction_range, context_window, fit_to_context, input_budget, record_failure,
+    record_success, summary_message, transcript, unsupported_generation_options, ChatMessage,
+    InvalidToolCall, LlmError, LlmResponse, ResponseCache, TokenCounter, TokenUsage,
+    ToolDefinition, UsageReporter, COMPACTION_PROMPT, DEFAULT_COMPACTION_THRESHOLD,
+    DEFAULT_KEEP_RECENT,
+};
+use serde_json::Value;
 use std::error::Error;
-use tracing::{debug, info};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::{self, UnboundedSender};
+use tracing::{debug, info, warn};
+
+/// Names of the providers accepted by `LlmClient::new`
+pub const SUPPORTED_PROVIDERS: &[&str] = &[
+    "openai",
+    "anthropic",
+    "deepseek",
+    "ollama",
+    "openai_compatible",
+    "azure_openai",
+    "gemini",
+    "mistral",
+    "mock",
+];
+
+/// Phrases of a provider error rejecting the tools of a request
+///
+/// "tool" alone also appears in errors about the messages, e.g. an unknown `tool_call_id`.
+const TOOL_KEYWORDS: &[&str] = &[
+    "support tools",
+    "tools is not supported",
+    "tools are not supported",
+    "'tools'",
+    "\"tools\"",
+    "function calling",
+];
+/// Parameters named by a provider error rejecting the response schema of a request
+///
+/// Only the request parameters carrying the schema are listed (`response_format` for the
+/// chat completions APIs, `responseJsonSchema` for Gemini, the forced `tool_choice` for
+/// Anthropic, the `format` schema for Ollama): words such as "format" or "schema" alone
+/// also appear in errors about the messages.
+const SCHEMA_KEYWORDS: &[&str] = &[
+    "response_format",
+    "json_schema",
+    "responsejsonschema",
+    "responsemimetype",
+    "tool_choice",
+    "json schema",
+];
+
+/// Minimum delay between two progress updates of a streamed response
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Pending call of a provider of the chain
+type ProviderCall<'a> =
+    Pin<Box<dyn Future<Output = Result<LlmResponse, Box<dyn Error>>> + Send + 'a>>;
+
+/// A provider/model of the fallback chain of a client
+#[derive(Debug)]
+struct ChainEntry {
+    /// "provider/model", identifying the circuit of the entry
+    label: String,
+    /// Name of the model, used to attribute the token usage
+    model: String,
+    /// Provider calling the model
+    provider: Box<dyn LlmProvider>,
+}
 
 /// Generic LLM client that delegates work to a concrete provider.
+///
+/// The client holds an ordered chain of providers: the configured one first, then the
+/// fallbacks tried in turn when a call fails with a retryable error.
 #[derive(Debug)]
 pub struct LlmClient {
-    provider: Box<dyn LlmProvider>,
+    /// Providers of the client, in fallback order
+    chain: Vec<ChainEntry>,
+    /// Counter incremented on every LLM call, shared with the task worker enforcing limits
+    call_counter: Option<Arc<AtomicUsize>>,
+    /// Receives the text assembled so far while a response is streamed
+    progress: Option<UnboundedSender<String>>,
+    /// Tools exposed to the model through native tool calling
+    tools: Vec<ToolDefinition>,
+    /// Set once the model rejected a tool-calling request, falling back to text
+    tools_unsupported: AtomicBool,
+    /// Set once the model rejected a structured output request, falling back to the prompt
+    structured_unsupported: AtomicBool,
+    /// Token counter of the model
+    token_counter: TokenCounter,
+    /// Number of tokens available for the messages of a request
+    input_budget: usize,
+    /// Model that answered the last call
+    last_model: Mutex<String>,
+    /// Receives the token usage of every call
+    usage: Option<UsageReporter>,
+    /// On-disk cache of the responses, if enabled
+    cache: Option<ResponseCache>,
+    /// Compaction of the conversations nearing the input budget
+    compaction: CompactionConfig,
 }
 
 impl LlmClient {
     /// Creates a new LLM client with the specified provider and model.
     ///
     /// # Arguments
-    /// * `provider_name` - Name of the LLM provider ("openai", "anthropic", or "ollama")
+    /// * `provider_name` - Name of the LLM provider (one of `SUPPORTED_PROVIDERS`)
     /// * `model` - Model name to use with the provider
     ///
     /// # Returns
     /// * `Result<LlmClient, Box<dyn Error>>` - New LLM client instance or error
     pub fn new(provider_name: &str, model: &str) -> Result<Self, Box<dyn Error>> {
-        let provider: Box<dyn LlmProvider> = match provider_name {
-            "openai" => Box::new(crate::llm::providers::openai::OpenAiProvider::new(model)?),
-            "anthropic" => Box::new(crate::llm::providers::anthropic::AnthropicProvider::new(
-                model,
-            )?),
-            "deepseek" => Box::new(crate::llm::providers::deepseek::DeepSeekProvider::new(model)?),
-            "ollama" => Box::new(crate::llm::providers::ollama::OllamaProvider::new(model)?),
-            _ => return Err(format!("Unknown provider '{}'", provider_name).into()),
+        Self::with_generation(provider_name, model, GenerationConfig::default())
+    }
+
+    /// Creates a new LLM client sending the given generation settings with every request.
+    ///
+    /// # Arguments
+    /// * `provider_name` - Name of the LLM provider (one of `SUPPORTED_PROVIDERS`)
+    /// * `model` - Model name to use with the provider
+    /// * `generation` - Generation settings (temperature, max tokens...)
+    ///
+    /// # Returns
+    /// * `Result<LlmClient, Box<dyn Error>>` - New LLM client instance or error
+    pub fn with_generation(
+        provider_name: &str,
+        model: &str,
+        generation: GenerationConfig,
+    ) -> Result<Self, Box<dyn Error>> {
+        Self::build(
+            provider_name,
+            model,
+            generation,
+            RetryPolicy::default(),
+            &EndpointConfig::default(),
+            &MockConfig::default(),
+        )
+    }
+
+    /// Creates a new LLM client from resolved role or task settings.
+    ///
+    /// # Arguments
+    /// * `settings` - Provider, model, generation, retry and endpoint settings
+    ///
+    /// # Returns
+    /// * `Result<LlmClient, Box<dyn Error>>` - New LLM client instance, or an error if the
+    ///   provider or model is missing or unknown
+    pub fn from_settings(settings: &LlmSettings) -> Result<Self, Box<dyn Error>> {
+        let provider = settings
+            .provider
+            .as_deref()
+            .ok_or("LLM provider is required")?;
+        let model = settings.model.as_deref().ok_or("LLM model is required")?;
+        let mut client = Self::build(
+            provider,
+            model,
+            settings.generation.clone(),
+            RetryPolicy::from_config(&settings.retry),
+            &settings.endpoint,
+            &settings.mock,
+        )?;
+        for (fallback_provider, fallback_model) in &settings.fallbacks {
+            // The endpoint of the task only applies to its own provider
+            let endpoint = if fallback_provider == provider {
+                settings.endpoint.clone()
+            } else {
+                EndpointConfig::default()
+            };
+            client.add_fallback(
+                fallback_provider,
+                fallback_model,
+                settings.generation.clone(),
+                RetryPolicy::from_config(&settings.retry),
+                &endpoint,
+                &settings.mock,
+            )?;
+        }
+        client.cache = match ResponseCache::from_config(
+            &settings.cache,
+            provider,
+            &settings.endpoint,
+            model,
+            &settings.generation,
+        ) {
+            Ok(cache) => cache,
+            Err(e) => {
+                warn!("LLM response cache disabled: {}", e);
+                None
+            }
+        };
+        client.compaction = settings.compaction.clone();
+        Ok(client)
+    }
+
+    /// Creates the provider of the client and sizes its context budget.
+    ///
+    /// # Arguments
+    /// * `provider_name` - Name of the LLM provider (one of `SUPPORTED_PROVIDERS`)
+    /// * `model` - Model name to use with the provider
+    /// * `generation` - Generation settings (temperature, max tokens...)
+    /// * `retry` - Retry policy of the provider requests
+    /// * `endpoint` - Base URL, API key variable and headers of the provider
+    /// * `mock` - Mode and real provider of the `mock` provider
+    ///
+    /// # Returns
+    /// * `Result<LlmClient, Box<dyn Error>>` - New LLM client instance or error
+    fn build(
+        provider_name: &str,
+        model: &str,
+        generation: GenerationConfig,
+        retry: RetryPolicy,
+        endpoint: &EndpointConfig,
+        mock: &MockConfig,
+    ) -> Result<Self, Box<dyn Error>> {
+        // A mock provider is sized like the real provider it records or replays
+        let (counted_provider, counted_model) = match (provider_name, &mock.provider, &mock.model) {
+            ("mock", Some(provider), Some(model)) => (provider.as_str(), model.as_str()),
+            _ => (provider_name, model),
         };
+        let window = generation
+            .context_window
+            .unwrap_or_else(|| context_window(counted_provider, counted_model));
+        let input_budget = input_budget(window, generation.max_tokens);
+        let provider =
+            Self::create_provider(provider_name, model, generation, retry, endpoint, mock)?;
 
-        Ok(LlmClient { provider })
+        Ok(LlmClient {
+            chain: vec![ChainEntry {
+                label: format!("{}/{}", provider_name, model),
+                model: counted_model.to_string(),
+                provider,
+            }],
+            call_counter: None,
+            progress: None,
+            tools: Vec::new(),
+            tools_unsupported: AtomicBool::new(false),
+            structured_unsupported: AtomicBool::new(false),
+            token_counter: TokenCounter::for_model(counted_provider, counted_model),
+            input_budget,
+            last_model: Mutex::new(counted_model.to_string()),
+            usage: None,
+            cache: None,
+            compaction: CompactionConfig::default(),
+        })
+    }
+
+    /// Appends a provider/model to the fallback chain of the client.
+    ///
+    /// The context budget shrinks to the smallest window of the chain, so that every
+    /// fallback accepts the conversations sized for the first provider.
+    ///
+    /// # Arguments
+    /// * `provider_name` - Name of the LLM provider (one of `SUPPORTED_PROVIDERS`)
+    /// * `model` - Model name to use with the provider
+    /// * `generation` - Generation settings (temperature, max tokens...)
+    /// * `retry` - Retry policy of the provider requests
+    /// * `endpoint` - Base URL, API key variable and headers of the provider
+    /// * `mock` - Mode and real provider of the `mock` provider
+    ///
+    /// # Returns
+    /// * `Result<(), Box<dyn Error>>` - Ok, or an error if the provider is unknown or misconfigured
+    fn add_fallback(
+        &mut self,
+        provider_name: &str,
+        model: &str,
+        generation: GenerationConfig,
+        retry: RetryPolicy,
+        endpoint: &EndpointConfig,
+        mock: &MockConfig,
+    ) -> Result<(), Box<dyn Error>> {
+        let window = generation
+            .context_window
+            .unwrap_or_else(|| context_window(provider_name, model));
+        self.input_budget = self
+            .input_budget
+            .min(input_budget(window, generation.max_tokens));
+        let provider =
+            Self::create_provider(provider_name, model, generation, retry, endpoint, mock)?;
+        self.chain.push(ChainEntry {
+            label: format!("{}/{}", provider_name, model),
+            model: model.to_string(),
+            provider,
+        });
+        Ok(())
+    }
+
+    /// Creates a provider, wrapping the real provider of a recording `mock`.
+    ///
+    /// # Arguments
+    /// * `provider_name` - Name of the LLM provider (one of `SUPPORTED_PROVIDERS`)
+    /// * `model` - Model name to use with the provider
+    /// * `generation` - Generation settings (temperature, max tokens...)
+    /// * `retry` - Retry policy of the provider requests
+    /// * `endpoint` - Base URL, API key variable and headers of the provider
+    /// * `mock` - Mode and real provider of the `mock` provider
+    ///
+    /// # Returns
+    /// * `Result<Box<dyn LlmProvider>, Box<dyn Error>>` - The provider, or an error if it is
+    ///   unknown or misconfigured
+    fn create_provider(
+        provider_name: &str,
+        model: &str,
+        generation: GenerationConfig,
+        retry: RetryPolicy,
+        endpoint: &EndpointConfig,
+        mock: &MockConfig,
+    ) -> Result<Box<dyn LlmProvider>, Box<dyn Error>> {
+        use crate::llm::providers::mock::MockProvider;
+
+        Ok(match (provider_name, mock.mode) {
+            ("mock", MockMode::Script) => Box::new(MockProvider::script(model)?),
+            ("mock", MockMode::Replay) => Box::new(MockProvider::replay(model)?),
+            ("mock", MockMode::Record) => {
+                let (Some(real_provider), Some(real_model)) = (&mock.provider, &mock.model) else {
+                    return Err(
+                        "Recording mock provider requires mock.provider and mock.model".into(),
+                    );
+                };
+                let inner = Self::create_api_provider(
+                    real_provider,
+                    real_model,
+                    generation,
+                    retry,
+                    endpoint,
+                )?;
+                Box::new(MockProvider::record(model, inner)?)
+            }
+            _ => Self::create_api_provider(provider_name, model, generation, retry, endpoint)?,
+        })
+    }
+
+    /// Creates the provider calling a real LLM API.
+    ///
+    /// # Arguments
+    /// * `provider_name` - Name of the LLM provider
+    /// * `model` - Model name to use with the provider
+    /// * `generation` - Generation settings (temperature, max tokens...)
+    /// * `retry` - Retry policy of the provider requests
+    /// * `endpoint` - Base URL, API key variable and headers of the provider
+    ///
+    /// # Returns
+    /// * `Result<Box<dyn LlmProvider>, Box<dyn Error>>` - The provider, or an error if it is
+    ///   unknown or misconfigured
+    fn create_api_provider(
+        provider_name: &str,
+        model: &str,
+        generation: GenerationConfig,
+        retry: RetryPolicy,
+        endpoint: &EndpointConfig,
+    ) -> Result<Box<dyn LlmProvider>, Box<dyn Error>> {
+        use crate::llm::providers::{
+            anthropic, azure_openai, deepseek, gemini, mistral, ollama, openai, openai_compatible,
+        };
+
+        let unsupported = unsupported_generation_options(provider_name, &generation);
+        if !unsupported.is_empty() {
+            warn!(
+                "{} does not support generation.{}, ignored",
+                provider_name,
+                unsupported.join(", generation.")
+            );
+        }
+
+        Ok(match provider_name {
+            "openai" => Box::new(openai::OpenAiProvider::new(
+                model, generation, retry, endpoint,
+            )?),
+            "anthropic" => Box::new(anthropic::AnthropicProvider::new(
+                model, generation, retry, endpoint,
+            )?),
+            "deepseek" => Box::new(deepseek::DeepSeekProvider::new(
+                model, generation, retry, endpoint,
+            )?),
+            "ollama" => Box::new(ollama::OllamaProvider::new(
+                model, generation, retry, endpoint,
+            )?),
+            "openai_compatible" => Box::new(openai_compatible::OpenAiCompatibleProvider::new(
+                model, generation, retry, endpoint,
+            )?),
+            "azure_openai" => Box::new(azure_openai::AzureOpenAiProvider::new(
+                model, generation, retry, endpoint,
+            )?),
+            "gemini" => Box::new(gemini::GeminiProvider::new(
+                model, generation, retry, endpoint,
+            )?),
+            "mistral" => Box::new(mistral::MistralProvider::new(
+                model, generation, retry, endpoint,
+            )?),
+            _ => return Err(format!("Unknown provider '{}'", provider_name).into()),
+        })
     }
 
     /// Calls the LLM with system and user prompts and returns the raw response.
@@ -42,11 +408,389 @@ impl LlmClient {
     /// # Returns
     /// * `Result<String, Box<dyn Error>>` - LLM response text or error
     pub async fn call_llm_api(&self, messages: Vec<ChatMessage>) -> Result<String, Box<dyn Error>> {
-        self.provider.call_llm_api(messages).await
+        if let Some(counter) = &self.call_counter {
+            counter.fetch_add(1, Ordering::Relaxed);
+        }
+        let key = self
+            .cache
+            .as_ref()
+            .map(|cache| cache.key(&messages, &[], None));
+        if let Some(response) = self.cache_hit(key.as_deref()) {
+            return Ok(response.content);
+        }
+        let input_tokens = self.input_tokens(&messages);
+        let (response, entry) = self
+            .call_chain(|provider| provider.call_llm_api(messages.clone()))
+            .await?;
+        self.report_usage(&entry.model, input_tokens, &response);
+        self.cache_store(key.as_deref(), entry, &response);
+        Ok(response.content)
+    }
+
+    /// Calls the LLM in streaming mode, sending every partial token as it arrives.
+    ///
+    /// # Arguments
+    /// * `messages` - Vector of chat messages containing the conversation history
+    /// * `tokens` - Channel receiving the partial tokens, e.g. consumed as a stream by the caller
+    ///
+    /// # Returns
+    /// * `Result<String, Box<dyn Error>>` - The assembled response text or error
+    pub async fn call_llm_api_stream(
+        &self,
+        messages: Vec<ChatMessage>,
+        tokens: &TokenSender,
+    ) -> Result<String, Box<dyn Error>> {
+        if let Some(counter) = &self.call_counter {
+            counter.fetch_add(1, Ordering::Relaxed);
+        }
+        let key = self
+            .cache
+            .as_ref()
+            .map(|cache| cache.key(&messages, &[], None));
+        if let Some(response) = self.cache_hit(key.as_deref()) {
+            let _ = tokens.send(response.content.clone());
+            return Ok(response.content);
+        }
+        let input_tokens = self.input_tokens(&messages);
+        let (response, entry) = self
+            .call_chain(|provider| provider.call_llm_api_stream(messages.clone(), tokens))
+            .await?;
+        self.report_usage(&entry.model, input_tokens, &response);
+        self.cache_store(key.as_deref(), entry, &response);
+        Ok(response.content)
+    }
+
+    /// Calls the providers of the chain in turn until one answers.
+    ///
+    /// Entries whose circuit is open are skipped, unless every circuit is open. A retryable
+    /// error (rate limit, outage, timeout) moves on to the next entry, logging and reporting
+    /// the fallback; other errors are returned right away.
+    ///
+    /// # Arguments
+    /// * `call` - Calls one provider with the request
+    ///
+    /// # Returns
+    /// * `Result<(LlmResponse, &ChainEntry), Box<dyn Error>>` - The response and the entry
+    ///   that answered it, or the error of the last provider tried
+    async fn call_chain<'a, F>(
+        &'a self,
+        call: F,
+    ) -> Result<(LlmResponse, &'a ChainEntry), Box<dyn Error>>
+    where
+        F: Fn(&'a dyn LlmProvider) -> ProviderCall<'a>,
+    {
+        let mut entries: Vec<&ChainEntry> = self
+            .chain
+            .iter()
+            .filter(|entry| !circuit_open(&entry.label))
+            .collect();
+        if entries.is_empty() {
+            entries = self.chain.iter().collect();
+        }
+
+        let mut entries = entries.into_iter().peekable();
+        while let Some(entry) = entries.next() {
+            let error = match call(entry.provider.as_ref()).await {
+                Ok(response) => {
+                    record_success(&entry.label);
+                    *self.last_model.lock().unwrap() = entry.model.clone();
+                    return Ok((response, entry));
+                }
+                Err(e)
+                    if e.downcast_ref::<LlmError>()
+                        .is_some_and(LlmError::is_retryable) =>
+                {
+                    e
+                }
+                Err(e) => return Err(e),
+            };
+            if record_failure(&entry.label) {
+                warn!(
+                    "Circuit of {} opened, skipping it for the next calls",
+                    entry.label
+                );
+            }
+            let Some(next) = entries.peek() else {
+                return Err(error);
+            };
+            warn!(
+                "{} failed, falling back to {}: {}",
+                entry.label, next.label, error
+            );
+            if let Some(reporter) = &self.usage {
+                reporter.report_fallback(&entry.label, &next.label, &error.to_string());
+            }
+        }
+        Err("No LLM provider configured".into())
+    }
+
+    /// Returns the model that answered the last call of the client, which differs from
+    /// the configured model after a fallback.
+    ///
+    /// # Returns
+    /// * `String` - Name of the model
+    pub fn last_model(&self) -> String {
+        self.last_model.lock().unwrap().clone()
+    }
+
+    /// Tells the providers which role the client answers, used by the `mock` provider to
+    /// match scripted and recorded responses.
+    ///
+    /// # Arguments
+    /// * `role` - Name of the role owning the client
+    pub fn set_role(&mut self, role: &str) {
+        for entry in &mut self.chain {
+            entry.provider.set_role(role);
+        }
+    }
+
+    /// Reports the token usage of every subsequent call of this client.
+    ///
+    /// # Arguments
+    /// * `usage` - Reporter attributing the usage to the task and role of the client
+    pub fn set_usage(&mut self, usage: UsageReporter) {
+        self.usage = Some(usage);
+    }
+
+    /// Estimates the input tokens of a request, in case the provider does not report them.
+    ///
+    /// # Arguments
+    /// * `messages` - Messages of the request
+    ///
+    /// # Returns
+    /// * `Option<u64>` - The estimate, or None when no usage is reported
+    fn input_tokens(&self, messages: &[ChatMessage]) -> Option<u64> {
+        self.usage
+            .as_ref()
+            .map(|_| self.token_counter.count_messages(messages) as u64)
+    }
+
+    /// Reports the token usage of a call, estimated if the provider did not return it.
+    ///
+    /// # Arguments
+    /// * `model` - Model that answered the call
+    /// * `input_tokens` - Estimated input tokens of the request
+    /// * `response` - Response of the call
+    fn report_usage(&self, model: &str, input_tokens: Option<u64>, response: &LlmResponse) {
+        let Some(reporter) = &self.usage else {
+            return;
+        };
+        let usage = response.usage.unwrap_or_else(|| TokenUsage {
+            input_tokens: input_tokens.unwrap_or_default(),
+            output_tokens: self.token_counter.count_message(&response.to_message()) as u64,
+        });
+        reporter.report(model, usage);
+    }
+
+    /// Returns the cached response of a request, logging and reporting the hit.
+    ///
+    /// Only responses of the primary entry are cached, so its model is the one reported.
+    ///
+    /// # Arguments
+    /// * `key` - Cache key of the request, None without a cache
+    ///
+    /// # Returns
+    /// * `Option<LlmResponse>` - The cached response, None on a miss
+    fn cache_hit(&self, key: Option<&str>) -> Option<LlmResponse> {
+        let key = key?;
+        let response = self.cache.as_ref()?.get(key)?;
+        let model = &self.chain[0].model;
+        info!("LLM cache hit for {} (key {})", model, &key[..12]);
+        *self.last_model.lock().unwrap() = model.clone();
+        if let Some(reporter) = &self.usage {
+            reporter.report_cache_hit(model);
+        }
+        Some(response)
+    }
+
+    /// Stores the response of a request in the cache, if enabled.
+    ///
+    /// The cache is scoped to the primary provider and model: responses of a fallback
+    /// entry are not stored, so that they are never replayed as the primary model's.
+    ///
+    /// # Arguments
+    /// * `key` - Cache key of the request, None without a cache
+    /// * `entry` - Chain entry that answered the request
+    /// * `response` - Response of the provider
+    fn cache_store(&self, key: Option<&str>, entry: &ChainEntry, response: &LlmResponse) {
+        if entry.label != self.chain[0].label {
+            return;
+        }
+        if let (Some(cache), Some(key)) = (&self.cache, key) {
+            cache.put(key, response);
+        }
+    }
+
+    /// Streams every subsequent response of this client, reporting its partial text.
+    ///
+    /// # Arguments
+    /// * `progress` - Channel receiving the text assembled so far, at most every 200ms
+    pub fn set_progress(&mut self, progress: UnboundedSender<String>) {
+        self.progress = Some(progress);
+    }
+
+    /// Exposes the given tools to the model on every subsequent call of this client.
+    ///
+    /// Providers without native tool calling ignore them, leaving the model to the
+    /// `MODULE_REQUEST:` text protocol.
+    ///
+    /// # Arguments
+    /// * `tools` - Tools the model may call
+    pub fn set_tools(&mut self, tools: Vec<ToolDefinition>) {
+        self.tools = tools;
+    }
+
+    /// Calls the LLM, exposing the tools when the provider supports them.
+    ///
+    /// If the provider rejects the tools (a 400 or 422 response about tools or functions),
+    /// the client warns once and keeps to text responses from then on. Any other error,
+    /// including a tool call with invalid arguments, is returned as is.
+    ///
+    /// # Arguments
+    /// * `messages` - Vector of chat messages containing the conversation history
+    ///
+    /// # Returns
+    /// * `Result<LlmResponse, Box<dyn Error>>` - The response text and tool calls, or error
+    async fn complete(&self, messages: Vec<ChatMessage>) -> Result<LlmResponse, Box<dyn Error>> {
+        if self.tools.is_empty()
+            || !self.chain[0].provider.supports_tools()
+            || self.tools_unsupported.load(Ordering::Relaxed)
+        {
+            return Ok(LlmResponse::text(self.complete_text(messages).await?));
+        }
+
+        if let Some(counter) = &self.call_counter {
+            counter.fetch_add(1, Ordering::Relaxed);
+        }
+        let key = self
+            .cache
+            .as_ref()
+            .map(|cache| cache.key(&messages, &self.tools, None));
+        if let Some(response) = self.cache_hit(key.as_deref()) {
+            return Ok(response);
+        }
+        let input_tokens = self.input_tokens(&messages);
+        let error = match self
+            .call_chain(|provider| provider.call_llm_api_with_tools(messages.clone(), &self.tools))
+            .await
+        {
+            Ok((response, entry)) => {
+                self.report_usage(&entry.model, input_tokens, &response);
+                self.cache_store(key.as_deref(), entry, &response);
+                return Ok(response);
+            }
+            Err(e) if rejects_feature(e.as_ref(), TOOL_KEYWORDS) => e.to_string(),
+            Err(e) => return Err(e),
+        };
+        warn!(
+            "Tool calling rejected, falling back to the text protocol: {}",
+            error
+        );
+        self.tools_unsupported.store(true, Ordering::Relaxed);
+        Ok(LlmResponse::text(self.complete_text(messages).await?))
+    }
+
+    /// Calls the LLM, constraining the response to a JSON schema when the provider supports it.
+    ///
+    /// If the provider rejects the schema (a 400 or 422 response about it), the client warns
+    /// once and leaves the schema to the prompt from then on, as with providers lacking
+    /// structured output. Any other error is returned as is.
+    ///
+    /// # Arguments
+    /// * `messages` - Vector of chat messages containing the conversation history
+    /// * `schema` - JSON schema the response must match
+    ///
+    /// # Returns
+    /// * `Result<LlmResponse, Box<dyn Error>>` - The response, holding the JSON document, or error
+    async fn complete_structured(
+        &self,
+        messages: Vec<ChatMessage>,
+        schema: &Value,
+    ) -> Result<LlmResponse, Box<dyn Error>> {
+        if !self.chain[0].provider.supports_structured_output()
+            || self.structured_unsupported.load(Ordering::Relaxed)
+        {
+            return self.complete(messages).await;
+        }
+
+        if let Some(counter) = &self.call_counter {
+            counter.fetch_add(1, Ordering::Relaxed);
+        }
+        let key = self
+            .cache
+            .as_ref()
+            .map(|cache| cache.key(&messages, &[], Some(schema)));
+        if let Some(response) = self.cache_hit(key.as_deref()) {
+            return Ok(response);
+        }
+        let input_tokens = self.input_tokens(&messages);
+        let error = match self
+            .call_chain(|provider| provider.call_llm_api_structured(messages.clone(), schema))
+            .await
+        {
+            Ok((response, entry)) => {
+                self.report_usage(&entry.model, input_tokens, &response);
+                self.cache_store(key.as_deref(), entry, &response);
+                return Ok(response);
+            }
+            Err(e) if rejects_feature(e.as_ref(), SCHEMA_KEYWORDS) => e.to_string(),
+            Err(e) => return Err(e),
+        };
+        warn!(
+            "Structured output rejected, falling back to the prompt: {}",
+            error
+        );
+        self.structured_unsupported.store(true, Ordering::Relaxed);
+        self.complete(messages).await
+    }
+
+    /// Calls the LLM for a text response, streaming it when a progress channel is set.
+    ///
+    /// # Arguments
+    /// * `messages` - Vector of chat messages containing the conversation history
+    ///
+    /// # Returns
+    /// * `Result<String, Box<dyn Error>>` - The complete response text or error
+    async fn complete_text(&self, messages: Vec<ChatMessage>) -> Result<String, Box<dyn Error>> {
+        let Some(progress) = &self.progress else {
+            return self.call_llm_api(messages).await;
+        };
+
+        let (tokens, mut token_rx) = mpsc::unbounded_channel();
+        let call = async move {
+            self.call_llm_api_stream(messages, &tokens)
+                .await
+                .map_err(|e| e.to_string())
+        };
+        let report = async {
+            let mut partial = String::new();
+            let mut last_report: Option<Instant> = None;
+            while let Some(token) = token_rx.recv().await {
+                partial.push_str(&token);
+                if last_report.is_none_or(|at| at.elapsed() >= PROGRESS_INTERVAL) {
+                    let _ = progress.send(partial.clone());
+                    last_report = Some(Instant::now());
+                }
+            }
+            let _ = progress.send(partial);
+        };
+
+        let (response, _) = tokio::join!(call, report);
+        Ok(response?)
+    }
+
+    /// Counts every subsequent LLM call of this client in the given counter.
+    ///
+    /// # Arguments
+    /// * `counter` - Counter shared between the clients of a task
+    pub fn set_call_counter(&mut self, counter: Arc<AtomicUsize>) {
+        self.call_counter = Some(counter);
     }
 
     /// Calls the LLM with format validation and automatic retries if format check fails.
     ///
+    /// Responses calling a tool are returned as is, without format validation.
+    ///
     /// # Arguments
     /// * `system_prompt` - System prompt to set context/behavior
     /// * `user_prompt` - User's input prompt
@@ -55,33 +799,194 @@ impl LlmClient {
     /// * `max_retries` - Maximum number of retry attempts
     ///
     /// # Returns
-    /// * `Result<String, Box<dyn Error>>` - Validated LLM response or error
+    /// * `Result<LlmResponse, Box<dyn Error>>` - Validated LLM response or error
     pub async fn call_llm_with_format_check<F>(
         &self,
         messages: &mut Vec<ChatMessage>,
         validate_response: F,
         format_reminder: &str,
         max_retries: usize,
-    ) -> Result<String, Box<dyn Error>>
+    ) -> Result<LlmResponse, Box<dyn Error>>
+    where
+        F: Fn(&str) -> bool,
+    {
+        self.call_with_format_check(
+            messages,
+            None,
+            validate_response,
+            format_reminder,
+            max_retries,
+        )
+        .await
+    }
+
+    /// Calls the LLM for a JSON document matching a schema, with format validation and
+    /// automatic retries.
+    ///
+    /// The schema is enforced by the provider when it supports structured output (OpenAI
+    /// `json_schema`, a forced Anthropic tool, Ollama `format`); other providers rely on the
+    /// schema given in the prompt.
+    ///
+    /// # Arguments
+    /// * `messages` - Conversation history, including the prompt describing the schema
+    /// * `schema` - JSON schema the response must match
+    /// * `validate_response` - Function to validate response format
+    /// * `format_reminder` - Format instructions to include in retry attempts
+    /// * `max_retries` - Maximum number of retry attempts
+    ///
+    /// # Returns
+    /// * `Result<LlmResponse, Box<dyn Error>>` - Validated LLM response or error
+    pub async fn call_llm_with_schema<F>(
+        &self,
+        messages: &mut Vec<ChatMessage>,
+        schema: &Value,
+        validate_response: F,
+        format_reminder: &str,
+        max_retries: usize,
+    ) -> Result<LlmResponse, Box<dyn Error>>
+    where
+        F: Fn(&str) -> bool,
+    {
+        self.call_with_format_check(
+            messages,
+            Some(schema),
+            validate_response,
+            format_reminder,
+            max_retries,
+        )
+        .await
+    }
+
+    /// Replaces the older turns of a conversation by an LLM-written summary once it crosses
+    /// the compaction threshold, with the `summarize` strategy.
+    ///
+    /// The replaced turns are reported to the task manager, which keeps them in the task
+    /// events. If no summary can be written, the conversation is left to truncation.
+    ///
+    /// # Arguments
+    /// * `messages` - The conversation, modified in place
+    async fn compact(&self, messages: &mut Vec<ChatMessage>) {
+        if self.compaction.strategy != CompactionStrategy::Summarize {
+            return;
+        }
+        let threshold = self
+            .compaction
+            .threshold
+            .unwrap_or(DEFAULT_COMPACTION_THRESHOLD);
+        let tokens_before = self.token_counter.count_messages(messages);
+        if tokens_before as f64 <= self.input_budget as f64 * threshold as f64 {
+            return;
+        }
+        let keep_recent = self.compaction.keep_recent.unwrap_or(DEFAULT_KEEP_RECENT);
+        let Some(range) = compaction_range(messages, keep_recent) else {
+            return;
+        };
+
+        let prompt = ChatMessage::new("system", COMPACTION_PROMPT);
+        let room = self
+            .input_budget
+            .saturating_sub(2 * self.token_counter.count_message(&prompt));
+        let transcript = self
+            .token_counter
+            .truncate(&transcript(&messages[range.clone()]), room);
+        let summary = match self
+            .call_llm_api(vec![prompt, ChatMessage::new("user", &transcript)])
+            .await
+        {
+            Ok(summary) if !summary.trim().is_empty() => summary.trim().to_string(),
+            Ok(_) => {
+                warn!("Conversation compaction returned an empty summary, truncating instead");
+                return;
+            }
+            Err(e) => {
+                warn!("Conversation compaction failed, truncating instead: {}", e);
+                return;
+            }
+        };
+
+        // The summary belongs to a parallel branch as soon as it covers one of its turns
+        let mut summary_msg = summary_message(&summary);
+        summary_msg.inherited = messages[range.clone()].iter().all(|msg| msg.inherited);
+        let compacted: Vec<ChatMessage> = messages.splice(range, [summary_msg]).collect();
+        let tokens_after = self.token_counter.count_messages(messages);
+        info!(
+            "Compacted {} messages into a summary ({} -> {} tokens)",
+            compacted.len(),
+            tokens_before,
+            tokens_after
+        );
+        if let Some(reporter) = &self.usage {
+            reporter.report_compaction(compacted, summary, tokens_before, tokens_after);
+        }
+    }
+
+    /// Calls the LLM until the response passes the validation or the attempts run out.
+    ///
+    /// # Arguments
+    /// * `messages` - Conversation history, extended with a reminder after every failure
+    /// * `schema` - JSON schema enforced by the provider, if any
+    /// * `validate_response` - Function to validate response format
+    /// * `format_reminder` - Format instructions to include in retry attempts
+    /// * `max_retries` - Maximum number of retry attempts
+    ///
+    /// # Returns
+    /// * `Result<LlmResponse, Box<dyn Error>>` - Validated LLM response or error
+    async fn call_with_format_check<F>(
+        &self,
+        messages: &mut Vec<ChatMessage>,
+        schema: Option<&Value>,
+        validate_response: F,
+        format_reminder: &str,
+        max_retries: usize,
+    ) -> Result<LlmResponse, Box<dyn Error>>
     where
         F: Fn(&str) -> bool,
     {
         let mut attempts = 0;
 
-        manage_token_count(messages, 35000);
+        self.compact(messages).await;
+        if fit_to_context(messages, &self.token_counter, self.input_budget) {
+            info!(
+                "Conversation trimmed to {} messages to fit the context window",
+                messages.len()
+            );
+        }
 
         loop {
             attempts += 1;
-            let response = self.call_llm_api(messages.clone()).await?;
+            let result = match schema {
+                Some(schema) => self.complete_structured(messages.clone(), schema).await,
+                None => self.complete(messages.clone()).await,
+            };
+            let mut response = match result {
+                Ok(response) => response,
+                Err(e) if e.is::<InvalidToolCall>() && attempts < max_retries => {
+                    info!("Retrying after an invalid tool call: {}", e);
+                    messages.push(ChatMessage::new(
+                        "user",
+                        &format!(
+                            "Your last tool call was rejected: {}.\n\
+                             Call the tool again with a JSON object as arguments.",
+                            e
+                        ),
+                    ));
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
             debug!("messages: {:?}", messages);
-            debug!("LLM response: {}", response);
+            debug!("LLM response: {:?}", response);
 
-            if validate_response(&response) {
+            if !response.tool_calls.is_empty() {
+                response.tool_calls.truncate(1);
+                return Ok(response);
+            }
+            if validate_response(&response.content) {
                 return Ok(response);
             } else if attempts >= max_retries {
                 info!(
                     "LLM did not follow the format after {} attempts response: {}",
-                    max_retries, response
+                    max_retries, response.content
                 );
                 return Err(format!(
                     "LLM did not follow the format after {} attempts",
@@ -100,3 +1005,87 @@ impl LlmClient {
         }
     }
 }
+
+/// Whether a provider rejected a request because of one of its features
+///
+/// # Arguments
+/// * `error` - Error of the call
+/// * `keywords` - Words naming the feature in the error messages of the providers
+///
+/// # Returns
+/// * `bool` - True for a 400 or 422 response whose body mentions one of the keywords
+fn rejects_feature(error: &(dyn Error + 'static), keywords: &[&str]) -> bool {
+    match error.downcast_ref::<LlmError>() {
+        Some(LlmError::Http { status, body, .. }) if matches!(status.as_u16(), 400 | 422) => {
+            let body = body.to_lowercase();
+            keywords.iter().any(|keyword| body.contains(keyword))
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::StatusCode;
+
+    fn http_error(status: u16, body: &str) -> Box<dyn Error> {
+        Box::new(LlmError::Http {
+            provider: "openai",
+            status: StatusCode::from_u16(status).unwrap(),
+            body: body.to_string(),
+            retry_after: None,
+        })
+    }
+
+    #[test]
+    fn tool_rejections_are_recognized() {
+        for body in [
+            "registry.ollama.ai/library/llama2:latest does not support tools",
+            "Invalid parameter: 'tools' is not supported with this model.",
+            "Function calling is not enabled for models/gemini-1.0-pro",
+        ] {
+            assert!(rejects_feature(
+                http_error(400, body).as_ref(),
+                TOOL_KEYWORDS
+            ));
+        }
+        assert!(!rejects_feature(
+            http_error(400, "Invalid 'messages[3]': tool_call_id not found").as_ref(),
+            TOOL_KEYWORDS
+        ));
+    }
+
+    #[test]
+    fn schema_rejections_are_recognized() {
+        let rejections = [
+            "Invalid parameter: 'response_format' of type 'json_schema' is not supported with this model.",
+            "Invalid JSON payload received. Unknown name \"responseJsonSchema\" at 'generation_config'",
+            "tool_choice is not supported for this model",
+        ];
+        for body in rejections {
+            assert!(rejects_feature(
+                http_error(400, body).as_ref(),
+                SCHEMA_KEYWORDS
+            ));
+        }
+        assert!(!rejects_feature(
+            http_error(500, rejections[0]).as_ref(),
+            SCHEMA_KEYWORDS
+        ));
+    }
+
+    #[test]
+    fn other_client_errors_keep_structured_output() {
+        for body in [
+            "Invalid message format",
+            "Invalid 'messages[3]': tool_call_id not found",
+            "The schema of the request is invalid",
+        ] {
+            assert!(!rejects_feature(
+                http_error(400, body).as_ref(),
+                SCHEMA_KEYWORDS
+            ));
+        }
+    }
+}
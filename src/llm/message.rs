@@ -1,10 +1,170 @@
+use crate::llm::ToolCall;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::path::Path;
+
 /// Represents a chat message with a role and content
 #[derive(serde::Serialize, Debug, Clone)]
 pub struct ChatMessage {
-    /// Role of the message sender (e.g. "system", "user", "assistant")
+    /// Role of the message sender (e.g. "system", "user", "assistant", "tool")
     pub role: String,
     /// Content/text of the message
     pub content: String,
+    /// Images, documents and extra text sent after the content, for vision-capable models
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub parts: Vec<ContentPart>,
+    /// Tools called natively by an assistant message
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCall>,
+    /// ID of the tool call a "tool" message answers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+    /// Whether a parallel branch received the message from the task it started from
+    #[serde(skip)]
+    pub inherited: bool,
+}
+
+/// Part of a message besides its text content
+///
+/// Files are read when the request is sent, so that conversations stored in the
+/// database only hold their paths.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentPart {
+    /// Additional text
+    Text {
+        /// The text
+        text: String,
+    },
+    /// Image read from a file
+    ImageFile {
+        /// Path of the image
+        path: String,
+    },
+    /// Image held in memory
+    ImageData {
+        /// Media type of the image, e.g. "image/png"
+        media_type: String,
+        /// Bytes of the image, serialized in base64
+        #[serde(serialize_with = "to_base64", deserialize_with = "from_base64")]
+        data: Vec<u8>,
+    },
+    /// Document read from a file, e.g. a scanned PDF
+    File {
+        /// Path of the document
+        path: String,
+    },
+}
+
+/// Returns the media type of a file that can be attached to a message, from its extension
+///
+/// # Arguments
+/// * `path` - Path of the file
+///
+/// # Returns
+/// * `Option<&'static str>` - The media type of an image or PDF document, None otherwise
+pub fn attachment_media_type(path: &str) -> Option<&'static str> {
+    let extension = Path::new(path).extension()?.to_str()?.to_lowercase();
+    match extension.as_str() {
+        "png" => Some("image/png"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "gif" => Some("image/gif"),
+        "webp" => Some("image/webp"),
+        "pdf" => Some("application/pdf"),
+        _ => None,
+    }
+}
+
+impl ContentPart {
+    /// Creates the part attaching a file, if it is an image or a PDF document
+    ///
+    /// # Arguments
+    /// * `path` - Path of the file
+    ///
+    /// # Returns
+    /// * `Option<ContentPart>` - An image or file part, None for other files
+    pub fn attachment(path: &str) -> Option<Self> {
+        let media_type = attachment_media_type(path)?;
+        Some(if media_type.starts_with("image/") {
+            ContentPart::ImageFile {
+                path: path.to_string(),
+            }
+        } else {
+            ContentPart::File {
+                path: path.to_string(),
+            }
+        })
+    }
+
+    /// Whether the part is an image
+    pub fn is_image(&self) -> bool {
+        matches!(
+            self,
+            ContentPart::ImageFile { .. } | ContentPart::ImageData { .. }
+        )
+    }
+
+    /// Returns the file name of the part, sent along with documents
+    ///
+    /// # Returns
+    /// * `String` - The file name, or "attachment" for in-memory parts
+    pub fn file_name(&self) -> String {
+        match self {
+            ContentPart::ImageFile { path } | ContentPart::File { path } => Path::new(path)
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| path.clone()),
+            _ => "attachment".to_string(),
+        }
+    }
+
+    /// Reads the media type and base64 data of an image or file part
+    ///
+    /// # Returns
+    /// * `Result<(String, String), String>` - The media type and the data, or an error if
+    ///   the file cannot be read or the part is text
+    pub fn encode(&self) -> Result<(String, String), String> {
+        match self {
+            ContentPart::Text { .. } => Err("a text part has no data".to_string()),
+            ContentPart::ImageData { media_type, data } => {
+                Ok((media_type.clone(), STANDARD.encode(data)))
+            }
+            ContentPart::ImageFile { path } | ContentPart::File { path } => {
+                let media_type = attachment_media_type(path)
+                    .ok_or_else(|| format!("unsupported attachment type '{}'", path))?;
+                let data =
+                    std::fs::read(path).map_err(|e| format!("cannot read '{}': {}", path, e))?;
+                Ok((media_type.to_string(), STANDARD.encode(data)))
+            }
+        }
+    }
+
+    /// Describes the part as text, for providers and logs without attachments
+    ///
+    /// # Returns
+    /// * `String` - The text of a text part, a short reference otherwise
+    pub fn describe(&self) -> String {
+        match self {
+            ContentPart::Text { text } => text.clone(),
+            ContentPart::ImageFile { path } => format!("[image: {}]", path),
+            ContentPart::ImageData { media_type, data } => {
+                format!("[image: {}, {} bytes]", media_type, data.len())
+            }
+            ContentPart::File { path } => format!("[file: {}]", path),
+        }
+    }
+}
+
+/// Serializes bytes in base64
+fn to_base64<S: Serializer>(data: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&STANDARD.encode(data))
+}
+
+/// Deserializes base64 data into bytes
+fn from_base64<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+    let encoded = String::deserialize(deserializer)?;
+    STANDARD.decode(encoded).map_err(serde::de::Error::custom)
 }
 
 impl ChatMessage {
@@ -20,6 +180,67 @@ impl ChatMessage {
         ChatMessage {
             role: role.to_string(),
             content: content.to_string(),
+            parts: Vec::new(),
+            tool_calls: Vec::new(),
+            tool_call_id: None,
+            inherited: false,
+        }
+    }
+
+    /// Adds images, documents or text parts to the message
+    ///
+    /// # Arguments
+    /// * `parts` - The parts sent after the content
+    ///
+    /// # Returns
+    /// * `ChatMessage` - The message with its parts
+    pub fn with_parts(mut self, parts: Vec<ContentPart>) -> Self {
+        self.parts.extend(parts);
+        self
+    }
+
+    /// Returns the content with its parts described as text
+    ///
+    /// # Returns
+    /// * `String` - The content, followed by one line per part
+    pub fn text_with_parts(&self) -> String {
+        let mut text = self.content.clone();
+        for part in &self.parts {
+            if !text.is_empty() {
+                text.push('\n');
+            }
+            text.push_str(&part.describe());
+        }
+        text
+    }
+
+    /// Creates an assistant message calling tools
+    ///
+    /// # Arguments
+    /// * `content` - Text sent along with the tool calls, possibly empty
+    /// * `tool_calls` - The tools called by the assistant
+    ///
+    /// # Returns
+    /// * `ChatMessage` - New assistant message
+    pub fn assistant_with_tool_calls(content: &str, tool_calls: Vec<ToolCall>) -> Self {
+        ChatMessage {
+            tool_calls,
+            ..ChatMessage::new("assistant", content)
+        }
+    }
+
+    /// Creates a message carrying the result of a tool call
+    ///
+    /// # Arguments
+    /// * `tool_call_id` - ID of the tool call being answered
+    /// * `content` - Result of the tool call
+    ///
+    /// # Returns
+    /// * `ChatMessage` - New tool message
+    pub fn tool_result(tool_call_id: &str, content: &str) -> Self {
+        ChatMessage {
+            tool_call_id: Some(tool_call_id.to_string()),
+            ..ChatMessage::new("tool", content)
         }
     }
 }
@@ -1,10 +1,25 @@
+mod cache;
+mod compaction;
 mod embedders;
+mod fallback;
 mod llm_client;
 mod message;
 mod providers;
 mod format_validator;
+mod tokens;
+mod tool;
+mod transport;
+mod usage;
 
+pub use cache::*;
+pub use compaction::*;
 pub use embedders::*;
+pub use fallback::*;
 pub use llm_client::*;
 pub use message::*;
-pub use format_validator::*;
\ No newline at end of file
+pub use providers::{is_fatal_error, unsupported_generation_options, LlmError};
+pub use format_validator::*;
+pub use tokens::*;
+pub use tool::*;
+pub use transport::*;
+pub use usage::*;
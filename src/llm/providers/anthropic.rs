@@ -1,11 +1,24 @@
-use super::LlmProvider;
-use crate::llm::ChatMessage;
+use super::{
+    api_key, base_url, encode_part, pair_tool_calls, plain_messages, read_lines, sse_data,
+    with_headers, LlmProvider, RetryPolicy, TokenSender,
+};
+use crate::config::{EndpointConfig, GenerationConfig};
+use crate::llm::{
+    http_client, ChatMessage, ContentPart, LlmResponse, TokenUsage, ToolCall, ToolDefinition,
+};
 use async_trait::async_trait;
-use reqwest::Client;
-use serde_json::json;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
 use std::error::Error;
 use tracing::debug;
 
+/// Tool forced to return a structured output
+const OUTPUT_TOOL: &str = "output";
+/// Response limit of the Claude 3 models, when `max_tokens` is not set
+const CLAUDE_3_MAX_TOKENS: u32 = 4096;
+/// Response limit of the later models, when `max_tokens` is not set
+const DEFAULT_MAX_TOKENS: u32 = 8192;
+
 /// Provider implementation for Anthropic's API
 #[derive(Debug)]
 pub struct AnthropicProvider {
@@ -13,6 +26,14 @@ pub struct AnthropicProvider {
     api_key: String,
     /// Model identifier to use (e.g. "claude-2", "claude-instant-1")
     model: String,
+    /// Generation settings sent with every request
+    generation: GenerationConfig,
+    /// Retry policy of the HTTP requests
+    retry: RetryPolicy,
+    /// URL of the chat endpoint
+    url: String,
+    /// Extra headers sent with every request
+    headers: BTreeMap<String, String>,
 }
 
 impl AnthropicProvider {
@@ -20,31 +41,48 @@ impl AnthropicProvider {
     ///
     /// # Arguments
     /// * `model` - The model identifier to use
+    /// * `generation` - Generation settings sent with every request
+    /// * `retry` - Retry policy of the HTTP requests
+    /// * `endpoint` - Base URL, API key variable and extra headers
     ///
     /// # Returns
     /// * `Result<Self, Box<dyn Error>>` - Provider instance or error if API key not found
-    pub fn new(model: &str) -> Result<Self, Box<dyn Error>> {
-        let api_key = std::env::var("ANTHROPIC_API_KEY")
-            .map_err(|_| "ANTHROPIC_API_KEY environment variable not set")?;
+    pub fn new(
+        model: &str,
+        generation: GenerationConfig,
+        retry: RetryPolicy,
+        endpoint: &EndpointConfig,
+    ) -> Result<Self, Box<dyn Error>> {
+        let api_key = api_key(endpoint, "ANTHROPIC_API_KEY")?;
+        let url = base_url(endpoint, "ANTHROPIC_BASE_URL", "https://api.anthropic.com");
         Ok(AnthropicProvider {
             api_key,
             model: model.to_string(),
+            generation,
+            retry,
+            url: format!("{}/v1/messages", url),
+            headers: endpoint.headers.clone(),
         })
     }
-}
 
-#[async_trait]
-impl LlmProvider for AnthropicProvider {
-    /// Calls Anthropic's messages API
+    /// Sends a messages request
     ///
     /// # Arguments
-    /// * `system_prompt` - System message to set context/behavior
-    /// * `user_prompt` - User's input message
+    /// * `messages` - Vector of chat messages containing the conversation history
+    /// * `tools` - Tools exposed to the model, may be empty
+    /// * `forced_tool` - Tool the model must call, if any
+    /// * `stream` - Whether the response is streamed as server-sent events
     ///
     /// # Returns
-    /// * `Result<String, Box<dyn Error>>` - Generated response text or error
-    async fn call_llm_api(&self, messages: Vec<ChatMessage>) -> Result<String, Box<dyn Error>> {
-        let client = Client::new();
+    /// * `Result<reqwest::Response, Box<dyn Error>>` - The successful HTTP response or error
+    async fn send(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: &[ToolDefinition],
+        forced_tool: Option<&str>,
+        stream: bool,
+    ) -> Result<reqwest::Response, Box<dyn Error>> {
+        let client = http_client();
 
         let (system_messages, user_messages): (Vec<_>, Vec<_>) =
             messages.into_iter().partition(|msg| msg.role == "system");
@@ -53,37 +91,343 @@ impl LlmProvider for AnthropicProvider {
             .map(|m| m.content)
             .collect::<Vec<_>>()
             .join("\n");
-        let messages = user_messages
-            .into_iter()
-            .map(|msg| ChatMessage::new(&msg.role, &msg.content))
-            .collect::<Vec<_>>();
+        let messages: Vec<Value> = if tools.is_empty() {
+            plain_messages(user_messages)
+                .iter()
+                .map(to_request_message)
+                .collect()
+        } else {
+            pair_tool_calls(user_messages)
+                .iter()
+                .map(to_request_message)
+                .collect()
+        };
 
-        let request_body = json!({
+        let mut request_body = json!({
             "model": self.model,
             "system": system_content,
-            "max_tokens": 4096,
-            "messages": messages
+            "max_tokens": self.generation.max_tokens.unwrap_or_else(|| default_max_tokens(&self.model)),
+            "messages": messages,
+            "stream": stream
         });
+        if let Some(temperature) = self.generation.temperature {
+            request_body["temperature"] = json!(temperature);
+        }
+        if let Some(top_p) = self.generation.top_p {
+            request_body["top_p"] = json!(top_p);
+        }
+        if !self.generation.stop.is_empty() {
+            request_body["stop_sequences"] = json!(self.generation.stop);
+        }
+        if !tools.is_empty() {
+            let tools: Vec<Value> = tools
+                .iter()
+                .map(|tool| {
+                    json!({
+                        "name": tool.name,
+                        "description": tool.description,
+                        "input_schema": tool.parameters
+                    })
+                })
+                .collect();
+            request_body["tools"] = json!(tools);
+        }
+        if let Some(tool) = forced_tool {
+            request_body["tool_choice"] = json!({ "type": "tool", "name": tool });
+        }
 
-        let res = client
-            .post("https://api.anthropic.com/v1/messages")
-            .header("x-api-key", self.api_key.to_string())
-            .header("anthropic-version", "2023-06-01")
-            .json(&request_body)
-            .send()
+        let res = self
+            .retry
+            .send("Anthropic", || {
+                with_headers(client.post(&self.url), &self.headers)
+                    .header("x-api-key", self.api_key.to_string())
+                    .header("anthropic-version", "2023-06-01")
+                    .json(&request_body)
+            })
             .await?;
+        Ok(res)
+    }
+}
 
-        if !res.status().is_success() {
-            let text = res.text().await?;
-            return Err(format!("Anthropic API error: {}", text).into());
-        }
-
+#[async_trait]
+impl LlmProvider for AnthropicProvider {
+    /// Calls Anthropic's messages API
+    ///
+    /// # Arguments
+    /// * `system_prompt` - System message to set context/behavior
+    /// * `user_prompt` - User's input message
+    ///
+    /// # Returns
+    /// * `Result<LlmResponse, Box<dyn Error>>` - Generated response text and usage, or error
+    async fn call_llm_api(
+        &self,
+        messages: Vec<ChatMessage>,
+    ) -> Result<LlmResponse, Box<dyn Error>> {
+        let res = self.send(messages, &[], None, false).await?;
         let json_resp: serde_json::Value = res.json().await?;
         if let Some(content) = json_resp["content"][0]["text"].as_str() {
             debug!("Anthropic response: {}", content);
-            Ok(content.trim().to_string())
+            Ok(
+                LlmResponse::text(content.trim().to_string())
+                    .with_usage(usage(&json_resp["usage"])),
+            )
         } else {
             Err("No content in Anthropic LLM response".into())
         }
     }
+
+    fn supports_tools(&self) -> bool {
+        true
+    }
+
+    fn supports_structured_output(&self) -> bool {
+        true
+    }
+
+    /// Calls Anthropic's messages API, forcing a tool whose input is the JSON document
+    ///
+    /// Tool inputs are objects: schemas of another type are left to the prompt.
+    ///
+    /// # Arguments
+    /// * `messages` - Vector of chat messages containing the conversation history
+    /// * `schema` - JSON schema the response must match
+    ///
+    /// # Returns
+    /// * `Result<LlmResponse, Box<dyn Error>>` - The JSON response and usage, or error
+    async fn call_llm_api_structured(
+        &self,
+        messages: Vec<ChatMessage>,
+        schema: &Value,
+    ) -> Result<LlmResponse, Box<dyn Error>> {
+        if schema["type"] != "object" {
+            return self.call_llm_api(messages).await;
+        }
+        let tool = ToolDefinition {
+            name: OUTPUT_TOOL.to_string(),
+            description: "Returns the final output, matching the required schema".to_string(),
+            parameters: schema.clone(),
+        };
+        let res = self
+            .send(messages, &[tool], Some(OUTPUT_TOOL), false)
+            .await?;
+        let json_resp: serde_json::Value = res.json().await?;
+        let input = json_resp["content"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find(|block| block["type"] == "tool_use")
+            .map(|block| &block["input"])
+            .ok_or("No structured output in Anthropic LLM response")?;
+        Ok(LlmResponse::text(input.to_string()).with_usage(usage(&json_resp["usage"])))
+    }
+
+    /// Calls Anthropic's messages API with tool use
+    ///
+    /// # Arguments
+    /// * `messages` - Vector of chat messages containing the conversation history
+    /// * `tools` - Tools the model may call
+    ///
+    /// # Returns
+    /// * `Result<LlmResponse, Box<dyn Error>>` - The response text and tool calls, or error
+    async fn call_llm_api_with_tools(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: &[ToolDefinition],
+    ) -> Result<LlmResponse, Box<dyn Error>> {
+        let res = self.send(messages, tools, None, false).await?;
+        let json_resp: serde_json::Value = res.json().await?;
+
+        let mut content = String::new();
+        let mut tool_calls = Vec::new();
+        for block in json_resp["content"].as_array().into_iter().flatten() {
+            match block["type"].as_str() {
+                Some("text") => content.push_str(block["text"].as_str().unwrap_or_default()),
+                Some("tool_use") => tool_calls.push(ToolCall {
+                    id: block["id"].as_str().unwrap_or_default().to_string(),
+                    name: block["name"].as_str().unwrap_or_default().to_string(),
+                    arguments: block["input"].clone(),
+                }),
+                _ => {}
+            }
+        }
+
+        if content.trim().is_empty() && tool_calls.is_empty() {
+            return Err("No content in Anthropic LLM response".into());
+        }
+        debug!(
+            "Anthropic response: {} ({} tool calls)",
+            content,
+            tool_calls.len()
+        );
+        Ok(LlmResponse {
+            content: content.trim().to_string(),
+            tool_calls,
+            usage: usage(&json_resp["usage"]),
+        })
+    }
+
+    /// Calls Anthropic's messages API with server-sent events streaming
+    ///
+    /// # Arguments
+    /// * `messages` - Vector of chat messages containing the conversation history
+    /// * `tokens` - Channel receiving the partial tokens
+    ///
+    /// # Returns
+    /// * `Result<LlmResponse, Box<dyn Error>>` - The assembled response text and usage, or error
+    async fn call_llm_api_stream(
+        &self,
+        messages: Vec<ChatMessage>,
+        tokens: &TokenSender,
+    ) -> Result<LlmResponse, Box<dyn Error>> {
+        let res = self.send(messages, &[], None, true).await?;
+        let mut content = String::new();
+        let mut input_usage = None;
+        let mut output_tokens = 0;
+        read_lines(res, |line| {
+            let Some(data) = sse_data(line) else {
+                return Ok(true);
+            };
+            let event: serde_json::Value = serde_json::from_str(data)?;
+            match event["type"].as_str() {
+                Some("content_block_delta") => {
+                    if let Some(token) = event["delta"]["text"].as_str() {
+                        content.push_str(token);
+                        let _ = tokens.send(token.to_string());
+                    }
+                    Ok(true)
+                }
+                Some("message_start") => {
+                    input_usage = usage(&event["message"]["usage"]);
+                    Ok(true)
+                }
+                Some("message_delta") => {
+                    output_tokens = event["usage"]["output_tokens"]
+                        .as_u64()
+                        .unwrap_or(output_tokens);
+                    Ok(true)
+                }
+                Some("message_stop") => Ok(false),
+                Some("error") => Err(format!("Anthropic API error: {}", event["error"]).into()),
+                _ => Ok(true),
+            }
+        })
+        .await?;
+
+        if content.is_empty() {
+            return Err("No content in Anthropic LLM response".into());
+        }
+        debug!("Anthropic response: {}", content);
+        let usage = input_usage.map(|usage| TokenUsage {
+            output_tokens,
+            ..usage
+        });
+        Ok(LlmResponse::text(content.trim().to_string()).with_usage(usage))
+    }
+}
+
+/// Reads the `usage` block of a messages API response
+///
+/// Tokens written to and read from the prompt cache are counted as input tokens.
+///
+/// # Arguments
+/// * `usage` - The `usage` object of the response or of the `message_start` event
+///
+/// # Returns
+/// * `Option<TokenUsage>` - The input and output tokens, if reported
+fn usage(usage: &Value) -> Option<TokenUsage> {
+    let cached = ["cache_creation_input_tokens", "cache_read_input_tokens"]
+        .iter()
+        .filter_map(|field| usage[field].as_u64())
+        .sum::<u64>();
+    Some(TokenUsage {
+        input_tokens: usage["input_tokens"].as_u64()? + cached,
+        output_tokens: usage["output_tokens"].as_u64().unwrap_or(0),
+    })
+}
+
+/// Converts a chat message into the messages API format
+///
+/// Tool calls become `tool_use` blocks and tool results `tool_result` blocks of a
+/// user message; images and documents become `image` and `document` blocks.
+///
+/// # Arguments
+/// * `msg` - The message to convert
+///
+/// # Returns
+/// * `Value` - The message with its content blocks
+fn to_request_message(msg: &ChatMessage) -> Value {
+    if msg.role == "tool" {
+        let content = if msg.parts.is_empty() {
+            json!(msg.content)
+        } else {
+            json!(content_blocks(msg))
+        };
+        return json!({
+            "role": "user",
+            "content": [{
+                "type": "tool_result",
+                "tool_use_id": msg.tool_call_id,
+                "content": content
+            }]
+        });
+    }
+    if msg.tool_calls.is_empty() && msg.parts.is_empty() {
+        return json!({ "role": msg.role, "content": msg.content });
+    }
+
+    let mut blocks = content_blocks(msg);
+    for call in &msg.tool_calls {
+        blocks.push(json!({
+            "type": "tool_use",
+            "id": call.id,
+            "name": call.name,
+            "input": call.arguments
+        }));
+    }
+    json!({ "role": msg.role, "content": blocks })
+}
+
+/// Converts the content and parts of a message into text, image and document blocks
+///
+/// # Arguments
+/// * `msg` - The message to convert
+///
+/// # Returns
+/// * `Vec<Value>` - The content blocks, without the tool calls
+fn content_blocks(msg: &ChatMessage) -> Vec<Value> {
+    let mut blocks = Vec::new();
+    if !msg.content.is_empty() {
+        blocks.push(json!({ "type": "text", "text": msg.content }));
+    }
+    for part in &msg.parts {
+        let encoded = match part {
+            ContentPart::Text { .. } => None,
+            part => encode_part(part),
+        };
+        blocks.push(match encoded {
+            Some((media_type, data)) => json!({
+                "type": if part.is_image() { "image" } else { "document" },
+                "source": { "type": "base64", "media_type": media_type, "data": data }
+            }),
+            None => json!({ "type": "text", "text": part.describe() }),
+        });
+    }
+    blocks
+}
+
+/// Returns the response limit of a model, sent when `max_tokens` is not set since the
+/// messages API requires one
+///
+/// # Arguments
+/// * `model` - The model identifier
+///
+/// # Returns
+/// * `u32` - 4096 for the Claude 3 models, which accept no more, 8192 otherwise
+fn default_max_tokens(model: &str) -> u32 {
+    let claude_3 = ["claude-3-opus", "claude-3-sonnet", "claude-3-haiku"];
+    if claude_3.iter().any(|prefix| model.starts_with(prefix)) {
+        CLAUDE_3_MAX_TOKENS
+    } else {
+        DEFAULT_MAX_TOKENS
+    }
 }
@@ -0,0 +1,196 @@
+use super::openai::OpenAiProvider;
+use super::{api_key, base_url, LlmProvider, RetryPolicy, TokenSender};
+use crate::config::{EndpointConfig, GenerationConfig};
+use crate::llm::{ChatMessage, LlmResponse, ToolDefinition};
+use async_trait::async_trait;
+use serde_json::Value;
+use std::error::Error;
+
+/// API version used when neither `llm_endpoint.api_version` nor `AZURE_OPENAI_API_VERSION` is set
+const DEFAULT_API_VERSION: &str = "2024-10-21";
+
+/// Provider for Azure OpenAI, whose chat completions are served per deployment
+///
+/// The model of the task is the name of the deployment.
+#[derive(Debug)]
+pub struct AzureOpenAiProvider {
+    /// OpenAI provider pointed at the deployment
+    inner: OpenAiProvider,
+}
+
+impl AzureOpenAiProvider {
+    /// Creates a new Azure OpenAI provider instance
+    ///
+    /// # Arguments
+    /// * `deployment` - Name of the deployment serving the model
+    /// * `generation` - Generation settings sent with every request
+    /// * `retry` - Retry policy of the HTTP requests
+    /// * `endpoint` - Resource URL, API key variable, API version and extra headers
+    ///
+    /// # Returns
+    /// * `Result<Self, Box<dyn Error>>` - Provider instance, or an error if the resource URL
+    ///   or the API key is missing
+    pub fn new(
+        deployment: &str,
+        generation: GenerationConfig,
+        retry: RetryPolicy,
+        endpoint: &EndpointConfig,
+    ) -> Result<Self, Box<dyn Error>> {
+        let url = base_url(endpoint, "AZURE_OPENAI_BASE_URL", "");
+        if url.is_empty() {
+            return Err("azure_openai provider requires llm_endpoint.base_url \
+                        or the AZURE_OPENAI_BASE_URL environment variable \
+                        (e.g. https://<resource>.openai.azure.com)"
+                .into());
+        }
+        let api_key = api_key(endpoint, "AZURE_OPENAI_API_KEY")?;
+        let api_version = endpoint
+            .api_version
+            .clone()
+            .or_else(|| std::env::var("AZURE_OPENAI_API_VERSION").ok())
+            .unwrap_or_else(|| DEFAULT_API_VERSION.to_string());
+
+        Ok(AzureOpenAiProvider {
+            inner: OpenAiProvider::with_endpoint(
+                "Azure OpenAI",
+                format!(
+                    "{}/openai/deployments/{}/chat/completions?api-version={}",
+                    url, deployment, api_version
+                ),
+                Some(("api-key".to_string(), api_key)),
+                endpoint.headers.clone(),
+                deployment,
+                generation,
+                retry,
+            ),
+        })
+    }
+}
+
+#[async_trait]
+impl LlmProvider for AzureOpenAiProvider {
+    /// Calls the chat completions API of the deployment
+    ///
+    /// # Arguments
+    /// * `messages` - Vector of chat messages containing the conversation history
+    ///
+    /// # Returns
+    /// * `Result<LlmResponse, Box<dyn Error>>` - Generated response text and usage, or error
+    async fn call_llm_api(
+        &self,
+        messages: Vec<ChatMessage>,
+    ) -> Result<LlmResponse, Box<dyn Error>> {
+        self.inner.call_llm_api(messages).await
+    }
+
+    /// Calls the chat completions API of the deployment with server-sent events streaming
+    ///
+    /// # Arguments
+    /// * `messages` - Vector of chat messages containing the conversation history
+    /// * `tokens` - Channel receiving the partial tokens
+    ///
+    /// # Returns
+    /// * `Result<LlmResponse, Box<dyn Error>>` - The assembled response text and usage, or error
+    async fn call_llm_api_stream(
+        &self,
+        messages: Vec<ChatMessage>,
+        tokens: &TokenSender,
+    ) -> Result<LlmResponse, Box<dyn Error>> {
+        self.inner.call_llm_api_stream(messages, tokens).await
+    }
+
+    fn supports_structured_output(&self) -> bool {
+        true
+    }
+
+    /// Calls the chat completions API of the deployment with a `json_schema` response format
+    ///
+    /// # Arguments
+    /// * `messages` - Vector of chat messages containing the conversation history
+    /// * `schema` - JSON schema the response must match
+    ///
+    /// # Returns
+    /// * `Result<LlmResponse, Box<dyn Error>>` - The JSON response and usage, or error
+    async fn call_llm_api_structured(
+        &self,
+        messages: Vec<ChatMessage>,
+        schema: &Value,
+    ) -> Result<LlmResponse, Box<dyn Error>> {
+        self.inner.call_llm_api_structured(messages, schema).await
+    }
+
+    fn supports_tools(&self) -> bool {
+        true
+    }
+
+    /// Calls the chat completions API of the deployment with function tools
+    ///
+    /// # Arguments
+    /// * `messages` - Vector of chat messages containing the conversation history
+    /// * `tools` - Tools the model may call
+    ///
+    /// # Returns
+    /// * `Result<LlmResponse, Box<dyn Error>>` - The response text and tool calls, or error
+    async fn call_llm_api_with_tools(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: &[ToolDefinition],
+    ) -> Result<LlmResponse, Box<dyn Error>> {
+        self.inner.call_llm_api_with_tools(messages, tools).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::stub;
+    use super::*;
+    use crate::llm::ChatMessage;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn sends_deployment_request_and_reads_response() {
+        let (url, requests) = stub::serve(json!({
+            "choices": [{ "message": { "role": "assistant", "content": "Done" } }],
+            "usage": { "prompt_tokens": 20, "completion_tokens": 1 }
+        }))
+        .await;
+        std::env::set_var("KHEISH_TEST_AZURE_KEY", "azure-key");
+        let endpoint = EndpointConfig {
+            base_url: Some(url),
+            api_key_env: Some("KHEISH_TEST_AZURE_KEY".to_string()),
+            api_version: Some("2024-06-01".to_string()),
+            ..Default::default()
+        };
+        let provider = AzureOpenAiProvider::new(
+            "gpt4o-prod",
+            GenerationConfig::default(),
+            RetryPolicy::default(),
+            &endpoint,
+        )
+        .unwrap();
+
+        let response = provider
+            .call_llm_api(vec![
+                ChatMessage::new("system", "You are terse."),
+                ChatMessage::new("user", "Finish"),
+            ])
+            .await
+            .unwrap();
+
+        assert_eq!(response.content, "Done");
+        let usage = response.usage.unwrap();
+        assert_eq!((usage.input_tokens, usage.output_tokens), (20, 1));
+
+        let request = requests.lock().unwrap()[0].clone();
+        assert_eq!(
+            request.uri,
+            "/openai/deployments/gpt4o-prod/chat/completions?api-version=2024-06-01"
+        );
+        assert_eq!(request.header("api-key"), "azure-key");
+        assert_eq!(request.header("authorization"), "");
+        let messages = request.body["messages"].as_array().unwrap();
+        assert_eq!(messages[0]["role"], "system");
+        assert_eq!(messages[0]["content"], "You are terse.");
+        assert_eq!(messages[1]["content"], "Finish");
+    }
+}
@@ -1,8 +1,12 @@
-use super::LlmProvider;
-use crate::llm::ChatMessage;
+use super::{
+    api_key, base_url, chat_completions_usage, read_lines, sse_data, text_messages, with_headers,
+    LlmProvider, RetryPolicy, TokenSender,
+};
+use crate::config::{EndpointConfig, GenerationConfig};
+use crate::llm::{http_client, ChatMessage, LlmResponse};
 use async_trait::async_trait;
-use reqwest::Client;
 use serde_json::json;
+use std::collections::BTreeMap;
 use std::error::Error;
 
 /// Provider implementation for DeepSeek's API
@@ -12,6 +16,14 @@ pub struct DeepSeekProvider {
     api_key: String,
     /// Model identifier to use (e.g. "deepseek-chat")
     model: String,
+    /// Generation settings sent with every request
+    generation: GenerationConfig,
+    /// Retry policy of the HTTP requests
+    retry: RetryPolicy,
+    /// URL of the chat endpoint
+    url: String,
+    /// Extra headers sent with every request
+    headers: BTreeMap<String, String>,
 }
 
 impl DeepSeekProvider {
@@ -19,54 +31,134 @@ impl DeepSeekProvider {
     ///
     /// # Arguments
     /// * `model` - The model identifier to use
+    /// * `generation` - Generation settings sent with every request
+    /// * `retry` - Retry policy of the HTTP requests
+    /// * `endpoint` - Base URL, API key variable and extra headers
     ///
     /// # Returns
     /// * `Result<Self, Box<dyn Error>>` - Provider instance or error if API key not found
-    pub fn new(model: &str) -> Result<Self, Box<dyn Error>> {
-        let api_key = std::env::var("DEEPSEEK_API_KEY")
-            .map_err(|_| "DEEPSEEK_API_KEY environment variable not set")?;
+    pub fn new(
+        model: &str,
+        generation: GenerationConfig,
+        retry: RetryPolicy,
+        endpoint: &EndpointConfig,
+    ) -> Result<Self, Box<dyn Error>> {
+        let api_key = api_key(endpoint, "DEEPSEEK_API_KEY")?;
+        let url = base_url(endpoint, "DEEPSEEK_BASE_URL", "https://api.deepseek.com");
         Ok(DeepSeekProvider {
             api_key,
             model: model.to_string(),
+            generation,
+            retry,
+            url: format!("{}/chat/completions", url),
+            headers: endpoint.headers.clone(),
         })
     }
-}
 
-#[async_trait]
-impl LlmProvider for DeepSeekProvider {
-    /// Calls DeepSeek's chat completions API
+    /// Sends a chat completions request
     ///
     /// # Arguments
     /// * `messages` - Vector of chat messages containing the conversation history
+    /// * `stream` - Whether the response is streamed as server-sent events
     ///
     /// # Returns
-    /// * `Result<String, Box<dyn Error>>` - Generated response text or error
-    async fn call_llm_api(&self, messages: Vec<ChatMessage>) -> Result<String, Box<dyn Error>> {
-        let client = Client::new();
-        let request_body = json!({
+    /// * `Result<reqwest::Response, Box<dyn Error>>` - The successful HTTP response or error
+    async fn send(
+        &self,
+        messages: Vec<ChatMessage>,
+        stream: bool,
+    ) -> Result<reqwest::Response, Box<dyn Error>> {
+        let client = http_client();
+        let mut request_body = json!({
           "model": self.model,
-          "messages": messages,
-          "temperature": 0.7,
-          "stream": false
+          "messages": text_messages(messages),
+          "temperature": self.generation.temperature.unwrap_or(0.7),
+          "stream": stream
         });
+        if stream {
+            request_body["stream_options"] = json!({ "include_usage": true });
+        }
+        if let Some(max_tokens) = self.generation.max_tokens {
+            request_body["max_tokens"] = json!(max_tokens);
+        }
+        if let Some(top_p) = self.generation.top_p {
+            request_body["top_p"] = json!(top_p);
+        }
+        if !self.generation.stop.is_empty() {
+            request_body["stop"] = json!(self.generation.stop);
+        }
 
-        let res = client
-            .post("https://api.deepseek.com/chat/completions")
-            .bearer_auth(&self.api_key)
-            .json(&request_body)
-            .send()
+        let res = self
+            .retry
+            .send("DeepSeek", || {
+                with_headers(client.post(&self.url), &self.headers)
+                    .bearer_auth(&self.api_key)
+                    .json(&request_body)
+            })
             .await?;
+        Ok(res)
+    }
+}
 
-        if !res.status().is_success() {
-            let text = res.text().await?;
-            return Err(format!("DeepSeek API error: {}", text).into());
-        }
-
+#[async_trait]
+impl LlmProvider for DeepSeekProvider {
+    /// Calls DeepSeek's chat completions API
+    ///
+    /// # Arguments
+    /// * `messages` - Vector of chat messages containing the conversation history
+    ///
+    /// # Returns
+    /// * `Result<LlmResponse, Box<dyn Error>>` - Generated response text and usage, or error
+    async fn call_llm_api(
+        &self,
+        messages: Vec<ChatMessage>,
+    ) -> Result<LlmResponse, Box<dyn Error>> {
+        let res = self.send(messages, false).await?;
         let json_resp: serde_json::Value = res.json().await?;
         if let Some(content) = json_resp["choices"][0]["message"]["content"].as_str() {
-            Ok(content.trim().to_string())
+            Ok(LlmResponse::text(content.trim().to_string())
+                .with_usage(chat_completions_usage(&json_resp)))
         } else {
             Err("No content in DeepSeek LLM response".into())
         }
     }
+
+    /// Calls DeepSeek's chat completions API with server-sent events streaming
+    ///
+    /// # Arguments
+    /// * `messages` - Vector of chat messages containing the conversation history
+    /// * `tokens` - Channel receiving the partial tokens
+    ///
+    /// # Returns
+    /// * `Result<LlmResponse, Box<dyn Error>>` - The assembled response text and usage, or error
+    async fn call_llm_api_stream(
+        &self,
+        messages: Vec<ChatMessage>,
+        tokens: &TokenSender,
+    ) -> Result<LlmResponse, Box<dyn Error>> {
+        let res = self.send(messages, true).await?;
+        let mut content = String::new();
+        let mut usage = None;
+        read_lines(res, |line| {
+            let Some(data) = sse_data(line) else {
+                return Ok(true);
+            };
+            if data == "[DONE]" {
+                return Ok(false);
+            }
+            let event: serde_json::Value = serde_json::from_str(data)?;
+            if let Some(token) = event["choices"][0]["delta"]["content"].as_str() {
+                content.push_str(token);
+                let _ = tokens.send(token.to_string());
+            }
+            usage = chat_completions_usage(&event).or(usage);
+            Ok(true)
+        })
+        .await?;
+
+        if content.is_empty() {
+            return Err("No content in DeepSeek LLM response".into());
+        }
+        Ok(LlmResponse::text(content.trim().to_string()).with_usage(usage))
+    }
 }
@@ -0,0 +1,515 @@
+use super::{
+    api_key, base_url, encode_part, pair_tool_calls, plain_messages, read_lines, sse_data,
+    with_headers, LlmProvider, RetryPolicy, TokenSender,
+};
+use crate::config::{EndpointConfig, GenerationConfig};
+use crate::llm::{
+    http_client, ChatMessage, ContentPart, LlmResponse, TokenUsage, ToolCall, ToolDefinition,
+};
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use tracing::debug;
+
+/// Provider implementation for Google's Gemini API
+#[derive(Debug)]
+pub struct GeminiProvider {
+    /// Gemini API key loaded from environment
+    api_key: String,
+    /// Model identifier to use (e.g. "gemini-1.5-pro")
+    model: String,
+    /// Generation settings sent with every request
+    generation: GenerationConfig,
+    /// Retry policy of the HTTP requests
+    retry: RetryPolicy,
+    /// Base URL of the API
+    url: String,
+    /// Extra headers sent with every request
+    headers: BTreeMap<String, String>,
+}
+
+impl GeminiProvider {
+    /// Creates a new Gemini provider instance
+    ///
+    /// # Arguments
+    /// * `model` - The model identifier to use
+    /// * `generation` - Generation settings sent with every request
+    /// * `retry` - Retry policy of the HTTP requests
+    /// * `endpoint` - Base URL, API key variable and extra headers
+    ///
+    /// # Returns
+    /// * `Result<Self, Box<dyn Error>>` - Provider instance or error if API key not found
+    pub fn new(
+        model: &str,
+        generation: GenerationConfig,
+        retry: RetryPolicy,
+        endpoint: &EndpointConfig,
+    ) -> Result<Self, Box<dyn Error>> {
+        let api_key = api_key(endpoint, "GEMINI_API_KEY")?;
+        let url = base_url(
+            endpoint,
+            "GEMINI_BASE_URL",
+            "https://generativelanguage.googleapis.com",
+        );
+        Ok(GeminiProvider {
+            api_key,
+            model: model.to_string(),
+            generation,
+            retry,
+            url,
+            headers: endpoint.headers.clone(),
+        })
+    }
+
+    /// Sends a `generateContent` request, or a `streamGenerateContent` one when streaming
+    ///
+    /// System messages are sent as the `systemInstruction` of the request.
+    ///
+    /// # Arguments
+    /// * `messages` - Vector of chat messages containing the conversation history
+    /// * `tools` - Tools exposed to the model, may be empty
+    /// * `schema` - JSON schema the response must match, if any
+    /// * `stream` - Whether the response is streamed as server-sent events
+    ///
+    /// # Returns
+    /// * `Result<reqwest::Response, Box<dyn Error>>` - The successful HTTP response or error
+    async fn send(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: &[ToolDefinition],
+        schema: Option<&Value>,
+        stream: bool,
+    ) -> Result<reqwest::Response, Box<dyn Error>> {
+        let client = http_client();
+
+        let (system_messages, user_messages): (Vec<_>, Vec<_>) =
+            messages.into_iter().partition(|msg| msg.role == "system");
+        let system_content = system_messages
+            .into_iter()
+            .map(|m| m.content)
+            .collect::<Vec<_>>()
+            .join("\n");
+        let messages = if tools.is_empty() {
+            plain_messages(user_messages)
+        } else {
+            pair_tool_calls(user_messages)
+        };
+
+        let mut generation_config = json!({});
+        if let Some(temperature) = self.generation.temperature {
+            generation_config["temperature"] = json!(temperature);
+        }
+        if let Some(top_p) = self.generation.top_p {
+            generation_config["topP"] = json!(top_p);
+        }
+        if let Some(max_tokens) = self.generation.max_tokens {
+            generation_config["maxOutputTokens"] = json!(max_tokens);
+        }
+        if !self.generation.stop.is_empty() {
+            generation_config["stopSequences"] = json!(self.generation.stop);
+        }
+        if let Some(seed) = self.generation.seed {
+            generation_config["seed"] = json!(seed);
+        }
+        if let Some(schema) = schema {
+            generation_config["responseMimeType"] = json!("application/json");
+            generation_config["responseJsonSchema"] = schema.clone();
+        }
+
+        let mut request_body = json!({
+            "contents": to_contents(&messages),
+            "generationConfig": generation_config
+        });
+        if !system_content.is_empty() {
+            request_body["systemInstruction"] = json!({ "parts": [{ "text": system_content }] });
+        }
+        if !tools.is_empty() {
+            let declarations: Vec<Value> = tools
+                .iter()
+                .map(|tool| {
+                    json!({
+                        "name": tool.name,
+                        "description": tool.description,
+                        "parametersJsonSchema": tool.parameters
+                    })
+                })
+                .collect();
+            request_body["tools"] = json!([{ "functionDeclarations": declarations }]);
+        }
+
+        let url = if stream {
+            format!(
+                "{}/v1beta/models/{}:streamGenerateContent?alt=sse",
+                self.url, self.model
+            )
+        } else {
+            format!("{}/v1beta/models/{}:generateContent", self.url, self.model)
+        };
+        let res = self
+            .retry
+            .send("Gemini", || {
+                with_headers(client.post(&url), &self.headers)
+                    .header("x-goog-api-key", self.api_key.to_string())
+                    .json(&request_body)
+            })
+            .await?;
+        Ok(res)
+    }
+
+    /// Reads the text and usage of a `generateContent` response
+    ///
+    /// # Arguments
+    /// * `res` - The successful HTTP response
+    ///
+    /// # Returns
+    /// * `Result<LlmResponse, Box<dyn Error>>` - The response text and usage, or error
+    async fn text_response(&self, res: reqwest::Response) -> Result<LlmResponse, Box<dyn Error>> {
+        let json_resp: serde_json::Value = res.json().await?;
+        let content = response_text(&json_resp);
+        if content.trim().is_empty() {
+            return Err("No content in Gemini LLM response".into());
+        }
+        debug!("Gemini response: {}", content);
+        Ok(LlmResponse::text(content.trim().to_string()).with_usage(usage(&json_resp)))
+    }
+}
+
+#[async_trait]
+impl LlmProvider for GeminiProvider {
+    /// Calls Gemini's `generateContent` API
+    ///
+    /// # Arguments
+    /// * `messages` - Vector of chat messages containing the conversation history
+    ///
+    /// # Returns
+    /// * `Result<LlmResponse, Box<dyn Error>>` - Generated response text and usage, or error
+    async fn call_llm_api(
+        &self,
+        messages: Vec<ChatMessage>,
+    ) -> Result<LlmResponse, Box<dyn Error>> {
+        let res = self.send(messages, &[], None, false).await?;
+        self.text_response(res).await
+    }
+
+    fn supports_structured_output(&self) -> bool {
+        true
+    }
+
+    /// Calls Gemini's `generateContent` API with a JSON response schema
+    ///
+    /// # Arguments
+    /// * `messages` - Vector of chat messages containing the conversation history
+    /// * `schema` - JSON schema the response must match
+    ///
+    /// # Returns
+    /// * `Result<LlmResponse, Box<dyn Error>>` - The JSON response and usage, or error
+    async fn call_llm_api_structured(
+        &self,
+        messages: Vec<ChatMessage>,
+        schema: &Value,
+    ) -> Result<LlmResponse, Box<dyn Error>> {
+        let res = self.send(messages, &[], Some(schema), false).await?;
+        self.text_response(res).await
+    }
+
+    fn supports_tools(&self) -> bool {
+        true
+    }
+
+    /// Calls Gemini's `generateContent` API with function declarations
+    ///
+    /// Gemini does not identify function calls, so each call gets a generated id.
+    ///
+    /// # Arguments
+    /// * `messages` - Vector of chat messages containing the conversation history
+    /// * `tools` - Tools the model may call
+    ///
+    /// # Returns
+    /// * `Result<LlmResponse, Box<dyn Error>>` - The response text and tool calls, or error
+    async fn call_llm_api_with_tools(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: &[ToolDefinition],
+    ) -> Result<LlmResponse, Box<dyn Error>> {
+        let res = self.send(messages, tools, None, false).await?;
+        let json_resp: serde_json::Value = res.json().await?;
+
+        let content = response_text(&json_resp);
+        let tool_calls: Vec<ToolCall> = response_parts(&json_resp)
+            .filter_map(|part| part.get("functionCall"))
+            .map(|call| ToolCall {
+                id: uuid::Uuid::new_v4().to_string(),
+                name: call["name"].as_str().unwrap_or_default().to_string(),
+                arguments: call.get("args").cloned().unwrap_or_else(|| json!({})),
+            })
+            .collect();
+
+        if content.trim().is_empty() && tool_calls.is_empty() {
+            return Err("No content in Gemini LLM response".into());
+        }
+        debug!(
+            "Gemini response: {} ({} tool calls)",
+            content,
+            tool_calls.len()
+        );
+        Ok(LlmResponse {
+            content: content.trim().to_string(),
+            tool_calls,
+            usage: usage(&json_resp),
+        })
+    }
+
+    /// Calls Gemini's `streamGenerateContent` API with server-sent events
+    ///
+    /// # Arguments
+    /// * `messages` - Vector of chat messages containing the conversation history
+    /// * `tokens` - Channel receiving the partial tokens
+    ///
+    /// # Returns
+    /// * `Result<LlmResponse, Box<dyn Error>>` - The assembled response text and usage, or error
+    async fn call_llm_api_stream(
+        &self,
+        messages: Vec<ChatMessage>,
+        tokens: &TokenSender,
+    ) -> Result<LlmResponse, Box<dyn Error>> {
+        let res = self.send(messages, &[], None, true).await?;
+        let mut content = String::new();
+        let mut stream_usage = None;
+        read_lines(res, |line| {
+            let Some(data) = sse_data(line) else {
+                return Ok(true);
+            };
+            let event: serde_json::Value = serde_json::from_str(data)?;
+            if event.get("error").is_some() {
+                return Err(format!("Gemini API error: {}", event["error"]).into());
+            }
+            let token = response_text(&event);
+            if !token.is_empty() {
+                content.push_str(&token);
+                let _ = tokens.send(token);
+            }
+            stream_usage = usage(&event).or(stream_usage);
+            Ok(true)
+        })
+        .await?;
+
+        if content.trim().is_empty() {
+            return Err("No content in Gemini LLM response".into());
+        }
+        debug!("Gemini response: {}", content);
+        Ok(LlmResponse::text(content.trim().to_string()).with_usage(stream_usage))
+    }
+}
+
+/// Returns the parts of the first candidate of a response
+///
+/// # Arguments
+/// * `response` - A `generateContent` response or stream chunk
+///
+/// # Returns
+/// * `impl Iterator<Item = &Value>` - The parts, empty if the response has no candidate
+fn response_parts(response: &Value) -> impl Iterator<Item = &Value> {
+    response["candidates"][0]["content"]["parts"]
+        .as_array()
+        .into_iter()
+        .flatten()
+}
+
+/// Concatenates the text parts of the first candidate of a response
+///
+/// # Arguments
+/// * `response` - A `generateContent` response or stream chunk
+///
+/// # Returns
+/// * `String` - The text of the response, empty if it has none
+fn response_text(response: &Value) -> String {
+    response_parts(response)
+        .filter(|part| part["thought"] != true)
+        .filter_map(|part| part["text"].as_str())
+        .collect()
+}
+
+/// Reads the `usageMetadata` block of a response
+///
+/// # Arguments
+/// * `response` - A `generateContent` response, or the last chunk of a stream
+///
+/// # Returns
+/// * `Option<TokenUsage>` - The prompt and candidates tokens, if reported
+fn usage(response: &Value) -> Option<TokenUsage> {
+    let usage = &response["usageMetadata"];
+    Some(TokenUsage {
+        input_tokens: usage["promptTokenCount"].as_u64()?,
+        output_tokens: usage["candidatesTokenCount"].as_u64().unwrap_or(0)
+            + usage["thoughtsTokenCount"].as_u64().unwrap_or(0),
+    })
+}
+
+/// Converts the conversation into Gemini `contents`
+///
+/// Assistant messages take the `model` role, tool calls become `functionCall` parts and
+/// tool results `functionResponse` parts of a user turn, named after the call they answer.
+/// Consecutive turns of the same role are merged.
+///
+/// # Arguments
+/// * `messages` - The conversation history, without system messages
+///
+/// # Returns
+/// * `Vec<Value>` - The contents of the request
+fn to_contents(messages: &[ChatMessage]) -> Vec<Value> {
+    let mut call_names: HashMap<&str, &str> = HashMap::new();
+    let mut contents: Vec<Value> = Vec::new();
+    for msg in messages {
+        let role = if msg.role == "assistant" {
+            "model"
+        } else {
+            "user"
+        };
+        let mut parts = Vec::new();
+        if msg.role == "tool" {
+            let id = msg.tool_call_id.as_deref().unwrap_or_default();
+            parts.push(json!({
+                "functionResponse": {
+                    "name": call_names.get(id).copied().unwrap_or(id),
+                    "response": { "content": msg.content }
+                }
+            }));
+        } else if !msg.content.is_empty() {
+            parts.push(json!({ "text": msg.content }));
+        }
+        parts.extend(msg.parts.iter().map(to_part));
+        for call in &msg.tool_calls {
+            call_names.insert(&call.id, &call.name);
+            parts.push(json!({
+                "functionCall": { "name": call.name, "args": call.arguments }
+            }));
+        }
+        if parts.is_empty() {
+            continue;
+        }
+
+        match contents.last_mut() {
+            Some(last) if last["role"] == role => {
+                if let Some(last_parts) = last["parts"].as_array_mut() {
+                    last_parts.extend(parts);
+                }
+            }
+            _ => contents.push(json!({ "role": role, "parts": parts })),
+        }
+    }
+    contents
+}
+
+/// Converts a content part into a Gemini text or `inlineData` part
+///
+/// # Arguments
+/// * `part` - The part to convert
+///
+/// # Returns
+/// * `Value` - The part, or its description if the file cannot be sent
+fn to_part(part: &ContentPart) -> Value {
+    let encoded = match part {
+        ContentPart::Text { .. } => None,
+        part => encode_part(part),
+    };
+    match encoded {
+        Some((media_type, data)) => json!({
+            "inlineData": { "mimeType": media_type, "data": data }
+        }),
+        None => json!({ "text": part.describe() }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::stub;
+    use super::*;
+
+    /// Creates a provider pointed at a stub server
+    fn provider(url: &str, key_env: &str) -> GeminiProvider {
+        std::env::set_var(key_env, "gemini-key");
+        let endpoint = EndpointConfig {
+            base_url: Some(url.to_string()),
+            api_key_env: Some(key_env.to_string()),
+            ..Default::default()
+        };
+        GeminiProvider::new(
+            "gemini-test",
+            GenerationConfig::default(),
+            RetryPolicy::default(),
+            &endpoint,
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn sends_system_instruction_and_reads_text() {
+        let (url, requests) = stub::serve(json!({
+            "candidates": [{ "content": { "role": "model", "parts": [{ "text": " Hello " }] } }],
+            "usageMetadata": { "promptTokenCount": 12, "candidatesTokenCount": 3 }
+        }))
+        .await;
+        let provider = provider(&url, "KHEISH_TEST_GEMINI_TEXT_KEY");
+
+        let response = provider
+            .call_llm_api(vec![
+                ChatMessage::new("system", "Be brief."),
+                ChatMessage::new("user", "Hi"),
+                ChatMessage::new("assistant", "Hi, how can I help?"),
+                ChatMessage::new("user", "Say hello"),
+            ])
+            .await
+            .unwrap();
+
+        assert_eq!(response.content, "Hello");
+        let usage = response.usage.unwrap();
+        assert_eq!((usage.input_tokens, usage.output_tokens), (12, 3));
+
+        let request = requests.lock().unwrap()[0].clone();
+        assert_eq!(request.uri, "/v1beta/models/gemini-test:generateContent");
+        assert_eq!(request.header("x-goog-api-key"), "gemini-key");
+        assert_eq!(
+            request.body["systemInstruction"]["parts"][0]["text"],
+            "Be brief."
+        );
+        let roles: Vec<&str> = request.body["contents"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|content| content["role"].as_str().unwrap())
+            .collect();
+        assert_eq!(roles, ["user", "model", "user"]);
+    }
+
+    #[tokio::test]
+    async fn declares_tools_and_reads_function_calls() {
+        let (url, requests) = stub::serve(json!({
+            "candidates": [{ "content": { "role": "model", "parts": [
+                { "functionCall": { "name": "fs_read", "args": { "path": "a.txt" } } }
+            ] } }]
+        }))
+        .await;
+        let provider = provider(&url, "KHEISH_TEST_GEMINI_TOOLS_KEY");
+        let tools = [ToolDefinition {
+            name: "fs_read".to_string(),
+            description: "Reads a file".to_string(),
+            parameters: json!({ "type": "object", "properties": { "path": { "type": "string" } } }),
+        }];
+
+        let response = provider
+            .call_llm_api_with_tools(vec![ChatMessage::new("user", "Read a.txt")], &tools)
+            .await
+            .unwrap();
+
+        assert_eq!(response.tool_calls.len(), 1);
+        assert_eq!(response.tool_calls[0].name, "fs_read");
+        assert_eq!(response.tool_calls[0].arguments, json!({ "path": "a.txt" }));
+
+        let request = requests.lock().unwrap()[0].clone();
+        let declaration = &request.body["tools"][0]["functionDeclarations"][0];
+        assert_eq!(declaration["name"], "fs_read");
+        assert_eq!(declaration["parametersJsonSchema"], tools[0].parameters);
+        assert!(request.body.get("systemInstruction").is_none());
+    }
+}
@@ -0,0 +1,333 @@
+use super::openai::{function_tools, parse_tool_calls, to_request_message};
+use super::{
+    api_key, base_url, chat_completions_usage, pair_tool_calls, plain_messages, read_lines,
+    sse_data, with_headers, LlmProvider, RetryPolicy, TokenSender,
+};
+use crate::config::{EndpointConfig, GenerationConfig};
+use crate::llm::{http_client, ChatMessage, ContentPart, LlmResponse, ToolDefinition};
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::error::Error;
+
+/// Provider implementation for Mistral's chat completions API
+#[derive(Debug)]
+pub struct MistralProvider {
+    /// API key loaded from environment
+    api_key: String,
+    /// Model identifier to use (e.g. "mistral-large-latest")
+    model: String,
+    /// Generation settings sent with every request
+    generation: GenerationConfig,
+    /// Retry policy of the HTTP requests
+    retry: RetryPolicy,
+    /// URL of the chat endpoint
+    url: String,
+    /// Extra headers sent with every request
+    headers: BTreeMap<String, String>,
+}
+
+impl MistralProvider {
+    /// Creates a new Mistral provider instance
+    ///
+    /// # Arguments
+    /// * `model` - The model identifier to use
+    /// * `generation` - Generation settings sent with every request
+    /// * `retry` - Retry policy of the HTTP requests
+    /// * `endpoint` - Base URL, API key variable and extra headers
+    ///
+    /// # Returns
+    /// * `Result<Self, Box<dyn Error>>` - Provider instance or error if API key not found
+    pub fn new(
+        model: &str,
+        generation: GenerationConfig,
+        retry: RetryPolicy,
+        endpoint: &EndpointConfig,
+    ) -> Result<Self, Box<dyn Error>> {
+        let api_key = api_key(endpoint, "MISTRAL_API_KEY")?;
+        let url = base_url(endpoint, "MISTRAL_BASE_URL", "https://api.mistral.ai/v1");
+        Ok(MistralProvider {
+            api_key,
+            model: model.to_string(),
+            generation,
+            retry,
+            url: format!("{}/chat/completions", url),
+            headers: endpoint.headers.clone(),
+        })
+    }
+
+    /// Sends a chat completions request
+    ///
+    /// Mistral rejects unknown fields, so the request only carries the options it
+    /// supports; the seed is sent as `random_seed`.
+    ///
+    /// # Arguments
+    /// * `messages` - Vector of chat messages containing the conversation history
+    /// * `tools` - Tools exposed to the model, may be empty
+    /// * `schema` - JSON schema the response must match, if any
+    /// * `stream` - Whether the response is streamed as server-sent events
+    ///
+    /// # Returns
+    /// * `Result<reqwest::Response, Box<dyn Error>>` - The successful HTTP response or error
+    async fn send(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: &[ToolDefinition],
+        schema: Option<&Value>,
+        stream: bool,
+    ) -> Result<reqwest::Response, Box<dyn Error>> {
+        let client = http_client();
+        let messages = messages.into_iter().map(describe_files);
+        let messages: Vec<Value> = if tools.is_empty() {
+            plain_messages(messages.collect())
+                .iter()
+                .map(to_request_message)
+                .collect()
+        } else {
+            pair_tool_calls(messages.collect())
+                .iter()
+                .map(to_request_message)
+                .collect()
+        };
+        let mut request_body = json!({
+          "model": self.model,
+          "messages": messages,
+          "temperature": self.generation.temperature.unwrap_or(0.7),
+          "stream": stream
+        });
+        if let Some(max_tokens) = self.generation.max_tokens {
+            request_body["max_tokens"] = json!(max_tokens);
+        }
+        if let Some(top_p) = self.generation.top_p {
+            request_body["top_p"] = json!(top_p);
+        }
+        if !self.generation.stop.is_empty() {
+            request_body["stop"] = json!(self.generation.stop);
+        }
+        if let Some(seed) = self.generation.seed {
+            request_body["random_seed"] = json!(seed);
+        }
+        if let Some(schema) = schema {
+            request_body["response_format"] = json!({
+                "type": "json_schema",
+                "json_schema": { "name": "output", "schema": schema }
+            });
+        }
+        if !tools.is_empty() {
+            request_body["tools"] = json!(function_tools(tools));
+            request_body["parallel_tool_calls"] = json!(false);
+        }
+
+        let res = self
+            .retry
+            .send("Mistral", || {
+                with_headers(client.post(&self.url), &self.headers)
+                    .bearer_auth(&self.api_key)
+                    .json(&request_body)
+            })
+            .await?;
+        Ok(res)
+    }
+
+    /// Reads the text and usage of a chat completions response
+    ///
+    /// # Arguments
+    /// * `res` - The successful HTTP response
+    ///
+    /// # Returns
+    /// * `Result<LlmResponse, Box<dyn Error>>` - The response text and usage, or error
+    async fn text_response(&self, res: reqwest::Response) -> Result<LlmResponse, Box<dyn Error>> {
+        let json_resp: serde_json::Value = res.json().await?;
+        if let Some(content) = json_resp["choices"][0]["message"]["content"].as_str() {
+            Ok(LlmResponse::text(content.trim().to_string())
+                .with_usage(chat_completions_usage(&json_resp)))
+        } else {
+            Err("No content in Mistral LLM response".into())
+        }
+    }
+}
+
+#[async_trait]
+impl LlmProvider for MistralProvider {
+    /// Calls Mistral's chat completions API
+    ///
+    /// # Arguments
+    /// * `messages` - Vector of chat messages containing the conversation history
+    ///
+    /// # Returns
+    /// * `Result<LlmResponse, Box<dyn Error>>` - Generated response text and usage, or error
+    async fn call_llm_api(
+        &self,
+        messages: Vec<ChatMessage>,
+    ) -> Result<LlmResponse, Box<dyn Error>> {
+        let res = self.send(messages, &[], None, false).await?;
+        self.text_response(res).await
+    }
+
+    fn supports_structured_output(&self) -> bool {
+        true
+    }
+
+    /// Calls Mistral's chat completions API with a `json_schema` response format
+    ///
+    /// # Arguments
+    /// * `messages` - Vector of chat messages containing the conversation history
+    /// * `schema` - JSON schema the response must match
+    ///
+    /// # Returns
+    /// * `Result<LlmResponse, Box<dyn Error>>` - The JSON response and usage, or error
+    async fn call_llm_api_structured(
+        &self,
+        messages: Vec<ChatMessage>,
+        schema: &Value,
+    ) -> Result<LlmResponse, Box<dyn Error>> {
+        let res = self.send(messages, &[], Some(schema), false).await?;
+        self.text_response(res).await
+    }
+
+    fn supports_tools(&self) -> bool {
+        true
+    }
+
+    /// Calls Mistral's chat completions API with function tools
+    ///
+    /// # Arguments
+    /// * `messages` - Vector of chat messages containing the conversation history
+    /// * `tools` - Tools the model may call
+    ///
+    /// # Returns
+    /// * `Result<LlmResponse, Box<dyn Error>>` - The response text and tool calls, or error
+    async fn call_llm_api_with_tools(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: &[ToolDefinition],
+    ) -> Result<LlmResponse, Box<dyn Error>> {
+        let res = self.send(messages, tools, None, false).await?;
+        let json_resp: serde_json::Value = res.json().await?;
+        let message = &json_resp["choices"][0]["message"];
+        let content = message["content"].as_str().unwrap_or_default().trim();
+        let tool_calls = parse_tool_calls(message)?;
+
+        if content.is_empty() && tool_calls.is_empty() {
+            return Err("No content in Mistral LLM response".into());
+        }
+        Ok(LlmResponse {
+            content: content.to_string(),
+            tool_calls,
+            usage: chat_completions_usage(&json_resp),
+        })
+    }
+
+    /// Calls Mistral's chat completions API with server-sent events streaming
+    ///
+    /// # Arguments
+    /// * `messages` - Vector of chat messages containing the conversation history
+    /// * `tokens` - Channel receiving the partial tokens
+    ///
+    /// # Returns
+    /// * `Result<LlmResponse, Box<dyn Error>>` - The assembled response text and usage, or error
+    async fn call_llm_api_stream(
+        &self,
+        messages: Vec<ChatMessage>,
+        tokens: &TokenSender,
+    ) -> Result<LlmResponse, Box<dyn Error>> {
+        let res = self.send(messages, &[], None, true).await?;
+        let mut content = String::new();
+        let mut usage = None;
+        read_lines(res, |line| {
+            let Some(data) = sse_data(line) else {
+                return Ok(true);
+            };
+            if data == "[DONE]" {
+                return Ok(false);
+            }
+            let event: serde_json::Value = serde_json::from_str(data)?;
+            if let Some(token) = event["choices"][0]["delta"]["content"].as_str() {
+                content.push_str(token);
+                let _ = tokens.send(token.to_string());
+            }
+            usage = chat_completions_usage(&event).or(usage);
+            Ok(true)
+        })
+        .await?;
+
+        if content.is_empty() {
+            return Err("No content in Mistral LLM response".into());
+        }
+        Ok(LlmResponse::text(content.trim().to_string()).with_usage(usage))
+    }
+}
+
+/// Replaces the documents of a message by their description, the chat completions API of
+/// Mistral only accepting images
+///
+/// # Arguments
+/// * `msg` - The message to convert
+///
+/// # Returns
+/// * `ChatMessage` - The message with its images and text parts only
+fn describe_files(mut msg: ChatMessage) -> ChatMessage {
+    for part in &mut msg.parts {
+        if let ContentPart::File { .. } = part {
+            *part = ContentPart::Text {
+                text: part.describe(),
+            };
+        }
+    }
+    msg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::stub;
+    use super::*;
+
+    #[tokio::test]
+    async fn sends_chat_completions_request_and_reads_response() {
+        let (url, requests) = stub::serve(json!({
+            "choices": [{ "message": { "role": "assistant", "content": "Bonjour" } }],
+            "usage": { "prompt_tokens": 9, "completion_tokens": 2 }
+        }))
+        .await;
+        std::env::set_var("KHEISH_TEST_MISTRAL_KEY", "mistral-key");
+        let endpoint = EndpointConfig {
+            base_url: Some(url),
+            api_key_env: Some("KHEISH_TEST_MISTRAL_KEY".to_string()),
+            ..Default::default()
+        };
+        let generation = GenerationConfig {
+            seed: Some(7),
+            ..Default::default()
+        };
+        let provider = MistralProvider::new(
+            "mistral-test",
+            generation,
+            RetryPolicy::default(),
+            &endpoint,
+        )
+        .unwrap();
+
+        let response = provider
+            .call_llm_api(vec![
+                ChatMessage::new("system", "Answer in French."),
+                ChatMessage::new("user", "Hello"),
+            ])
+            .await
+            .unwrap();
+
+        assert_eq!(response.content, "Bonjour");
+        let usage = response.usage.unwrap();
+        assert_eq!((usage.input_tokens, usage.output_tokens), (9, 2));
+
+        let request = requests.lock().unwrap()[0].clone();
+        assert_eq!(request.uri, "/chat/completions");
+        assert_eq!(request.header("authorization"), "Bearer mistral-key");
+        assert_eq!(request.body["model"], "mistral-test");
+        assert_eq!(request.body["random_seed"], 7);
+        assert!(request.body.get("seed").is_none());
+        let messages = request.body["messages"].as_array().unwrap();
+        assert_eq!(messages[0]["role"], "system");
+        assert_eq!(messages[0]["content"], "Answer in French.");
+        assert_eq!(messages[1]["role"], "user");
+    }
+}
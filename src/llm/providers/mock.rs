@@ -0,0 +1,436 @@
+use super::{LlmProvider, TokenSender};
+use crate::llm::{ChatMessage, LlmResponse, ToolCall, ToolDefinition};
+use async_trait::async_trait;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::warn;
+
+/// Cassettes already truncated by a recorder of this process; the recorders of the
+/// other roles append to them
+static RECORDED_CASSETTES: Lazy<Mutex<HashSet<PathBuf>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+/// Provider answering from a script or a cassette instead of a real LLM, for offline runs
+#[derive(Debug)]
+pub struct MockProvider {
+    /// Source of the responses
+    source: Source,
+    /// Role of the agent owning the provider, None until set
+    role: Option<String>,
+}
+
+/// Where the responses of a mock provider come from
+#[derive(Debug)]
+enum Source {
+    /// Scripted responses, with the number of times each one was served
+    Script(Vec<ScriptedResponse>, Mutex<Vec<usize>>),
+    /// Recorded interactions, with whether each one was served
+    Replay(Vec<Interaction>, Mutex<Vec<bool>>),
+    /// Real provider whose interactions are appended to the cassette
+    Record(Box<dyn LlmProvider>, PathBuf),
+}
+
+/// Script of a mock provider
+#[derive(Debug, Deserialize)]
+struct Script {
+    /// Responses, tried in order
+    responses: Vec<ScriptedResponse>,
+}
+
+/// A scripted response and the requests it answers
+#[derive(Debug, Deserialize)]
+struct ScriptedResponse {
+    /// Role the response is reserved to; any role if unset
+    #[serde(default)]
+    role: Option<String>,
+    /// Regex the content of the last message must match; any message if unset
+    #[serde(default, rename = "match", with = "serde_regex")]
+    pattern: Option<Regex>,
+    /// Text of the response
+    #[serde(default)]
+    content: String,
+    /// Tools called by the response
+    #[serde(default)]
+    tool_calls: Vec<ScriptedToolCall>,
+    /// Number of times the response is served; unlimited if unset
+    #[serde(default)]
+    times: Option<usize>,
+}
+
+/// A tool call of a scripted response
+#[derive(Debug, Deserialize)]
+struct ScriptedToolCall {
+    /// Name of the called tool (`<module>_<action>`)
+    name: String,
+    /// Arguments of the call
+    #[serde(default)]
+    arguments: Value,
+}
+
+/// A request/response pair of a cassette, stored as one JSON line
+#[derive(Debug, Serialize, Deserialize)]
+struct Interaction {
+    /// Role of the agent that made the request
+    role: Option<String>,
+    /// Messages of the request
+    messages: Value,
+    /// Text of the response
+    content: String,
+    /// Tools called by the response
+    #[serde(default)]
+    tool_calls: Vec<ToolCall>,
+}
+
+/// Deserializes the optional regex of a scripted response
+mod serde_regex {
+    use regex::Regex;
+    use serde::{Deserialize, Deserializer};
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<Regex>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Option::<String>::deserialize(deserializer)?
+            .map(|pattern| Regex::new(&pattern).map_err(serde::de::Error::custom))
+            .transpose()
+    }
+}
+
+impl MockProvider {
+    /// Creates a provider serving the responses of a script
+    ///
+    /// # Arguments
+    /// * `path` - Path of the YAML or JSON script (`responses: [{ role, match, content,
+    ///   tool_calls, times }]`)
+    ///
+    /// # Returns
+    /// * `Result<Self, Box<dyn Error>>` - Provider instance, or an error if the script cannot
+    ///   be read or parsed
+    pub fn script(path: &str) -> Result<Self, Box<dyn Error>> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read mock script '{}': {}", path, e))?;
+        let script: Script = if path.ends_with(".json") {
+            serde_json::from_str(&text)
+                .map_err(|e| format!("Invalid mock script '{}': {}", path, e))?
+        } else {
+            serde_yaml::from_str(&text)
+                .map_err(|e| format!("Invalid mock script '{}': {}", path, e))?
+        };
+        let served = Mutex::new(vec![0; script.responses.len()]);
+        Ok(MockProvider {
+            source: Source::Script(script.responses, served),
+            role: None,
+        })
+    }
+
+    /// Creates a provider serving back the interactions of a cassette
+    ///
+    /// # Arguments
+    /// * `path` - Path of the cassette, one JSON interaction per line
+    ///
+    /// # Returns
+    /// * `Result<Self, Box<dyn Error>>` - Provider instance, or an error if the cassette
+    ///   cannot be read or parsed
+    pub fn replay(path: &str) -> Result<Self, Box<dyn Error>> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read mock cassette '{}': {}", path, e))?;
+        let mut interactions = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let interaction: Interaction = serde_json::from_str(line).map_err(|e| {
+                format!("Invalid mock cassette '{}' line {}: {}", path, index + 1, e)
+            })?;
+            interactions.push(interaction);
+        }
+        let served = Mutex::new(vec![false; interactions.len()]);
+        Ok(MockProvider {
+            source: Source::Replay(interactions, served),
+            role: None,
+        })
+    }
+
+    /// Creates a provider calling a real provider and recording its interactions
+    ///
+    /// The cassette is truncated by the first recorder of this process and appended to by
+    /// the recorders of the other roles.
+    ///
+    /// # Arguments
+    /// * `path` - Path of the cassette
+    /// * `inner` - The real provider
+    ///
+    /// # Returns
+    /// * `Result<Self, Box<dyn Error>>` - Provider instance, or an error if the cassette
+    ///   cannot be created
+    pub fn record(path: &str, inner: Box<dyn LlmProvider>) -> Result<Self, Box<dyn Error>> {
+        let path = PathBuf::from(path);
+        let mut recorded = RECORDED_CASSETTES.lock().unwrap();
+        if recorded.insert(path.clone()) {
+            if let Some(parent) = path
+                .parent()
+                .filter(|parent| !parent.as_os_str().is_empty())
+            {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::File::create(&path).map_err(|e| {
+                format!("Failed to create mock cassette '{}': {}", path.display(), e)
+            })?;
+        }
+        Ok(MockProvider {
+            source: Source::Record(inner, path),
+            role: None,
+        })
+    }
+
+    /// Answers a request from the script or the cassette
+    ///
+    /// # Arguments
+    /// * `messages` - Messages of the request
+    ///
+    /// # Returns
+    /// * `Result<LlmResponse, Box<dyn Error>>` - The matching response, or an error if none
+    ///   is left
+    fn respond(&self, messages: &[ChatMessage]) -> Result<LlmResponse, Box<dyn Error>> {
+        match &self.source {
+            Source::Script(responses, served) => self.scripted(responses, served, messages),
+            Source::Replay(interactions, served) => self.replayed(interactions, served, messages),
+            Source::Record(..) => unreachable!("recorders call their inner provider"),
+        }
+    }
+
+    /// Returns the first scripted response matching the role and the last message
+    fn scripted(
+        &self,
+        responses: &[ScriptedResponse],
+        served: &Mutex<Vec<usize>>,
+        messages: &[ChatMessage],
+    ) -> Result<LlmResponse, Box<dyn Error>> {
+        let last = messages
+            .last()
+            .map(|msg| msg.content.as_str())
+            .unwrap_or("");
+        let mut served = served.lock().unwrap();
+        let index = responses
+            .iter()
+            .enumerate()
+            .position(|(index, response)| {
+                response.times.is_none_or(|times| served[index] < times)
+                    && (response.role.is_none() || response.role == self.role)
+                    && response
+                        .pattern
+                        .as_ref()
+                        .is_none_or(|pattern| pattern.is_match(last))
+            })
+            .ok_or_else(|| {
+                format!(
+                    "No scripted response left for role '{}' and message: {}",
+                    self.role.as_deref().unwrap_or("none"),
+                    last.chars().take(200).collect::<String>()
+                )
+            })?;
+        served[index] += 1;
+
+        let response = &responses[index];
+        let call = served[index];
+        Ok(LlmResponse {
+            content: response.content.clone(),
+            tool_calls: response
+                .tool_calls
+                .iter()
+                .enumerate()
+                .map(|(position, tool_call)| ToolCall {
+                    id: format!("mock-{}-{}-{}", index, call, position),
+                    name: tool_call.name.clone(),
+                    arguments: tool_call.arguments.clone(),
+                })
+                .collect(),
+            usage: None,
+        })
+    }
+
+    /// Returns the first unserved interaction of the role with the same messages, else the
+    /// next unserved interaction of the role
+    fn replayed(
+        &self,
+        interactions: &[Interaction],
+        served: &Mutex<Vec<bool>>,
+        messages: &[ChatMessage],
+    ) -> Result<LlmResponse, Box<dyn Error>> {
+        let request = serde_json::to_value(messages)?;
+        let mut served = served.lock().unwrap();
+        let unserved = |index: &usize| !served[*index] && interactions[*index].role == self.role;
+        let index = (0..interactions.len())
+            .filter(unserved)
+            .find(|index| interactions[*index].messages == request)
+            .or_else(|| {
+                let index = (0..interactions.len()).find(unserved)?;
+                warn!(
+                    "Mock cassette has no interaction matching the request of role '{}', \
+                     replaying the next one",
+                    self.role.as_deref().unwrap_or("none")
+                );
+                Some(index)
+            })
+            .ok_or_else(|| {
+                format!(
+                    "No recorded interaction left for role '{}'",
+                    self.role.as_deref().unwrap_or("none")
+                )
+            })?;
+        served[index] = true;
+
+        let interaction = &interactions[index];
+        Ok(LlmResponse {
+            content: interaction.content.clone(),
+            tool_calls: interaction.tool_calls.clone(),
+            usage: None,
+        })
+    }
+
+    /// Appends an interaction to the cassette
+    ///
+    /// # Arguments
+    /// * `path` - Path of the cassette
+    /// * `messages` - Messages of the request
+    /// * `response` - Response of the real provider
+    fn save(
+        &self,
+        path: &Path,
+        messages: &[ChatMessage],
+        response: &LlmResponse,
+    ) -> Result<(), Box<dyn Error>> {
+        let interaction = Interaction {
+            role: self.role.clone(),
+            messages: serde_json::to_value(messages)?,
+            content: response.content.clone(),
+            tool_calls: response.tool_calls.clone(),
+        };
+        let line = format!("{}\n", serde_json::to_string(&interaction)?);
+        let _recorded = RECORDED_CASSETTES.lock().unwrap();
+        OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(path)?
+            .write_all(line.as_bytes())?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl LlmProvider for MockProvider {
+    /// Answers from the script or the cassette, or calls and records the real provider
+    ///
+    /// # Arguments
+    /// * `messages` - Vector of chat messages containing the conversation history
+    ///
+    /// # Returns
+    /// * `Result<LlmResponse, Box<dyn Error>>` - The response, or an error if none matches
+    async fn call_llm_api(
+        &self,
+        messages: Vec<ChatMessage>,
+    ) -> Result<LlmResponse, Box<dyn Error>> {
+        let Source::Record(inner, path) = &self.source else {
+            return self.respond(&messages);
+        };
+        let response = inner.call_llm_api(messages.clone()).await?;
+        self.save(path, &messages, &response)?;
+        Ok(response)
+    }
+
+    /// Streams the response of the real provider when recording; scripted and replayed
+    /// responses are sent as a single token
+    ///
+    /// # Arguments
+    /// * `messages` - Vector of chat messages containing the conversation history
+    /// * `tokens` - Channel receiving the partial tokens
+    ///
+    /// # Returns
+    /// * `Result<LlmResponse, Box<dyn Error>>` - The assembled response, or error
+    async fn call_llm_api_stream(
+        &self,
+        messages: Vec<ChatMessage>,
+        tokens: &TokenSender,
+    ) -> Result<LlmResponse, Box<dyn Error>> {
+        let Source::Record(inner, path) = &self.source else {
+            let response = self.respond(&messages)?;
+            let _ = tokens.send(response.content.clone());
+            return Ok(response);
+        };
+        let response = inner.call_llm_api_stream(messages.clone(), tokens).await?;
+        self.save(path, &messages, &response)?;
+        Ok(response)
+    }
+
+    fn supports_structured_output(&self) -> bool {
+        match &self.source {
+            Source::Record(inner, _) => inner.supports_structured_output(),
+            _ => false,
+        }
+    }
+
+    /// Calls and records the real provider with the schema when recording; scripted and
+    /// replayed responses ignore it
+    ///
+    /// # Arguments
+    /// * `messages` - Vector of chat messages containing the conversation history
+    /// * `schema` - JSON schema the response must match
+    ///
+    /// # Returns
+    /// * `Result<LlmResponse, Box<dyn Error>>` - The response, holding the JSON document, or error
+    async fn call_llm_api_structured(
+        &self,
+        messages: Vec<ChatMessage>,
+        schema: &Value,
+    ) -> Result<LlmResponse, Box<dyn Error>> {
+        let Source::Record(inner, path) = &self.source else {
+            return self.respond(&messages);
+        };
+        let response = inner
+            .call_llm_api_structured(messages.clone(), schema)
+            .await?;
+        self.save(path, &messages, &response)?;
+        Ok(response)
+    }
+
+    fn supports_tools(&self) -> bool {
+        match &self.source {
+            Source::Record(inner, _) => inner.supports_tools(),
+            _ => true,
+        }
+    }
+
+    /// Answers a request exposing tools; scripted and replayed responses may call them
+    ///
+    /// # Arguments
+    /// * `messages` - Vector of chat messages containing the conversation history
+    /// * `tools` - Tools the model may call
+    ///
+    /// # Returns
+    /// * `Result<LlmResponse, Box<dyn Error>>` - The response text and tool calls, or error
+    async fn call_llm_api_with_tools(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: &[ToolDefinition],
+    ) -> Result<LlmResponse, Box<dyn Error>> {
+        let Source::Record(inner, path) = &self.source else {
+            return self.respond(&messages);
+        };
+        let response = inner
+            .call_llm_api_with_tools(messages.clone(), tools)
+            .await?;
+        self.save(path, &messages, &response)?;
+        Ok(response)
+    }
+
+    fn set_role(&mut self, role: &str) {
+        self.role = Some(role.to_string());
+    }
+}
@@ -1,14 +1,345 @@
-use crate::llm::ChatMessage;
+use crate::config::{EndpointConfig, GenerationConfig};
+use crate::llm::{ChatMessage, ContentPart, LlmResponse, TokenUsage, ToolDefinition};
 use async_trait::async_trait;
+use reqwest::RequestBuilder;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashSet};
 use std::error::Error;
 use std::fmt::Debug;
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::warn;
 
 pub mod anthropic;
+pub mod azure_openai;
 pub mod ollama;
 pub mod openai;
+pub mod openai_compatible;
 pub mod deepseek;
+pub mod gemini;
+pub mod mistral;
+pub mod mock;
+mod retry;
+#[cfg(test)]
+mod stub;
+
+pub use retry::{is_fatal_error, LlmError, RetryPolicy};
+
+/// Channel receiving the partial tokens of a streamed response
+pub type TokenSender = UnboundedSender<String>;
 
 #[async_trait]
 pub trait LlmProvider: Debug + Send + Sync {
-    async fn call_llm_api(&self, messages: Vec<ChatMessage>) -> Result<String, Box<dyn Error>>;
+    async fn call_llm_api(&self, messages: Vec<ChatMessage>)
+        -> Result<LlmResponse, Box<dyn Error>>;
+
+    /// Calls the LLM API in streaming mode, sending every partial token as it arrives
+    ///
+    /// Providers without streaming support send the whole response as a single token.
+    ///
+    /// # Arguments
+    /// * `messages` - Vector of chat messages containing the conversation history
+    /// * `tokens` - Channel receiving the partial tokens
+    ///
+    /// # Returns
+    /// * `Result<LlmResponse, Box<dyn Error>>` - The assembled response text and usage, or error
+    async fn call_llm_api_stream(
+        &self,
+        messages: Vec<ChatMessage>,
+        tokens: &TokenSender,
+    ) -> Result<LlmResponse, Box<dyn Error>> {
+        let response = self.call_llm_api(messages).await?;
+        let _ = tokens.send(response.content.clone());
+        Ok(response)
+    }
+
+    /// Whether the provider implements native tool calling
+    fn supports_tools(&self) -> bool {
+        false
+    }
+
+    /// Calls the LLM API, exposing the given tools through native tool calling
+    ///
+    /// Providers without tool support ignore the tools and return a text response.
+    ///
+    /// # Arguments
+    /// * `messages` - Vector of chat messages containing the conversation history
+    /// * `tools` - Tools the model may call
+    ///
+    /// # Returns
+    /// * `Result<LlmResponse, Box<dyn Error>>` - The response text and tool calls, or error
+    async fn call_llm_api_with_tools(
+        &self,
+        messages: Vec<ChatMessage>,
+        _tools: &[ToolDefinition],
+    ) -> Result<LlmResponse, Box<dyn Error>> {
+        self.call_llm_api(messages).await
+    }
+
+    /// Whether the provider can constrain a response to a JSON schema
+    fn supports_structured_output(&self) -> bool {
+        false
+    }
+
+    /// Calls the LLM API, constraining the response to a JSON schema
+    ///
+    /// Providers without structured output ignore the schema and return a text response,
+    /// leaving the prompt to describe the expected output.
+    ///
+    /// # Arguments
+    /// * `messages` - Vector of chat messages containing the conversation history
+    /// * `schema` - JSON schema the response must match
+    ///
+    /// # Returns
+    /// * `Result<LlmResponse, Box<dyn Error>>` - The response, holding the JSON document, or error
+    async fn call_llm_api_structured(
+        &self,
+        messages: Vec<ChatMessage>,
+        _schema: &Value,
+    ) -> Result<LlmResponse, Box<dyn Error>> {
+        self.call_llm_api(messages).await
+    }
+
+    /// Tells the provider which role it answers, used by the `mock` provider to match
+    /// scripted responses; other providers ignore it
+    ///
+    /// # Arguments
+    /// * `role` - Name of the role owning the provider
+    fn set_role(&mut self, _role: &str) {}
+}
+
+/// Returns the generation settings that a provider cannot send and ignores
+///
+/// Temperature and max tokens are supported by every provider; the `mock` provider
+/// ignores all settings and reports none.
+///
+/// # Arguments
+/// * `provider` - Name of the provider
+/// * `generation` - Generation settings of the requests
+///
+/// # Returns
+/// * `Vec<&'static str>` - Names of the settings that are set but not supported
+pub fn unsupported_generation_options(
+    provider: &str,
+    generation: &GenerationConfig,
+) -> Vec<&'static str> {
+    let supported: &[&str] = match provider {
+        "openai" | "openai_compatible" | "azure_openai" => {
+            &["top_p", "stop", "seed", "reasoning_effort"]
+        }
+        "anthropic" | "deepseek" => &["top_p", "stop"],
+        "ollama" | "gemini" | "mistral" => &["top_p", "stop", "seed"],
+        _ => return Vec::new(),
+    };
+    [
+        ("top_p", generation.top_p.is_some()),
+        ("stop", !generation.stop.is_empty()),
+        ("seed", generation.seed.is_some()),
+        ("reasoning_effort", generation.reasoning_effort.is_some()),
+    ]
+    .into_iter()
+    .filter(|(option, set)| *set && !supported.contains(option))
+    .map(|(option, _)| option)
+    .collect()
+}
+
+/// Returns the base URL of a provider, without trailing slash
+///
+/// # Arguments
+/// * `endpoint` - Endpoint settings of the provider
+/// * `env` - Environment variable overriding the default URL (e.g. "OPENAI_BASE_URL")
+/// * `default` - URL of the public API
+///
+/// # Returns
+/// * `String` - The configured URL, else the environment variable, else the default
+pub fn base_url(endpoint: &EndpointConfig, env: &str, default: &str) -> String {
+    endpoint
+        .base_url
+        .clone()
+        .or_else(|| std::env::var(env).ok().filter(|url| !url.is_empty()))
+        .unwrap_or_else(|| default.to_string())
+        .trim_end_matches('/')
+        .to_string()
+}
+
+/// Reads the API key of a provider from the environment
+///
+/// # Arguments
+/// * `endpoint` - Endpoint settings of the provider, possibly naming another variable
+/// * `default_env` - Variable holding the key when the endpoint names none
+///
+/// # Returns
+/// * `Result<String, Box<dyn Error>>` - The API key, or an error if the variable is not set
+pub fn api_key(endpoint: &EndpointConfig, default_env: &str) -> Result<String, Box<dyn Error>> {
+    let env = endpoint.api_key_env.as_deref().unwrap_or(default_env);
+    std::env::var(env).map_err(|_| format!("{} environment variable not set", env).into())
+}
+
+/// Reads the media type and base64 data of an image or file part
+///
+/// # Arguments
+/// * `part` - The part to send
+///
+/// # Returns
+/// * `Option<(String, String)>` - The media type and data, or None with a warning if the
+///   file cannot be read, in which case the part is sent as its description
+pub fn encode_part(part: &ContentPart) -> Option<(String, String)> {
+    match part.encode() {
+        Ok(encoded) => Some(encoded),
+        Err(e) => {
+            warn!("Attachment not sent: {}", e);
+            None
+        }
+    }
+}
+
+/// Adds the extra headers of an endpoint to a request
+///
+/// # Arguments
+/// * `request` - The request being built
+/// * `headers` - Header names and values
+///
+/// # Returns
+/// * `RequestBuilder` - The request with the headers
+pub fn with_headers(request: RequestBuilder, headers: &BTreeMap<String, String>) -> RequestBuilder {
+    headers.iter().fold(request, |request, (name, value)| {
+        request.header(name, value)
+    })
+}
+
+/// Reads the `usage` block of a chat completions response or stream chunk
+///
+/// # Arguments
+/// * `value` - The response, or the last chunk of a stream
+///
+/// # Returns
+/// * `Option<TokenUsage>` - The prompt and completion tokens, if reported
+pub fn chat_completions_usage(value: &Value) -> Option<TokenUsage> {
+    let usage = &value["usage"];
+    Some(TokenUsage {
+        input_tokens: usage["prompt_tokens"].as_u64()?,
+        output_tokens: usage["completion_tokens"].as_u64()?,
+    })
+}
+
+/// Converts tool calls and tool results into plain text messages
+///
+/// Used for requests sent without tools, which providers reject when the history
+/// contains tool messages.
+///
+/// # Arguments
+/// * `messages` - The conversation history
+///
+/// # Returns
+/// * `Vec<ChatMessage>` - Messages with only a role and a content
+pub fn plain_messages(messages: Vec<ChatMessage>) -> Vec<ChatMessage> {
+    messages
+        .into_iter()
+        .map(|msg| {
+            if msg.role == "tool" {
+                return ChatMessage::new("user", &msg.content).with_parts(msg.parts);
+            }
+            let mut content = msg.content;
+            for call in &msg.tool_calls {
+                if !content.is_empty() {
+                    content.push('\n');
+                }
+                content.push_str(&call.to_text());
+            }
+            ChatMessage::new(&msg.role, &content).with_parts(msg.parts)
+        })
+        .collect()
+}
+
+/// Converts the messages for a provider without attachments, describing their parts as text
+///
+/// # Arguments
+/// * `messages` - The conversation history
+///
+/// # Returns
+/// * `Vec<ChatMessage>` - Messages with only a role and a content
+pub fn text_messages(messages: Vec<ChatMessage>) -> Vec<ChatMessage> {
+    plain_messages(messages)
+        .iter()
+        .map(|msg| ChatMessage::new(&msg.role, &msg.text_with_parts()))
+        .collect()
+}
+
+/// Keeps tool calls and results only where they are properly paired
+///
+/// Trimming the history can drop one side of a pair; an orphan result becomes a
+/// user message and an unanswered call is rendered as text.
+///
+/// # Arguments
+/// * `messages` - The conversation history
+///
+/// # Returns
+/// * `Vec<ChatMessage>` - Messages whose tool calls are all answered
+pub fn pair_tool_calls(messages: Vec<ChatMessage>) -> Vec<ChatMessage> {
+    let answered: HashSet<String> = messages
+        .iter()
+        .filter_map(|msg| msg.tool_call_id.clone())
+        .collect();
+    let mut called = HashSet::new();
+    let mut paired = Vec::with_capacity(messages.len());
+    for msg in messages {
+        if msg.role == "tool" {
+            let id = msg.tool_call_id.clone().unwrap_or_default();
+            if called.contains(&id) {
+                paired.push(msg);
+            } else {
+                paired.extend(plain_messages(vec![msg]));
+            }
+        } else if msg
+            .tool_calls
+            .iter()
+            .all(|call| answered.contains(&call.id))
+        {
+            called.extend(msg.tool_calls.iter().map(|call| call.id.clone()));
+            paired.push(msg);
+        } else {
+            paired.extend(plain_messages(vec![msg]));
+        }
+    }
+    paired
+}
+
+/// Reads a streamed response body line by line
+///
+/// # Arguments
+/// * `res` - The HTTP response to read
+/// * `on_line` - Called with every non-empty line, returns false to stop reading
+///
+/// # Returns
+/// * `Result<(), Box<dyn Error>>` - Ok once the body is read or `on_line` stopped, or the first error
+pub async fn read_lines<F>(mut res: reqwest::Response, mut on_line: F) -> Result<(), Box<dyn Error>>
+where
+    F: FnMut(&str) -> Result<bool, Box<dyn Error>> + Send,
+{
+    let mut buffer: Vec<u8> = Vec::new();
+    while let Some(chunk) = res.chunk().await? {
+        buffer.extend_from_slice(&chunk);
+        while let Some(end) = buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            if !line.trim().is_empty() && !on_line(line.trim())? {
+                return Ok(());
+            }
+        }
+    }
+    let line = String::from_utf8_lossy(&buffer);
+    if !line.trim().is_empty() {
+        on_line(line.trim())?;
+    }
+    Ok(())
+}
+
+/// Returns the payload of a server-sent event `data:` line
+///
+/// # Arguments
+/// * `line` - A line of a server-sent events stream
+///
+/// # Returns
+/// * `Option<&str>` - The data of the event, or None for any other field
+pub fn sse_data(line: &str) -> Option<&str> {
+    line.strip_prefix("data:").map(str::trim_start)
 }
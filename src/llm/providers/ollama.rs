@@ -1,8 +1,12 @@
-use super::LlmProvider;
-use crate::llm::ChatMessage;
+use super::{
+    base_url, encode_part, pair_tool_calls, plain_messages, read_lines, with_headers, LlmProvider,
+    RetryPolicy, TokenSender,
+};
+use crate::config::{EndpointConfig, GenerationConfig};
+use crate::llm::{http_client, ChatMessage, LlmResponse, TokenUsage, ToolCall, ToolDefinition};
 use async_trait::async_trait;
-use reqwest::Client;
-use serde_json::json;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
 use std::error::Error;
 
 /// Provider implementation for Ollama's local API
@@ -10,6 +14,14 @@ use std::error::Error;
 pub struct OllamaProvider {
     /// Model identifier to use (e.g. "llama2", "codellama")
     model: String,
+    /// Generation settings sent with every request
+    generation: GenerationConfig,
+    /// Retry policy of the HTTP requests
+    retry: RetryPolicy,
+    /// URL of the chat endpoint
+    url: String,
+    /// Extra headers sent with every request
+    headers: BTreeMap<String, String>,
 }
 
 impl OllamaProvider {
@@ -17,14 +29,109 @@ impl OllamaProvider {
     ///
     /// # Arguments
     /// * `model` - The model identifier to use
+    /// * `generation` - Generation settings sent with every request
+    /// * `retry` - Retry policy of the HTTP requests
+    /// * `endpoint` - Base URL and extra headers
     ///
     /// # Returns
     /// * `Result<Self, Box<dyn Error>>` - Provider instance
-    pub fn new(model: &str) -> Result<Self, Box<dyn Error>> {
+    pub fn new(
+        model: &str,
+        generation: GenerationConfig,
+        retry: RetryPolicy,
+        endpoint: &EndpointConfig,
+    ) -> Result<Self, Box<dyn Error>> {
+        let url = base_url(endpoint, "OLLAMA_BASE_URL", "http://localhost:11434");
         Ok(OllamaProvider {
             model: model.to_string(),
+            generation,
+            retry,
+            url: format!("{}/api/chat", url),
+            headers: endpoint.headers.clone(),
         })
     }
+
+    /// Sends a chat request
+    ///
+    /// # Arguments
+    /// * `messages` - Vector of chat messages containing the conversation history
+    /// * `tools` - Tools exposed to the model, may be empty
+    /// * `schema` - JSON schema the response must match, if any
+    /// * `stream` - Whether the response is streamed as newline-delimited JSON
+    ///
+    /// # Returns
+    /// * `Result<reqwest::Response, Box<dyn Error>>` - The successful HTTP response or error
+    async fn send(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: &[ToolDefinition],
+        schema: Option<&Value>,
+        stream: bool,
+    ) -> Result<reqwest::Response, Box<dyn Error>> {
+        let client = http_client();
+        let messages: Vec<Value> = if tools.is_empty() {
+            plain_messages(messages)
+                .iter()
+                .map(to_request_message)
+                .collect()
+        } else {
+            pair_tool_calls(messages)
+                .iter()
+                .map(to_request_message)
+                .collect()
+        };
+
+        let mut request_body = json!({
+            "model": self.model,
+            "stream": stream,
+            "messages": messages
+        });
+        if let Some(temperature) = self.generation.temperature {
+            request_body["options"]["temperature"] = json!(temperature);
+        }
+        if let Some(max_tokens) = self.generation.max_tokens {
+            request_body["options"]["num_predict"] = json!(max_tokens);
+        }
+        if let Some(schema) = schema {
+            request_body["format"] = schema.clone();
+        }
+        if let Some(top_p) = self.generation.top_p {
+            request_body["options"]["top_p"] = json!(top_p);
+        }
+        if !self.generation.stop.is_empty() {
+            request_body["options"]["stop"] = json!(self.generation.stop);
+        }
+        if let Some(seed) = self.generation.seed {
+            request_body["options"]["seed"] = json!(seed);
+        }
+        if let Some(context_window) = self.generation.context_window {
+            request_body["options"]["num_ctx"] = json!(context_window);
+        }
+        if !tools.is_empty() {
+            let tools: Vec<Value> = tools
+                .iter()
+                .map(|tool| {
+                    json!({
+                        "type": "function",
+                        "function": {
+                            "name": tool.name,
+                            "description": tool.description,
+                            "parameters": tool.parameters
+                        }
+                    })
+                })
+                .collect();
+            request_body["tools"] = json!(tools);
+        }
+
+        let res = self
+            .retry
+            .send("Ollama", || {
+                with_headers(client.post(&self.url), &self.headers).json(&request_body)
+            })
+            .await?;
+        Ok(res)
+    }
 }
 
 #[async_trait]
@@ -36,32 +143,193 @@ impl LlmProvider for OllamaProvider {
     /// * `user_prompt` - User's input message
     ///
     /// # Returns
-    /// * `Result<String, Box<dyn Error>>` - Generated response text or error
-    async fn call_llm_api(&self, messages: Vec<ChatMessage>) -> Result<String, Box<dyn Error>> {
-        let client = Client::new();
+    /// * `Result<LlmResponse, Box<dyn Error>>` - Generated response text and usage, or error
+    async fn call_llm_api(
+        &self,
+        messages: Vec<ChatMessage>,
+    ) -> Result<LlmResponse, Box<dyn Error>> {
+        let res = self.send(messages, &[], None, false).await?;
+        text_response(res).await
+    }
 
-        let request_body = json!({
-            "model": self.model,
-            "stream": false,
-            "messages": messages
-        });
+    fn supports_structured_output(&self) -> bool {
+        true
+    }
 
-        let res = client
-            .post("http://localhost:11400/api/chat")
-            .json(&request_body)
-            .send()
-            .await?;
+    /// Calls Ollama's chat API with the schema as response `format`
+    ///
+    /// # Arguments
+    /// * `messages` - Vector of chat messages containing the conversation history
+    /// * `schema` - JSON schema the response must match
+    ///
+    /// # Returns
+    /// * `Result<LlmResponse, Box<dyn Error>>` - The JSON response and usage, or error
+    async fn call_llm_api_structured(
+        &self,
+        messages: Vec<ChatMessage>,
+        schema: &Value,
+    ) -> Result<LlmResponse, Box<dyn Error>> {
+        let res = self.send(messages, &[], Some(schema), false).await?;
+        text_response(res).await
+    }
 
-        if !res.status().is_success() {
-            let text = res.text().await?;
-            return Err(format!("Ollama API error: {}", text).into());
-        }
+    fn supports_tools(&self) -> bool {
+        true
+    }
 
+    /// Calls Ollama's chat API with tools
+    ///
+    /// Ollama does not assign IDs to tool calls, so a random one is generated for each.
+    ///
+    /// # Arguments
+    /// * `messages` - Vector of chat messages containing the conversation history
+    /// * `tools` - Tools the model may call
+    ///
+    /// # Returns
+    /// * `Result<LlmResponse, Box<dyn Error>>` - The response text and tool calls, or error
+    async fn call_llm_api_with_tools(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: &[ToolDefinition],
+    ) -> Result<LlmResponse, Box<dyn Error>> {
+        let res = self.send(messages, tools, None, false).await?;
         let json_resp: serde_json::Value = res.json().await?;
-        if let Some(content) = json_resp["message"]["content"].as_str() {
-            Ok(content.trim().to_string())
-        } else {
-            Err("No content in Ollama LLM response".into())
+        let message = &json_resp["message"];
+        let content = message["content"].as_str().unwrap_or_default().trim();
+
+        let tool_calls: Vec<ToolCall> = message["tool_calls"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|call| ToolCall {
+                id: uuid::Uuid::new_v4().to_string(),
+                name: call["function"]["name"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+                arguments: call["function"]["arguments"].clone(),
+            })
+            .collect();
+
+        if content.is_empty() && tool_calls.is_empty() {
+            return Err("No content in Ollama LLM response".into());
         }
+        Ok(LlmResponse {
+            content: content.to_string(),
+            tool_calls,
+            usage: usage(&json_resp),
+        })
+    }
+
+    /// Calls Ollama's chat API with newline-delimited JSON streaming
+    ///
+    /// # Arguments
+    /// * `messages` - Vector of chat messages containing the conversation history
+    /// * `tokens` - Channel receiving the partial tokens
+    ///
+    /// # Returns
+    /// * `Result<LlmResponse, Box<dyn Error>>` - The assembled response text and usage, or error
+    async fn call_llm_api_stream(
+        &self,
+        messages: Vec<ChatMessage>,
+        tokens: &TokenSender,
+    ) -> Result<LlmResponse, Box<dyn Error>> {
+        let res = self.send(messages, &[], None, true).await?;
+        let mut content = String::new();
+        let mut usage = None;
+        read_lines(res, |line| {
+            let chunk: serde_json::Value = serde_json::from_str(line)?;
+            if let Some(error) = chunk["error"].as_str() {
+                return Err(format!("Ollama API error: {}", error).into());
+            }
+            if let Some(token) = chunk["message"]["content"].as_str() {
+                content.push_str(token);
+                let _ = tokens.send(token.to_string());
+            }
+            usage = self::usage(&chunk).or(usage);
+            Ok(!chunk["done"].as_bool().unwrap_or(false))
+        })
+        .await?;
+
+        if content.is_empty() {
+            return Err("No content in Ollama LLM response".into());
+        }
+        Ok(LlmResponse::text(content.trim().to_string()).with_usage(usage))
+    }
+}
+
+/// Reads the text and token counts of a chat response
+///
+/// # Arguments
+/// * `res` - The successful HTTP response
+///
+/// # Returns
+/// * `Result<LlmResponse, Box<dyn Error>>` - The response text and usage, or error
+async fn text_response(res: reqwest::Response) -> Result<LlmResponse, Box<dyn Error>> {
+    let json_resp: serde_json::Value = res.json().await?;
+    if let Some(content) = json_resp["message"]["content"].as_str() {
+        Ok(LlmResponse::text(content.trim().to_string()).with_usage(usage(&json_resp)))
+    } else {
+        Err("No content in Ollama LLM response".into())
+    }
+}
+
+/// Reads the token counts of a chat response or of the final chunk of a stream
+///
+/// # Arguments
+/// * `response` - The response or chunk
+///
+/// # Returns
+/// * `Option<TokenUsage>` - The prompt and generated tokens, if reported
+fn usage(response: &Value) -> Option<TokenUsage> {
+    Some(TokenUsage {
+        input_tokens: response["prompt_eval_count"].as_u64()?,
+        output_tokens: response["eval_count"].as_u64().unwrap_or(0),
+    })
+}
+
+/// Converts a chat message into the chat API format
+///
+/// Images are sent in `images`; other parts, which the chat API does not accept, are
+/// described in the content.
+///
+/// # Arguments
+/// * `msg` - The message to convert
+///
+/// # Returns
+/// * `Value` - The message, with its images and tool calls if any
+fn to_request_message(msg: &ChatMessage) -> Value {
+    let mut content = msg.content.clone();
+    let mut images = Vec::new();
+    for part in &msg.parts {
+        match part.is_image().then(|| encode_part(part)).flatten() {
+            Some((_, data)) => images.push(data),
+            None => {
+                if !content.is_empty() {
+                    content.push('\n');
+                }
+                content.push_str(&part.describe());
+            }
+        }
+    }
+    let mut message = json!({ "role": msg.role, "content": content });
+    if !images.is_empty() {
+        message["images"] = json!(images);
+    }
+    if !msg.tool_calls.is_empty() {
+        let tool_calls: Vec<Value> = msg
+            .tool_calls
+            .iter()
+            .map(|call| {
+                json!({
+                    "function": {
+                        "name": call.name,
+                        "arguments": call.arguments
+                    }
+                })
+            })
+            .collect();
+        message["tool_calls"] = json!(tool_calls);
     }
+    message
 }
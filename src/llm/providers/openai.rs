@@ -1,17 +1,33 @@
-use super::LlmProvider;
-use crate::llm::ChatMessage;
+use super::{
+    api_key, base_url, chat_completions_usage, encode_part, pair_tool_calls, plain_messages,
+    read_lines, sse_data, with_headers, LlmProvider, RetryPolicy, TokenSender,
+};
+use crate::config::{EndpointConfig, GenerationConfig};
+use crate::llm::{
+    http_client, ChatMessage, ContentPart, InvalidToolCall, LlmResponse, ToolCall, ToolDefinition,
+};
 use async_trait::async_trait;
-use reqwest::Client;
-use serde_json::json;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
 use std::error::Error;
 
-/// Provider implementation for OpenAI's API
+/// Provider implementation for OpenAI's API, also used by OpenAI-compatible servers
 #[derive(Debug)]
 pub struct OpenAiProvider {
-    /// OpenAI API key loaded from environment
-    api_key: String,
+    /// Name of the provider, used in errors and logs
+    name: &'static str,
+    /// URL of the chat completions endpoint
+    url: String,
+    /// Header carrying the API key and its value, if the API requires a key
+    auth: Option<(String, String)>,
+    /// Extra headers sent with every request
+    headers: BTreeMap<String, String>,
     /// Model identifier to use (e.g. "gpt-4", "gpt-3.5-turbo")
     model: String,
+    /// Generation settings sent with every request
+    generation: GenerationConfig,
+    /// Retry policy of the HTTP requests
+    retry: RetryPolicy,
 }
 
 impl OpenAiProvider {
@@ -19,16 +35,181 @@ impl OpenAiProvider {
     ///
     /// # Arguments
     /// * `model` - The model identifier to use
+    /// * `generation` - Generation settings sent with every request
+    /// * `retry` - Retry policy of the HTTP requests
+    /// * `endpoint` - Base URL, API key variable and extra headers
     ///
     /// # Returns
     /// * `Result<Self, Box<dyn Error>>` - Provider instance or error if API key not found
-    pub fn new(model: &str) -> Result<Self, Box<dyn Error>> {
-        let api_key = std::env::var("OPENAI_API_KEY")
-            .map_err(|_| "OPENAI_API_KEY environment variable not set")?;
-        Ok(OpenAiProvider {
-            api_key,
+    pub fn new(
+        model: &str,
+        generation: GenerationConfig,
+        retry: RetryPolicy,
+        endpoint: &EndpointConfig,
+    ) -> Result<Self, Box<dyn Error>> {
+        let api_key = api_key(endpoint, "OPENAI_API_KEY")?;
+        let url = base_url(endpoint, "OPENAI_BASE_URL", "https://api.openai.com/v1");
+        Ok(Self::with_endpoint(
+            "OpenAI",
+            format!("{}/chat/completions", url),
+            Some(("Authorization".to_string(), format!("Bearer {}", api_key))),
+            endpoint.headers.clone(),
+            model,
+            generation,
+            retry,
+        ))
+    }
+
+    /// Creates a provider for any API implementing OpenAI's chat completions
+    ///
+    /// # Arguments
+    /// * `name` - Name of the provider, used in errors and logs
+    /// * `url` - URL of the chat completions endpoint
+    /// * `auth` - Header carrying the API key and its value, if any
+    /// * `headers` - Extra headers sent with every request
+    /// * `model` - The model identifier to use
+    /// * `generation` - Generation settings sent with every request
+    /// * `retry` - Retry policy of the HTTP requests
+    ///
+    /// # Returns
+    /// * `Self` - Provider instance
+    pub fn with_endpoint(
+        name: &'static str,
+        url: String,
+        auth: Option<(String, String)>,
+        headers: BTreeMap<String, String>,
+        model: &str,
+        generation: GenerationConfig,
+        retry: RetryPolicy,
+    ) -> Self {
+        OpenAiProvider {
+            name,
+            url,
+            auth,
+            headers,
             model: model.to_string(),
-        })
+            generation,
+            retry,
+        }
+    }
+
+    /// Reads the text and usage of a chat completions response
+    ///
+    /// # Arguments
+    /// * `res` - The successful HTTP response
+    ///
+    /// # Returns
+    /// * `Result<LlmResponse, Box<dyn Error>>` - The response text and usage, or error
+    async fn text_response(&self, res: reqwest::Response) -> Result<LlmResponse, Box<dyn Error>> {
+        let json_resp: serde_json::Value = res.json().await?;
+        if let Some(content) = json_resp["choices"][0]["message"]["content"].as_str() {
+            Ok(LlmResponse::text(content.trim().to_string())
+                .with_usage(chat_completions_usage(&json_resp)))
+        } else {
+            Err(format!("No content in {} LLM response", self.name).into())
+        }
+    }
+
+    /// Adds the generation settings to a chat completions request
+    ///
+    /// Reasoning models reject `max_tokens` and a non-default temperature: with a
+    /// `reasoning_effort`, the limit is sent as `max_completion_tokens` and the temperature
+    /// only if set explicitly.
+    ///
+    /// # Arguments
+    /// * `request_body` - The request being built
+    fn add_generation(&self, request_body: &mut Value) {
+        let generation = &self.generation;
+        match (generation.temperature, generation.reasoning_effort) {
+            (Some(temperature), _) => request_body["temperature"] = json!(temperature),
+            (None, None) => request_body["temperature"] = json!(0.7),
+            (None, Some(_)) => {}
+        }
+        if let Some(max_tokens) = generation.max_tokens {
+            let field = match generation.reasoning_effort {
+                Some(_) => "max_completion_tokens",
+                None => "max_tokens",
+            };
+            request_body[field] = json!(max_tokens);
+        }
+        if let Some(top_p) = generation.top_p {
+            request_body["top_p"] = json!(top_p);
+        }
+        if !generation.stop.is_empty() {
+            request_body["stop"] = json!(generation.stop);
+        }
+        if let Some(seed) = generation.seed {
+            request_body["seed"] = json!(seed);
+        }
+        if let Some(reasoning_effort) = generation.reasoning_effort {
+            request_body["reasoning_effort"] = json!(reasoning_effort);
+        }
+    }
+
+    /// Sends a chat completions request
+    ///
+    /// # Arguments
+    /// * `messages` - Vector of chat messages containing the conversation history
+    /// * `tools` - Tools exposed to the model, may be empty
+    /// * `schema` - JSON schema the response must match, if any
+    /// * `stream` - Whether the response is streamed as server-sent events
+    ///
+    /// # Returns
+    /// * `Result<reqwest::Response, Box<dyn Error>>` - The successful HTTP response or error
+    async fn send(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: &[ToolDefinition],
+        schema: Option<&Value>,
+        stream: bool,
+    ) -> Result<reqwest::Response, Box<dyn Error>> {
+        let client = http_client();
+        let messages: Vec<Value> = if tools.is_empty() {
+            plain_messages(messages)
+                .iter()
+                .map(to_request_message)
+                .collect()
+        } else {
+            pair_tool_calls(messages)
+                .iter()
+                .map(to_request_message)
+                .collect()
+        };
+        let mut request_body = json!({
+          "model": self.model,
+          "messages": messages,
+          "stream": stream
+        });
+        if stream {
+            request_body["stream_options"] = json!({ "include_usage": true });
+        }
+        self.add_generation(&mut request_body);
+        if let Some(schema) = schema {
+            request_body["response_format"] = json!({
+                "type": "json_schema",
+                "json_schema": {
+                    "name": "output",
+                    "strict": true,
+                    "schema": strict_schema(schema.clone())
+                }
+            });
+        }
+        if !tools.is_empty() {
+            request_body["tools"] = json!(function_tools(tools));
+            request_body["parallel_tool_calls"] = json!(false);
+        }
+
+        let res = self
+            .retry
+            .send(self.name, || {
+                let mut request = with_headers(client.post(&self.url), &self.headers);
+                if let Some((header, value)) = &self.auth {
+                    request = request.header(header, value);
+                }
+                request.json(&request_body)
+            })
+            .await?;
+        Ok(res)
     }
 }
 
@@ -41,32 +222,305 @@ impl LlmProvider for OpenAiProvider {
     /// * `user_prompt` - User's input message
     ///
     /// # Returns
-    /// * `Result<String, Box<dyn Error>>` - Generated response text or error
-    async fn call_llm_api(&self, messages: Vec<ChatMessage>) -> Result<String, Box<dyn Error>> {
-        let client = Client::new();
-        let request_body = json!({
-          "model": self.model,
-          "messages": messages,
-          "temperature": 0.7
-        });
+    /// * `Result<LlmResponse, Box<dyn Error>>` - Generated response text and usage, or error
+    async fn call_llm_api(
+        &self,
+        messages: Vec<ChatMessage>,
+    ) -> Result<LlmResponse, Box<dyn Error>> {
+        let res = self.send(messages, &[], None, false).await?;
+        self.text_response(res).await
+    }
 
-        let res = client
-            .post("https://api.openai.com/v1/chat/completions")
-            .bearer_auth(&self.api_key)
-            .json(&request_body)
-            .send()
-            .await?;
+    fn supports_structured_output(&self) -> bool {
+        true
+    }
 
-        if !res.status().is_success() {
-            let text = res.text().await?;
-            return Err(format!("OpenAI API error: {}", text).into());
-        }
+    /// Calls OpenAI's chat completions API with a `json_schema` response format
+    ///
+    /// # Arguments
+    /// * `messages` - Vector of chat messages containing the conversation history
+    /// * `schema` - JSON schema the response must match
+    ///
+    /// # Returns
+    /// * `Result<LlmResponse, Box<dyn Error>>` - The JSON response and usage, or error
+    async fn call_llm_api_structured(
+        &self,
+        messages: Vec<ChatMessage>,
+        schema: &Value,
+    ) -> Result<LlmResponse, Box<dyn Error>> {
+        let res = self.send(messages, &[], Some(schema), false).await?;
+        self.text_response(res).await
+    }
 
+    fn supports_tools(&self) -> bool {
+        true
+    }
+
+    /// Calls OpenAI's chat completions API with function tools
+    ///
+    /// # Arguments
+    /// * `messages` - Vector of chat messages containing the conversation history
+    /// * `tools` - Tools the model may call
+    ///
+    /// # Returns
+    /// * `Result<LlmResponse, Box<dyn Error>>` - The response text and tool calls, or error
+    async fn call_llm_api_with_tools(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: &[ToolDefinition],
+    ) -> Result<LlmResponse, Box<dyn Error>> {
+        let res = self.send(messages, tools, None, false).await?;
         let json_resp: serde_json::Value = res.json().await?;
-        if let Some(content) = json_resp["choices"][0]["message"]["content"].as_str() {
-            Ok(content.trim().to_string())
-        } else {
-            Err("No content in OpenAI LLM response".into())
+        let message = &json_resp["choices"][0]["message"];
+        let content = message["content"].as_str().unwrap_or_default().trim();
+        let tool_calls = parse_tool_calls(message)?;
+
+        if content.is_empty() && tool_calls.is_empty() {
+            return Err(format!("No content in {} LLM response", self.name).into());
         }
+        Ok(LlmResponse {
+            content: content.to_string(),
+            tool_calls,
+            usage: chat_completions_usage(&json_resp),
+        })
+    }
+
+    /// Calls OpenAI's chat completions API with server-sent events streaming
+    ///
+    /// # Arguments
+    /// * `messages` - Vector of chat messages containing the conversation history
+    /// * `tokens` - Channel receiving the partial tokens
+    ///
+    /// # Returns
+    /// * `Result<LlmResponse, Box<dyn Error>>` - The assembled response text and usage, or error
+    async fn call_llm_api_stream(
+        &self,
+        messages: Vec<ChatMessage>,
+        tokens: &TokenSender,
+    ) -> Result<LlmResponse, Box<dyn Error>> {
+        let res = self.send(messages, &[], None, true).await?;
+        let mut content = String::new();
+        let mut usage = None;
+        read_lines(res, |line| {
+            let Some(data) = sse_data(line) else {
+                return Ok(true);
+            };
+            if data == "[DONE]" {
+                return Ok(false);
+            }
+            let event: serde_json::Value = serde_json::from_str(data)?;
+            if let Some(token) = event["choices"][0]["delta"]["content"].as_str() {
+                content.push_str(token);
+                let _ = tokens.send(token.to_string());
+            }
+            usage = chat_completions_usage(&event).or(usage);
+            Ok(true)
+        })
+        .await?;
+
+        if content.is_empty() {
+            return Err(format!("No content in {} LLM response", self.name).into());
+        }
+        Ok(LlmResponse::text(content.trim().to_string()).with_usage(usage))
+    }
+}
+
+/// Reads the tool calls of a chat completions response message
+///
+/// # Arguments
+/// * `message` - The `message` of the first choice
+///
+/// # Returns
+/// * `Result<Vec<ToolCall>, Box<dyn Error>>` - The tool calls, or an `InvalidToolCall`
+///   error if their arguments are not JSON
+pub(super) fn parse_tool_calls(message: &Value) -> Result<Vec<ToolCall>, Box<dyn Error>> {
+    let mut tool_calls = Vec::new();
+    for call in message["tool_calls"].as_array().into_iter().flatten() {
+        let name = call["function"]["name"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string();
+        let arguments = call["function"]["arguments"].as_str().unwrap_or("{}");
+        let arguments = serde_json::from_str(arguments).map_err(|e| InvalidToolCall {
+            name: name.clone(),
+            reason: e.to_string(),
+        })?;
+        tool_calls.push(ToolCall {
+            id: call["id"].as_str().unwrap_or_default().to_string(),
+            name,
+            arguments,
+        });
+    }
+    Ok(tool_calls)
+}
+
+/// Adapts a JSON schema to the strict mode of structured outputs
+///
+/// Strict mode requires every object to list all its properties as required and to
+/// forbid additional ones, so both are set on each object of the schema.
+///
+/// # Arguments
+/// * `schema` - The schema of the response
+///
+/// # Returns
+/// * `Value` - The schema accepted in strict mode
+pub(super) fn strict_schema(mut schema: Value) -> Value {
+    if let Some(object) = schema.as_object_mut() {
+        if let Some(properties) = object.get("properties").and_then(Value::as_object) {
+            let required: Vec<Value> = properties.keys().cloned().map(Value::String).collect();
+            object.insert("required".to_string(), Value::Array(required));
+            object.insert("additionalProperties".to_string(), Value::Bool(false));
+        } else if object.get("type").and_then(Value::as_str) == Some("object") {
+            object.insert("additionalProperties".to_string(), Value::Bool(false));
+        }
+        for (key, value) in object.iter_mut() {
+            match key.as_str() {
+                "properties" | "$defs" | "definitions" => {
+                    if let Some(children) = value.as_object_mut() {
+                        for child in children.values_mut() {
+                            *child = strict_schema(child.take());
+                        }
+                    }
+                }
+                "items" | "anyOf" | "allOf" | "oneOf" => *value = strict_schema(value.take()),
+                _ => {}
+            }
+        }
+    } else if let Some(schemas) = schema.as_array_mut() {
+        for child in schemas.iter_mut() {
+            *child = strict_schema(child.take());
+        }
+    }
+    schema
+}
+
+/// Converts tool definitions into chat completions function tools
+///
+/// # Arguments
+/// * `tools` - The tools exposed to the model
+///
+/// # Returns
+/// * `Vec<Value>` - The `tools` of the request
+pub(super) fn function_tools(tools: &[ToolDefinition]) -> Vec<Value> {
+    tools
+        .iter()
+        .map(|tool| {
+            json!({
+                "type": "function",
+                "function": {
+                    "name": tool.name,
+                    "description": tool.description,
+                    "parameters": tool.parameters
+                }
+            })
+        })
+        .collect()
+}
+
+/// Converts a chat message into the chat completions message format
+///
+/// # Arguments
+/// * `msg` - The message to convert
+///
+/// # Returns
+/// * `Value` - The message, with its content parts and tool calls or tool call ID
+pub(super) fn to_request_message(msg: &ChatMessage) -> Value {
+    if msg.role == "tool" {
+        return json!({
+            "role": "tool",
+            "tool_call_id": msg.tool_call_id,
+            "content": msg.text_with_parts()
+        });
+    }
+    let mut message = if msg.parts.is_empty() {
+        json!({ "role": msg.role, "content": msg.content })
+    } else {
+        json!({ "role": msg.role, "content": content_parts(msg) })
+    };
+    if !msg.tool_calls.is_empty() {
+        let tool_calls: Vec<Value> = msg
+            .tool_calls
+            .iter()
+            .map(|call| {
+                json!({
+                    "id": call.id,
+                    "type": "function",
+                    "function": {
+                        "name": call.name,
+                        "arguments": call.arguments.to_string()
+                    }
+                })
+            })
+            .collect();
+        message["tool_calls"] = json!(tool_calls);
+    }
+    message
+}
+
+/// Converts the content and parts of a message into chat completions content parts
+///
+/// Images become `image_url` parts and documents `file` parts, both as data URLs.
+///
+/// # Arguments
+/// * `msg` - The message to convert
+///
+/// # Returns
+/// * `Vec<Value>` - The text, image and file parts
+fn content_parts(msg: &ChatMessage) -> Vec<Value> {
+    let mut parts = Vec::new();
+    if !msg.content.is_empty() {
+        parts.push(json!({ "type": "text", "text": msg.content }));
+    }
+    for part in &msg.parts {
+        let encoded = match part {
+            ContentPart::Text { .. } => None,
+            part => encode_part(part),
+        };
+        parts.push(match encoded {
+            Some((media_type, data)) if part.is_image() => json!({
+                "type": "image_url",
+                "image_url": { "url": format!("data:{};base64,{}", media_type, data) }
+            }),
+            Some((media_type, data)) => json!({
+                "type": "file",
+                "file": {
+                    "filename": part.file_name(),
+                    "file_data": format!("data:{};base64,{}", media_type, data)
+                }
+            }),
+            None => json!({ "type": "text", "text": part.describe() }),
+        });
+    }
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strict_schema_requires_every_property() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "title": { "type": "string" },
+                "sections": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": { "name": { "type": "string" } }
+                    }
+                }
+            },
+            "required": ["title"]
+        });
+
+        let strict = strict_schema(schema);
+
+        assert_eq!(strict["required"], json!(["sections", "title"]));
+        assert_eq!(strict["additionalProperties"], json!(false));
+        let item = &strict["properties"]["sections"]["items"];
+        assert_eq!(item["required"], json!(["name"]));
+        assert_eq!(item["additionalProperties"], json!(false));
     }
 }
@@ -0,0 +1,147 @@
+use super::openai::OpenAiProvider;
+use super::{api_key, base_url, LlmProvider, RetryPolicy, TokenSender};
+use crate::config::{EndpointConfig, GenerationConfig};
+use crate::llm::{ChatMessage, LlmResponse, ToolDefinition};
+use async_trait::async_trait;
+use serde_json::Value;
+use std::error::Error;
+
+/// Variable holding the API key when `api_key_env` is not set; the key is optional
+const DEFAULT_API_KEY_ENV: &str = "OPENAI_COMPATIBLE_API_KEY";
+
+/// Provider for any server implementing OpenAI's chat completions API
+/// (vLLM, LiteLLM, llama.cpp...)
+#[derive(Debug)]
+pub struct OpenAiCompatibleProvider {
+    /// OpenAI provider pointed at the configured endpoint
+    inner: OpenAiProvider,
+}
+
+impl OpenAiCompatibleProvider {
+    /// Creates a new OpenAI-compatible provider instance
+    ///
+    /// # Arguments
+    /// * `model` - The model identifier to use
+    /// * `generation` - Generation settings sent with every request
+    /// * `retry` - Retry policy of the HTTP requests
+    /// * `endpoint` - Base URL, API key variable, auth header and extra headers
+    ///
+    /// # Returns
+    /// * `Result<Self, Box<dyn Error>>` - Provider instance, or an error if no base URL is
+    ///   configured or the configured API key variable is not set
+    pub fn new(
+        model: &str,
+        generation: GenerationConfig,
+        retry: RetryPolicy,
+        endpoint: &EndpointConfig,
+    ) -> Result<Self, Box<dyn Error>> {
+        let url = base_url(endpoint, "OPENAI_COMPATIBLE_BASE_URL", "");
+        if url.is_empty() {
+            return Err("openai_compatible provider requires llm_endpoint.base_url \
+                        or the OPENAI_COMPATIBLE_BASE_URL environment variable"
+                .into());
+        }
+
+        let api_key = match endpoint.api_key_env {
+            Some(_) => Some(api_key(endpoint, DEFAULT_API_KEY_ENV)?),
+            None => std::env::var(DEFAULT_API_KEY_ENV).ok(),
+        };
+        let auth = api_key.map(|key| match endpoint.auth_header.as_deref() {
+            Some(header) if !header.eq_ignore_ascii_case("authorization") => {
+                (header.to_string(), key)
+            }
+            _ => ("Authorization".to_string(), format!("Bearer {}", key)),
+        });
+
+        Ok(OpenAiCompatibleProvider {
+            inner: OpenAiProvider::with_endpoint(
+                "OpenAI-compatible",
+                format!("{}/chat/completions", url),
+                auth,
+                endpoint.headers.clone(),
+                model,
+                generation,
+                retry,
+            ),
+        })
+    }
+}
+
+#[async_trait]
+impl LlmProvider for OpenAiCompatibleProvider {
+    /// Calls the chat completions API of the server
+    ///
+    /// # Arguments
+    /// * `messages` - Vector of chat messages containing the conversation history
+    ///
+    /// # Returns
+    /// * `Result<LlmResponse, Box<dyn Error>>` - Generated response text and usage, or error
+    async fn call_llm_api(
+        &self,
+        messages: Vec<ChatMessage>,
+    ) -> Result<LlmResponse, Box<dyn Error>> {
+        self.inner.call_llm_api(messages).await
+    }
+
+    /// Calls the chat completions API of the server with server-sent events streaming
+    ///
+    /// # Arguments
+    /// * `messages` - Vector of chat messages containing the conversation history
+    /// * `tokens` - Channel receiving the partial tokens
+    ///
+    /// # Returns
+    /// * `Result<LlmResponse, Box<dyn Error>>` - The assembled response text and usage, or error
+    async fn call_llm_api_stream(
+        &self,
+        messages: Vec<ChatMessage>,
+        tokens: &TokenSender,
+    ) -> Result<LlmResponse, Box<dyn Error>> {
+        self.inner.call_llm_api_stream(messages, tokens).await
+    }
+
+    fn supports_tools(&self) -> bool {
+        true
+    }
+
+    fn supports_structured_output(&self) -> bool {
+        true
+    }
+
+    /// Calls the chat completions API of the server with a `json_schema` response format
+    ///
+    /// Servers without structured output reject the request; the client then falls back
+    /// to describing the schema in the prompt.
+    ///
+    /// # Arguments
+    /// * `messages` - Vector of chat messages containing the conversation history
+    /// * `schema` - JSON schema the response must match
+    ///
+    /// # Returns
+    /// * `Result<LlmResponse, Box<dyn Error>>` - The JSON response and usage, or error
+    async fn call_llm_api_structured(
+        &self,
+        messages: Vec<ChatMessage>,
+        schema: &Value,
+    ) -> Result<LlmResponse, Box<dyn Error>> {
+        self.inner.call_llm_api_structured(messages, schema).await
+    }
+
+    /// Calls the chat completions API of the server with function tools
+    ///
+    /// Servers without tool support reject the request; the client then falls back to
+    /// the text protocol.
+    ///
+    /// # Arguments
+    /// * `messages` - Vector of chat messages containing the conversation history
+    /// * `tools` - Tools the model may call
+    ///
+    /// # Returns
+    /// * `Result<LlmResponse, Box<dyn Error>>` - The response text and tool calls, or error
+    async fn call_llm_api_with_tools(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: &[ToolDefinition],
+    ) -> Result<LlmResponse, Box<dyn Error>> {
+        self.inner.call_llm_api_with_tools(messages, tools).await
+    }
+}
@@ -0,0 +1,273 @@
+use crate::config::RetryConfig;
+use reqwest::header::HeaderMap;
+use reqwest::{RequestBuilder, Response, StatusCode};
+use std::collections::hash_map::RandomState;
+use std::error::Error;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+use tracing::warn;
+
+/// Default number of attempts of a request, the first one included
+const DEFAULT_MAX_ATTEMPTS: u32 = 4;
+/// Default delay before the first retry
+const DEFAULT_INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+/// Default upper bound of the delay between two attempts
+const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Error of a request to an LLM provider
+#[derive(Debug, thiserror::Error)]
+pub enum LlmError {
+    /// The provider answered with a non-success status
+    #[error("{provider} API error ({status}): {body}")]
+    Http {
+        /// Name of the provider
+        provider: &'static str,
+        /// HTTP status of the response
+        status: StatusCode,
+        /// Body of the response
+        body: String,
+        /// Delay requested by the provider before retrying, if any
+        retry_after: Option<Duration>,
+    },
+    /// The request could not be sent or its response not received
+    #[error("{provider} request failed: {source}")]
+    Request {
+        /// Name of the provider
+        provider: &'static str,
+        /// Underlying HTTP client error
+        #[source]
+        source: reqwest::Error,
+    },
+}
+
+impl LlmError {
+    /// Whether retrying the request may succeed
+    ///
+    /// Rate limits, server errors, timeouts and connection errors are retryable; other
+    /// client errors (invalid API key, unknown model, malformed request...) are fatal.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            LlmError::Http { status, .. } => {
+                status.is_server_error() || matches!(status.as_u16(), 408 | 409 | 425 | 429 | 529)
+            }
+            LlmError::Request { source, .. } => {
+                source.is_connect() || source.is_timeout() || source.is_request()
+            }
+        }
+    }
+}
+
+/// Whether an LLM call failed with an error that retrying can never fix
+///
+/// # Arguments
+/// * `error` - Error returned by an LLM call
+///
+/// # Returns
+/// * `bool` - True for a non-retryable provider error
+pub fn is_fatal_error(error: &(dyn Error + 'static)) -> bool {
+    error
+        .downcast_ref::<LlmError>()
+        .is_some_and(|error| !error.is_retryable())
+}
+
+/// HTTP retry policy of the LLM providers: exponential backoff with full jitter
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Number of attempts of a request, the first one included
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled on every attempt
+    pub initial_backoff: Duration,
+    /// Upper bound of the delay between two attempts
+    pub max_backoff: Duration,
+    /// Timeout of each attempt, None to wait indefinitely
+    pub timeout: Option<Duration>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            initial_backoff: DEFAULT_INITIAL_BACKOFF,
+            max_backoff: DEFAULT_MAX_BACKOFF,
+            timeout: None,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Creates the policy described by `parameters.llm_retry`, unset values using the defaults
+    ///
+    /// # Arguments
+    /// * `config` - Retry settings of the task
+    ///
+    /// # Returns
+    /// * `RetryPolicy` - The resolved policy
+    pub fn from_config(config: &RetryConfig) -> Self {
+        let parse = |duration: &Option<String>| {
+            duration
+                .as_deref()
+                .and_then(|duration| humantime::parse_duration(duration).ok())
+        };
+        RetryPolicy {
+            max_attempts: config.max_attempts.unwrap_or(DEFAULT_MAX_ATTEMPTS).max(1),
+            initial_backoff: parse(&config.initial_backoff).unwrap_or(DEFAULT_INITIAL_BACKOFF),
+            max_backoff: parse(&config.max_backoff).unwrap_or(DEFAULT_MAX_BACKOFF),
+            timeout: parse(&config.timeout),
+        }
+    }
+
+    /// Sends a request, retrying it while it fails with a retryable error
+    ///
+    /// # Arguments
+    /// * `provider` - Name of the provider, used in errors and logs
+    /// * `request` - Builds the request, called once per attempt
+    ///
+    /// # Returns
+    /// * `Result<Response, LlmError>` - The successful response, or the last error
+    pub async fn send<F>(&self, provider: &'static str, request: F) -> Result<Response, LlmError>
+    where
+        F: Fn() -> RequestBuilder,
+    {
+        let mut attempt = 1;
+        loop {
+            let mut builder = request();
+            if let Some(timeout) = self.timeout {
+                builder = builder.timeout(timeout);
+            }
+            let error = match builder.send().await {
+                Ok(res) if res.status().is_success() => return Ok(res),
+                Ok(res) => {
+                    let status = res.status();
+                    let retry_after = retry_after(status, res.headers());
+                    let body = res.text().await.unwrap_or_default();
+                    LlmError::Http {
+                        provider,
+                        status,
+                        body,
+                        retry_after,
+                    }
+                }
+                Err(source) => LlmError::Request { provider, source },
+            };
+
+            if attempt >= self.max_attempts || !error.is_retryable() {
+                return Err(error);
+            }
+            let delay = match &error {
+                LlmError::Http {
+                    retry_after: Some(retry_after),
+                    ..
+                } => (*retry_after).min(self.max_backoff),
+                _ => self.backoff(attempt),
+            };
+            warn!(
+                "{} (attempt {}/{}), retrying in {:?}",
+                error, attempt, self.max_attempts, delay
+            );
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// Returns the delay before the retry following the given attempt
+    ///
+    /// # Arguments
+    /// * `attempt` - Number of the failed attempt, starting at 1
+    ///
+    /// # Returns
+    /// * `Duration` - A random delay between zero and the exponential backoff
+    fn backoff(&self, attempt: u32) -> Duration {
+        let exponential = self
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt - 1))
+            .min(self.max_backoff);
+        exponential.mul_f64(jitter())
+    }
+}
+
+/// Returns the delay requested by the provider before retrying, if any
+///
+/// Reads `retry-after-ms` and `retry-after` first, then the reset delay of an
+/// exhausted `x-ratelimit-*` budget. Invalid values are ignored; `send` bounds the
+/// delay by `max_backoff`.
+///
+/// # Arguments
+/// * `status` - Status of the failed response
+/// * `headers` - Headers of the failed response
+///
+/// # Returns
+/// * `Option<Duration>` - The delay, or None to use the backoff
+fn retry_after(status: StatusCode, headers: &HeaderMap) -> Option<Duration> {
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+
+    if let Some(millis) = header("retry-after-ms").and_then(seconds) {
+        return Duration::try_from_secs_f64(millis / 1000.0).ok();
+    }
+    if let Some(secs) = header("retry-after").and_then(seconds) {
+        return Duration::try_from_secs_f64(secs).ok();
+    }
+    if status != StatusCode::TOO_MANY_REQUESTS {
+        return None;
+    }
+    ["requests", "tokens"]
+        .iter()
+        .filter(|budget| header(&format!("x-ratelimit-remaining-{}", budget)) == Some("0"))
+        .filter_map(|budget| header(&format!("x-ratelimit-reset-{}", budget)))
+        .filter_map(|reset| humantime::parse_duration(reset).ok())
+        .max()
+}
+
+/// Parses a delay header, rejecting negative, NaN and infinite values
+///
+/// # Arguments
+/// * `value` - Value of the header
+///
+/// # Returns
+/// * `Option<f64>` - The delay, None if it is not a finite non-negative number
+fn seconds(value: &str) -> Option<f64> {
+    value
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|delay| delay.is_finite() && *delay >= 0.0)
+}
+
+/// Returns a random factor between 0 and 1
+fn jitter() -> f64 {
+    let random = RandomState::new().build_hasher().finish();
+    (random >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn headers(name: &'static str, value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(name, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn retry_after_reads_seconds_and_milliseconds() {
+        let status = StatusCode::TOO_MANY_REQUESTS;
+        assert_eq!(
+            retry_after(status, &headers("retry-after", "2")),
+            Some(Duration::from_secs(2))
+        );
+        assert_eq!(
+            retry_after(status, &headers("retry-after-ms", "1500")),
+            Some(Duration::from_millis(1500))
+        );
+    }
+
+    #[test]
+    fn retry_after_ignores_invalid_values() {
+        let status = StatusCode::TOO_MANY_REQUESTS;
+        for value in ["-1", "nan", "inf", "-inf", "1e400", "soon"] {
+            assert_eq!(retry_after(status, &headers("retry-after", value)), None);
+            assert_eq!(retry_after(status, &headers("retry-after-ms", value)), None);
+        }
+    }
+}
@@ -0,0 +1,65 @@
+//! Local HTTP server standing in for a provider API in tests
+
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::{HeaderMap, Uri};
+use axum::{Json, Router};
+use serde_json::Value;
+use std::sync::{Arc, Mutex};
+
+/// A request received by the stub server
+#[derive(Debug, Clone)]
+pub struct Request {
+    /// Path and query of the request
+    pub uri: String,
+    /// Headers of the request
+    pub headers: HeaderMap,
+    /// JSON body of the request
+    pub body: Value,
+}
+
+impl Request {
+    /// Returns the value of a header, or an empty string if missing
+    pub fn header(&self, name: &str) -> &str {
+        self.headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+    }
+}
+
+/// Requests received by a stub server
+pub type Requests = Arc<Mutex<Vec<Request>>>;
+
+/// Starts a server answering every request with the same JSON document
+///
+/// # Arguments
+/// * `response` - Body of the responses
+///
+/// # Returns
+/// * `(String, Requests)` - The base URL of the server and the requests it received
+pub async fn serve(response: Value) -> (String, Requests) {
+    let requests = Requests::default();
+    let app = Router::new()
+        .fallback(answer)
+        .with_state((response, requests.clone()));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    (url, requests)
+}
+
+/// Records a request and answers it
+async fn answer(
+    State((response, requests)): State<(Value, Requests)>,
+    uri: Uri,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Json<Value> {
+    requests.lock().unwrap().push(Request {
+        uri: uri.to_string(),
+        headers,
+        body: serde_json::from_slice(&body).unwrap_or(Value::Null),
+    });
+    Json(response)
+}
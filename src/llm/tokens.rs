@@ -0,0 +1,372 @@
+use crate::llm::{ChatMessage, ContentPart};
+use std::fmt;
+use tiktoken_rs::CoreBPE;
+use tracing::debug;
+
+/// Tokens added by the chat format around every message (role, separators)
+const MESSAGE_OVERHEAD: usize = 4;
+
+/// Estimated tokens of an image or document part, which providers bill by size or pages
+const ATTACHMENT_TOKENS: usize = 1_000;
+
+/// Tokens kept for the response when the generation settings set no `max_tokens`
+const DEFAULT_RESPONSE_RESERVE: usize = 4096;
+
+/// Counts the tokens of chat messages for a given model
+pub enum TokenCounter {
+    /// Exact count with the byte-pair encoding of an OpenAI-family model
+    Bpe(CoreBPE),
+    /// Estimate from the number of characters, calibrated per model family
+    Estimate {
+        /// Average number of characters per token
+        chars_per_token: f64,
+    },
+}
+
+impl fmt::Debug for TokenCounter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenCounter::Bpe(_) => write!(f, "TokenCounter::Bpe"),
+            TokenCounter::Estimate { chars_per_token } => {
+                write!(f, "TokenCounter::Estimate({})", chars_per_token)
+            }
+        }
+    }
+}
+
+impl TokenCounter {
+    /// Creates the token counter of a model
+    ///
+    /// Models known to tiktoken are counted exactly, whatever their provider (e.g. an
+    /// OpenAI model served by a compatible endpoint); others are estimated.
+    ///
+    /// # Arguments
+    /// * `provider` - Name of the LLM provider
+    /// * `model` - Name of the model
+    ///
+    /// # Returns
+    /// * `TokenCounter` - The counter for this model
+    pub fn for_model(provider: &str, model: &str) -> Self {
+        if let Ok(bpe) = tiktoken_rs::get_bpe_from_model(model) {
+            return TokenCounter::Bpe(bpe);
+        }
+        let chars_per_token = match provider {
+            "anthropic" => 3.5,
+            "deepseek" => 3.3,
+            _ => 3.8,
+        };
+        TokenCounter::Estimate { chars_per_token }
+    }
+
+    /// Counts the tokens of a text
+    ///
+    /// # Arguments
+    /// * `text` - The text to count
+    ///
+    /// # Returns
+    /// * `usize` - Number of tokens
+    pub fn count(&self, text: &str) -> usize {
+        match self {
+            TokenCounter::Bpe(bpe) => bpe.encode_with_special_tokens(text).len(),
+            TokenCounter::Estimate { chars_per_token } => {
+                (text.chars().count() as f64 / chars_per_token).ceil() as usize
+            }
+        }
+    }
+
+    /// Counts the tokens of a message, including its parts, tool calls and format overhead
+    ///
+    /// # Arguments
+    /// * `message` - The message to count
+    ///
+    /// # Returns
+    /// * `usize` - Number of tokens
+    pub fn count_message(&self, message: &ChatMessage) -> usize {
+        let tool_calls: usize = message
+            .tool_calls
+            .iter()
+            .map(|call| self.count(&call.name) + self.count(&call.arguments.to_string()))
+            .sum();
+        let parts: usize = message
+            .parts
+            .iter()
+            .map(|part| match part {
+                ContentPart::Text { text } => self.count(text),
+                _ => ATTACHMENT_TOKENS,
+            })
+            .sum();
+        MESSAGE_OVERHEAD + self.count(&message.content) + parts + tool_calls
+    }
+
+    /// Counts the tokens of a conversation
+    ///
+    /// # Arguments
+    /// * `messages` - The messages to count
+    ///
+    /// # Returns
+    /// * `usize` - Number of tokens
+    pub fn count_messages(&self, messages: &[ChatMessage]) -> usize {
+        messages.iter().map(|msg| self.count_message(msg)).sum()
+    }
+
+    /// Truncates a text to at most the given number of tokens
+    ///
+    /// # Arguments
+    /// * `text` - The text to truncate
+    /// * `max_tokens` - Number of tokens to keep
+    ///
+    /// # Returns
+    /// * `String` - The beginning of the text
+    pub fn truncate(&self, text: &str, max_tokens: usize) -> String {
+        match self {
+            TokenCounter::Bpe(bpe) => {
+                let tokens = bpe.encode_with_special_tokens(text);
+                if tokens.len() <= max_tokens {
+                    return text.to_string();
+                }
+                bpe.decode(tokens[..max_tokens].to_vec())
+                    .unwrap_or_else(|_| text.chars().take(max_tokens).collect())
+            }
+            TokenCounter::Estimate { chars_per_token } => {
+                let max_chars = (max_tokens as f64 * chars_per_token) as usize;
+                text.chars().take(max_chars).collect()
+            }
+        }
+    }
+}
+
+/// Returns the context window of a model, in tokens
+///
+/// Models unknown to Kheish get the window of the current models of their provider;
+/// `generation.context_window` overrides it.
+///
+/// # Arguments
+/// * `provider` - Name of the LLM provider
+/// * `model` - Name of the model (the deployment name with `azure_openai`)
+///
+/// # Returns
+/// * `usize` - Maximum number of tokens of a request and its response
+pub fn context_window(provider: &str, model: &str) -> usize {
+    match provider {
+        "anthropic" => 200_000,
+        "deepseek" => 64_000,
+        "ollama" => 8_192,
+        "gemini" => gemini_context_window(model),
+        "mistral" => mistral_context_window(model),
+        "mock" => 128_000,
+        // Deployments are named freely, only names of OpenAI models tell their window
+        "azure_openai"
+            if !["gpt-", "o1", "o3", "o4"]
+                .iter()
+                .any(|prefix| model.starts_with(prefix)) =>
+        {
+            128_000
+        }
+        _ => openai_context_window(model),
+    }
+}
+
+/// Returns the context window of an OpenAI model, in tokens
+///
+/// # Arguments
+/// * `model` - Name of the model
+///
+/// # Returns
+/// * `usize` - Maximum number of tokens of a request and its response
+fn openai_context_window(model: &str) -> usize {
+    if model.starts_with("gpt-4.1") {
+        1_047_576
+    } else if ["o1", "o3", "o4"]
+        .iter()
+        .any(|prefix| model.starts_with(prefix))
+    {
+        200_000
+    } else {
+        tiktoken_rs::model::get_context_size(model)
+    }
+}
+
+/// Returns the context window of a Gemini model, in tokens
+///
+/// # Arguments
+/// * `model` - Name of the model
+///
+/// # Returns
+/// * `usize` - Maximum number of tokens of a request and its response
+fn gemini_context_window(model: &str) -> usize {
+    if model.starts_with("gemini-1.0") || model == "gemini-pro" {
+        32_760
+    } else if model.starts_with("gemini-1.5-pro") {
+        2_097_152
+    } else {
+        1_048_576
+    }
+}
+
+/// Returns the context window of a Mistral model, in tokens
+///
+/// # Arguments
+/// * `model` - Name of the model
+///
+/// # Returns
+/// * `usize` - Maximum number of tokens of a request and its response
+fn mistral_context_window(model: &str) -> usize {
+    if model.starts_with("codestral") {
+        256_000
+    } else if model.starts_with("open-mixtral-8x22b") {
+        64_000
+    } else if ["open-mistral-7b", "open-mixtral-8x7b", "mistral-tiny"]
+        .iter()
+        .any(|prefix| model.starts_with(prefix))
+    {
+        32_000
+    } else {
+        128_000
+    }
+}
+
+/// Returns the number of tokens available for the messages of a request
+///
+/// # Arguments
+/// * `context_window` - Context window of the model
+/// * `max_tokens` - Tokens reserved for the response, if set
+///
+/// # Returns
+/// * `usize` - The context window minus the response reserve
+pub fn input_budget(context_window: usize, max_tokens: Option<u32>) -> usize {
+    let reserve = max_tokens
+        .map(|max_tokens| max_tokens as usize)
+        .unwrap_or(DEFAULT_RESPONSE_RESERVE.min(context_window / 4));
+    context_window.saturating_sub(reserve)
+}
+
+/// Evicts the oldest messages until the conversation fits in the token budget
+///
+/// System messages and the latest user turn (the last user message and everything
+/// after it) are never evicted. If they alone exceed the budget, the latest user
+/// message is truncated.
+///
+/// # Arguments
+/// * `messages` - The conversation, modified in place
+/// * `counter` - Token counter of the model
+/// * `budget` - Maximum number of tokens of the messages
+///
+/// # Returns
+/// * `bool` - True if messages were evicted or truncated, false otherwise
+pub fn fit_to_context(
+    messages: &mut Vec<ChatMessage>,
+    counter: &TokenCounter,
+    budget: usize,
+) -> bool {
+    let mut total = counter.count_messages(messages);
+    if total <= budget {
+        return false;
+    }
+
+    let latest_user_turn = messages
+        .iter()
+        .rposition(|msg| msg.role == "user")
+        .unwrap_or(messages.len());
+    let mut evicted = 0;
+    let mut kept = Vec::with_capacity(messages.len());
+    for (index, msg) in messages.drain(..).enumerate() {
+        let evictable = msg.role != "system" && index < latest_user_turn;
+        if total > budget && evictable {
+            total -= counter.count_message(&msg);
+            evicted += 1;
+        } else {
+            kept.push(msg);
+        }
+    }
+    *messages = kept;
+    debug!(
+        "Evicted {} messages to fit the context window ({} tokens left, budget {})",
+        evicted, total, budget
+    );
+
+    if total > budget {
+        if let Some(msg) = messages.iter_mut().rev().find(|msg| msg.role == "user") {
+            let excess = total - budget;
+            let keep = counter.count(&msg.content).saturating_sub(excess);
+            msg.content = counter.truncate(&msg.content, keep);
+            debug!("Truncated the latest user message to fit the context window");
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One token per character, plus the overhead of every message
+    const COUNTER: TokenCounter = TokenCounter::Estimate {
+        chars_per_token: 1.0,
+    };
+
+    /// System (14 tokens), user, assistant, latest user and assistant (24, 24, 24, 14 tokens)
+    fn conversation() -> Vec<ChatMessage> {
+        vec![
+            ChatMessage::new("system", &"s".repeat(10)),
+            ChatMessage::new("user", &"a".repeat(20)),
+            ChatMessage::new("assistant", &"b".repeat(20)),
+            ChatMessage::new("user", &"c".repeat(20)),
+            ChatMessage::new("assistant", &"d".repeat(10)),
+        ]
+    }
+
+    fn roles(messages: &[ChatMessage]) -> Vec<&str> {
+        messages.iter().map(|msg| msg.role.as_str()).collect()
+    }
+
+    #[test]
+    fn fit_leaves_conversations_within_the_budget() {
+        let mut messages = conversation();
+        assert!(!fit_to_context(&mut messages, &COUNTER, 100));
+        assert_eq!(messages.len(), 5);
+    }
+
+    #[test]
+    fn fit_evicts_the_oldest_messages_first() {
+        let mut messages = conversation();
+        assert!(fit_to_context(&mut messages, &COUNTER, 80));
+        assert_eq!(
+            roles(&messages),
+            ["system", "assistant", "user", "assistant"]
+        );
+        assert_eq!(messages[1].content, "b".repeat(20));
+    }
+
+    #[test]
+    fn fit_never_evicts_system_messages_nor_the_latest_user_turn() {
+        let mut messages = conversation();
+        assert!(fit_to_context(&mut messages, &COUNTER, 52));
+        assert_eq!(roles(&messages), ["system", "user", "assistant"]);
+        assert_eq!(messages[0].content, "s".repeat(10));
+        assert_eq!(messages[1].content, "c".repeat(20));
+        assert_eq!(messages[2].content, "d".repeat(10));
+    }
+
+    #[test]
+    fn fit_truncates_the_latest_user_message_when_nothing_else_can_go() {
+        let mut messages = conversation();
+        assert!(fit_to_context(&mut messages, &COUNTER, 40));
+        assert_eq!(roles(&messages), ["system", "user", "assistant"]);
+        assert_eq!(messages[0].content, "s".repeat(10));
+        assert_eq!(messages[1].content, "c".repeat(8));
+        assert_eq!(messages[2].content, "d".repeat(10));
+        assert_eq!(COUNTER.count_messages(&messages), 40);
+    }
+
+    #[test]
+    fn context_window_knows_provider_models() {
+        assert_eq!(context_window("azure_openai", "prod-chat"), 128_000);
+        assert_eq!(context_window("azure_openai", "gpt-4o"), 128_000);
+        assert_eq!(context_window("azure_openai", "gpt-4.1-mini"), 1_047_576);
+        assert_eq!(context_window("gemini", "gemini-2.5-flash"), 1_048_576);
+        assert_eq!(context_window("gemini", "gemini-1.0-pro"), 32_760);
+        assert_eq!(context_window("mistral", "mistral-large-latest"), 128_000);
+        assert_eq!(context_window("mistral", "open-mistral-7b"), 32_000);
+        assert_eq!(context_window("openai", "gpt-4o-mini"), 128_000);
+    }
+}
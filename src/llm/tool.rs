@@ -0,0 +1,96 @@
+use crate::llm::{ChatMessage, TokenUsage};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Definition of a tool the LLM can call through the provider's native tool-calling API
+#[derive(Debug, Clone)]
+pub struct ToolDefinition {
+    /// Name of the tool, `<module>_<action>` for module actions
+    pub name: String,
+    /// Description shown to the model
+    pub description: String,
+    /// JSON schema of the tool arguments
+    pub parameters: Value,
+}
+
+/// A tool call requested by the LLM
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolCall {
+    /// Provider-assigned ID, echoed back with the tool result
+    pub id: String,
+    /// Name of the called tool
+    pub name: String,
+    /// Arguments of the call, as a JSON object
+    pub arguments: Value,
+}
+
+impl ToolCall {
+    /// Renders the call as text, for providers or models without tool support
+    ///
+    /// # Returns
+    /// * `String` - A sentence naming the tool and its JSON arguments
+    pub fn to_text(&self) -> String {
+        format!(
+            "Called tool '{}' with arguments {}",
+            self.name, self.arguments
+        )
+    }
+}
+
+/// A tool call whose arguments are not a JSON document, answered with a format reminder
+#[derive(Debug, thiserror::Error)]
+#[error("invalid arguments for tool '{name}': {reason}")]
+pub struct InvalidToolCall {
+    /// Name of the called tool
+    pub name: String,
+    /// Why the arguments could not be read
+    pub reason: String,
+}
+
+/// Response of an LLM call that may contain tool calls
+#[derive(Debug, Clone)]
+pub struct LlmResponse {
+    /// Text of the response, possibly empty when tools are called
+    pub content: String,
+    /// Tools called by the model, in order
+    pub tool_calls: Vec<ToolCall>,
+    /// Tokens consumed by the call, if reported by the provider
+    pub usage: Option<TokenUsage>,
+}
+
+impl LlmResponse {
+    /// Creates a text-only response
+    ///
+    /// # Arguments
+    /// * `content` - Text of the response
+    ///
+    /// # Returns
+    /// * `LlmResponse` - Response without tool calls
+    pub fn text(content: String) -> Self {
+        LlmResponse {
+            content,
+            tool_calls: Vec::new(),
+            usage: None,
+        }
+    }
+
+    /// Sets the tokens consumed by the call
+    ///
+    /// # Arguments
+    /// * `usage` - Token usage reported by the provider, if any
+    ///
+    /// # Returns
+    /// * `LlmResponse` - The response with its usage
+    pub fn with_usage(mut self, usage: Option<TokenUsage>) -> Self {
+        self.usage = usage;
+        self
+    }
+
+    /// Converts the response into the assistant message recorded in the conversation
+    ///
+    /// # Returns
+    /// * `ChatMessage` - Assistant message with the text and tool calls of the response
+    pub fn to_message(&self) -> ChatMessage {
+        ChatMessage::assistant_with_tool_calls(self.content.trim(), self.tool_calls.clone())
+    }
+}
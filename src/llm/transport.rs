@@ -0,0 +1,182 @@
+use crate::config::HttpConfig;
+use once_cell::sync::Lazy;
+use reqwest::{Certificate, Client, NoProxy, Proxy};
+use std::error::Error;
+use std::sync::RwLock;
+use std::time::Duration;
+use tracing::warn;
+
+/// User agent of the requests when neither `http.user_agent` nor `KHEISH_USER_AGENT` is set
+const DEFAULT_USER_AGENT: &str = concat!("kheish/", env!("CARGO_PKG_VERSION"));
+
+/// Client shared by the whole process so that connections are pooled, with the settings it
+/// was built from
+static TRANSPORT: Lazy<RwLock<Option<(HttpConfig, Client)>>> = Lazy::new(|| RwLock::new(None));
+
+/// Builds the shared client from the transport settings of the process
+///
+/// The transport is process-global: it is configured once at startup, from the task given
+/// on the command line or from the environment alone, and serves every task of the process,
+/// including the ones created later through the API.
+///
+/// # Arguments
+/// * `config` - The `parameters.http` settings of the startup task
+///
+/// # Returns
+/// * `Result<(), Box<dyn Error>>` - Ok, or an error if a setting is invalid
+pub fn configure_transport(config: &HttpConfig) -> Result<(), Box<dyn Error>> {
+    let client = build_http_client(config)?;
+    *TRANSPORT.write().unwrap() = Some((config.clone(), client));
+    Ok(())
+}
+
+/// Whether the shared client honours the transport settings of a task
+///
+/// # Arguments
+/// * `config` - The `parameters.http` settings of the task
+///
+/// # Returns
+/// * `bool` - True if the settings are unset or the ones the client was built from
+pub fn transport_applies(config: &HttpConfig) -> bool {
+    *config == HttpConfig::default()
+        || TRANSPORT
+            .read()
+            .unwrap()
+            .as_ref()
+            .is_some_and(|(configured, _)| configured == config)
+}
+
+/// Returns the HTTP client shared by the providers, embedders, modules and output sinks
+///
+/// # Returns
+/// * `Client` - A handle on the shared client and its connection pool
+pub fn http_client() -> Client {
+    if let Some((_, client)) = TRANSPORT.read().unwrap().as_ref() {
+        return client.clone();
+    }
+    let mut transport = TRANSPORT.write().unwrap();
+    let (_, client) = transport.get_or_insert_with(|| {
+        let client = build_http_client(&HttpConfig::default()).unwrap_or_else(|e| {
+            warn!("Invalid HTTP transport settings, using the defaults: {}", e);
+            Client::new()
+        });
+        (HttpConfig::default(), client)
+    });
+    client.clone()
+}
+
+/// Builds a client from the transport settings, unset values read from the environment
+///
+/// # Arguments
+/// * `config` - The transport settings
+///
+/// # Returns
+/// * `Result<Client, Box<dyn Error>>` - The client, or an error naming the invalid setting
+pub fn build_http_client(config: &HttpConfig) -> Result<Client, Box<dyn Error>> {
+    let user_agent = setting(&config.user_agent, "KHEISH_USER_AGENT")
+        .unwrap_or_else(|| DEFAULT_USER_AGENT.to_string());
+    let mut builder = Client::builder().user_agent(user_agent);
+
+    if let Some(timeout) = duration(
+        &config.connect_timeout,
+        "connect_timeout",
+        "KHEISH_HTTP_CONNECT_TIMEOUT",
+    )? {
+        builder = builder.connect_timeout(timeout);
+    }
+    if let Some(timeout) = duration(
+        &config.read_timeout,
+        "read_timeout",
+        "KHEISH_HTTP_READ_TIMEOUT",
+    )? {
+        builder = builder.read_timeout(timeout);
+    }
+    if let Some(timeout) = duration(
+        &config.pool_idle_timeout,
+        "pool_idle_timeout",
+        "KHEISH_HTTP_POOL_IDLE_TIMEOUT",
+    )? {
+        builder = builder.pool_idle_timeout(timeout);
+    }
+    let max_idle = match config.pool_max_idle_per_host {
+        Some(max_idle) => Some(max_idle),
+        None => std::env::var("KHEISH_HTTP_POOL_MAX_IDLE_PER_HOST")
+            .ok()
+            .map(|max_idle| {
+                max_idle.parse::<usize>().map_err(|e| {
+                    format!(
+                        "invalid KHEISH_HTTP_POOL_MAX_IDLE_PER_HOST '{}': {}",
+                        max_idle, e
+                    )
+                })
+            })
+            .transpose()?,
+    };
+    if let Some(max_idle) = max_idle {
+        builder = builder.pool_max_idle_per_host(max_idle);
+    }
+
+    if let Some(url) = setting(&config.proxy, "KHEISH_HTTP_PROXY") {
+        let no_proxy = setting(&config.no_proxy, "KHEISH_HTTP_NO_PROXY")
+            .and_then(|hosts| NoProxy::from_string(&hosts))
+            .or_else(NoProxy::from_env);
+        let proxy = Proxy::all(&url)
+            .map_err(|e| format!("invalid http.proxy '{}': {}", url, e))?
+            .no_proxy(no_proxy);
+        builder = builder.proxy(proxy);
+    }
+
+    let ca_certs = if config.ca_certs.is_empty() {
+        std::env::var_os("KHEISH_CA_CERTS")
+            .map(|paths| std::env::split_paths(&paths).collect())
+            .unwrap_or_default()
+    } else {
+        config.ca_certs.iter().map(Into::into).collect::<Vec<_>>()
+    };
+    for path in ca_certs {
+        let pem = std::fs::read(&path)
+            .map_err(|e| format!("cannot read http.ca_certs '{}': {}", path.display(), e))?;
+        let certificates = Certificate::from_pem_bundle(&pem)
+            .map_err(|e| format!("invalid http.ca_certs '{}': {}", path.display(), e))?;
+        if certificates.is_empty() {
+            return Err(format!("no certificate in http.ca_certs '{}'", path.display()).into());
+        }
+        for certificate in certificates {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+
+    Ok(builder.build()?)
+}
+
+/// Returns a setting, or the environment variable overriding its default
+///
+/// # Arguments
+/// * `value` - The configured value
+/// * `env` - Environment variable read when the value is not configured
+///
+/// # Returns
+/// * `Option<String>` - The value, None if neither is set
+fn setting(value: &Option<String>, env: &str) -> Option<String> {
+    value
+        .clone()
+        .or_else(|| std::env::var(env).ok().filter(|value| !value.is_empty()))
+}
+
+/// Parses a duration setting, or the environment variable overriding its default
+///
+/// # Arguments
+/// * `value` - The configured duration (e.g. "10s")
+/// * `name` - Name of the setting, used in errors
+/// * `env` - Environment variable read when the value is not configured
+///
+/// # Returns
+/// * `Result<Option<Duration>, String>` - The duration if set, or an error if it is invalid
+fn duration(value: &Option<String>, name: &str, env: &str) -> Result<Option<Duration>, String> {
+    setting(value, env)
+        .map(|duration| {
+            humantime::parse_duration(&duration)
+                .map_err(|e| format!("invalid http.{} '{}': {}", name, duration, e))
+        })
+        .transpose()
+}
@@ -0,0 +1,157 @@
+use crate::config::ModelPrice;
+use crate::event::Event;
+use crate::llm::{ChatMessage, CompactionEntry, FallbackEntry};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tokio::sync::mpsc::UnboundedSender;
+
+/// Number of tokens consumed by an LLM call
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenUsage {
+    /// Tokens of the request (prompt)
+    pub input_tokens: u64,
+    /// Tokens of the response (completion)
+    pub output_tokens: u64,
+}
+
+/// Token usage of the LLM calls of a role with a model, and its cost
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UsageEntry {
+    /// Role that made the calls
+    pub role: String,
+    /// Model that answered the calls
+    pub model: String,
+    /// Number of calls
+    pub calls: u64,
+    /// Number of calls answered from the response cache, included in `calls`
+    #[serde(default)]
+    pub cache_hits: u64,
+    /// Total input tokens
+    pub input_tokens: u64,
+    /// Total output tokens
+    pub output_tokens: u64,
+    /// Cost of the calls, None if the model has no price
+    pub cost: Option<f64>,
+}
+
+/// Reports the token usage of every LLM call of a role to the task manager
+#[derive(Debug, Clone)]
+pub struct UsageReporter {
+    /// ID of the task the calls belong to
+    task_id: String,
+    /// Role making the calls
+    role: String,
+    /// Price of the tokens of each model
+    pricing: BTreeMap<String, ModelPrice>,
+    /// Channel of the task manager
+    manager_tx: UnboundedSender<Event>,
+}
+
+impl UsageReporter {
+    /// Creates a reporter for the calls of one role
+    ///
+    /// # Arguments
+    /// * `task_id` - ID of the task the calls belong to
+    /// * `role` - Role making the calls
+    /// * `pricing` - Price of the tokens of each model (`parameters.pricing`)
+    /// * `manager_tx` - Channel of the task manager
+    pub fn new(
+        task_id: &str,
+        role: &str,
+        pricing: BTreeMap<String, ModelPrice>,
+        manager_tx: UnboundedSender<Event>,
+    ) -> Self {
+        UsageReporter {
+            task_id: task_id.to_string(),
+            role: role.to_string(),
+            pricing,
+            manager_tx,
+        }
+    }
+
+    /// Reports the usage of one call, priced with the table of the task
+    ///
+    /// # Arguments
+    /// * `model` - Model that answered the call
+    /// * `usage` - Tokens consumed by the call
+    pub fn report(&self, model: &str, usage: TokenUsage) {
+        let entry = UsageEntry {
+            role: self.role.clone(),
+            model: model.to_string(),
+            calls: 1,
+            cache_hits: 0,
+            input_tokens: usage.input_tokens,
+            output_tokens: usage.output_tokens,
+            cost: self
+                .pricing
+                .get(model)
+                .map(|price| price.cost(usage.input_tokens, usage.output_tokens)),
+        };
+        let _ = self
+            .manager_tx
+            .send(Event::LlmUsage(self.task_id.clone(), entry));
+    }
+
+    /// Reports a call answered from the response cache, which consumes no tokens
+    ///
+    /// # Arguments
+    /// * `model` - Model whose cached response was served
+    pub fn report_cache_hit(&self, model: &str) {
+        let entry = UsageEntry {
+            role: self.role.clone(),
+            model: model.to_string(),
+            calls: 1,
+            cache_hits: 1,
+            input_tokens: 0,
+            output_tokens: 0,
+            cost: self.pricing.get(model).map(|_| 0.0),
+        };
+        let _ = self
+            .manager_tx
+            .send(Event::LlmUsage(self.task_id.clone(), entry));
+    }
+
+    /// Reports a call that failed over to the next provider/model of the chain
+    ///
+    /// # Arguments
+    /// * `from` - Provider/model that failed
+    /// * `to` - Provider/model the call failed over to
+    /// * `reason` - Error of the failed provider/model
+    pub fn report_fallback(&self, from: &str, to: &str, reason: &str) {
+        let entry = FallbackEntry {
+            role: self.role.clone(),
+            from: from.to_string(),
+            to: to.to_string(),
+            reason: reason.to_string(),
+        };
+        let _ = self
+            .manager_tx
+            .send(Event::LlmFallback(self.task_id.clone(), entry));
+    }
+
+    /// Reports older turns of the conversation replaced by a summary
+    ///
+    /// # Arguments
+    /// * `messages` - Messages replaced by the summary
+    /// * `summary` - Summary written by the LLM
+    /// * `tokens_before` - Tokens of the conversation before the compaction
+    /// * `tokens_after` - Tokens of the conversation after the compaction
+    pub fn report_compaction(
+        &self,
+        messages: Vec<ChatMessage>,
+        summary: String,
+        tokens_before: usize,
+        tokens_after: usize,
+    ) {
+        let entry = CompactionEntry {
+            role: self.role.clone(),
+            messages,
+            summary,
+            tokens_before,
+            tokens_after,
+        };
+        let _ = self
+            .manager_tx
+            .send(Event::ConversationCompacted(self.task_id.clone(), entry));
+    }
+}
@@ -8,7 +8,8 @@
 //! - Without a task configuration but with LLM provider/model
 //! - In task creation mode
 //!
-//! It also supports running an optional API server for external interaction.
+//! It also supports running an optional API server for external interaction,
+//! and a `validate` command that checks a task configuration without running it.
 
 #![feature(trait_upcasting)]
 
@@ -27,7 +28,9 @@ mod schema;
 mod utils;
 
 use clap::Parser;
+use colored::*;
 use core::TaskManager;
+use std::collections::BTreeMap;
 use tracing::{error, info, warn};
 
 /// Main entry point that initializes and runs the application.
@@ -41,6 +44,16 @@ use tracing::{error, info, warn};
 #[tokio::main]
 async fn main() {
     let cli = cli::Cli::try_parse().expect("Failed to parse CLI arguments");
+
+    if let Some(cli::Command::Validate {
+        task_config,
+        inputs,
+    }) = &cli.command
+    {
+        let inputs = inputs.iter().cloned().collect();
+        std::process::exit(validate(task_config, &inputs));
+    }
+
     utils::init_logging(&cli.logging_level, cli.api_enabled);
 
     if let Err(e) = dotenvy::dotenv() {
@@ -56,22 +69,93 @@ async fn main() {
         });
     }
 
-    let (mut task_manager, workers) = match cli.task_config {
+    let started = match cli.task_config {
         None => {
+            configure_transport(&config::HttpConfig::default());
             if cli.new_task {
-                TaskManager::new_without_task_creation(cli.api_enabled).await
+                TaskManager::new_without_task_creation(cli.api_enabled, cli.no_cache).await
             } else {
                 let llm_provider = cli.llm_provider.expect("LLM provider is required");
                 let llm_model = cli.llm_model.expect("LLM model is required");
-                TaskManager::new_without_task(&llm_provider, &llm_model, cli.api_enabled).await
+                Ok(TaskManager::new_without_task(
+                    &llm_provider,
+                    &llm_model,
+                    cli.api_enabled,
+                    cli.no_cache,
+                )
+                .await)
             }
         }
         Some(task_path) => {
             let config =
                 config::load_task_config(&task_path).expect("Failed to parse task configuration");
-            TaskManager::new(&config, cli.api_enabled)
+            let inputs = cli.inputs.into_iter().collect();
+            let config = match config::apply_inputs(config, &inputs) {
+                Ok(config) => config,
+                Err(e) => {
+                    error!("Invalid task inputs: {}", e);
+                    std::process::exit(1);
+                }
+            };
+            configure_transport(&config.parameters.http);
+            TaskManager::new(&config, cli.api_enabled, cli.no_cache)
+        }
+    };
+    let (mut task_manager, workers) = match started {
+        Ok(started) => started,
+        Err(e) => {
+            error!("Failed to start the task: {}", e);
+            std::process::exit(1);
         }
     };
 
     task_manager.run(workers).await;
 }
+
+/// Configures the HTTP transport shared by every task of the process, exiting on invalid settings
+///
+/// # Arguments
+/// * `http` - The `parameters.http` settings of the startup task, or the defaults
+fn configure_transport(http: &config::HttpConfig) {
+    if let Err(e) = llm::configure_transport(http) {
+        error!("Invalid HTTP transport settings: {}", e);
+        std::process::exit(1);
+    }
+}
+
+/// Validates a task configuration file and prints every problem found
+///
+/// # Arguments
+/// * `task_path` - Path to the task configuration file
+/// * `inputs` - Task input values supplied on the command line
+///
+/// # Returns
+/// * `i32` - Process exit code, non-zero if any error was found
+fn validate(task_path: &str, inputs: &BTreeMap<String, String>) -> i32 {
+    let issues = config::validate_task_file(task_path, inputs);
+    let errors = issues
+        .iter()
+        .filter(|issue| issue.severity == config::Severity::Error)
+        .count();
+    let warnings = issues.len() - errors;
+
+    for issue in &issues {
+        let line = format!("{}: {}", task_path, issue);
+        match issue.severity {
+            config::Severity::Error => println!("{}", line.red()),
+            config::Severity::Warning => println!("{}", line.yellow()),
+        }
+    }
+
+    if issues.is_empty() {
+        println!("{} {} is valid", "✓".green(), task_path.bold());
+    } else {
+        println!("{} error(s), {} warning(s)", errors, warnings);
+    }
+
+    if errors > 0 {
+        1
+    } else {
+        0
+    }
+}
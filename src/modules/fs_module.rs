@@ -1,4 +1,5 @@
 use crate::core::rag::VectorStoreProvider;
+use crate::llm::ContentPart;
 use crate::modules::{Module, ModuleAction};
 
 /// Module for interacting with the filesystem
@@ -59,6 +60,13 @@ impl Module for FileSystemModule {
                 }
                 let path = &params[0];
                 let full_path = path.to_string();
+                if ContentPart::attachment(&full_path).is_some() {
+                    std::fs::metadata(&full_path).map_err(|e| e.to_string())?;
+                    return Ok(format!(
+                        "File '{}' attached to the conversation.",
+                        full_path
+                    ));
+                }
                 let content = std::fs::read_to_string(&full_path).map_err(|e| e.to_string())?;
 
                 let chunks = chunk_text(&content, 2000);
@@ -86,6 +94,10 @@ impl Module for FileSystemModule {
 
                 for p in paths {
                     let full_path = p.to_string();
+                    if ContentPart::attachment(&full_path).is_some() {
+                        std::fs::metadata(&full_path).map_err(|e| e.to_string())?;
+                        continue;
+                    }
                     let content = std::fs::read_to_string(&full_path).map_err(|e| e.to_string())?;
                     let chunks = chunk_text(&content, 2000);
                     for (i, c) in chunks.iter().enumerate() {
@@ -97,7 +109,7 @@ impl Module for FileSystemModule {
                     }
                 }
 
-                Ok("All specified files indexed into RAG.".to_string())
+                Ok("All specified files indexed into RAG, images and PDF documents attached to the conversation.".to_string())
             }
 
             "list_directory" => {
@@ -170,7 +182,7 @@ impl Module for FileSystemModule {
             ModuleAction {
                 name: "read".to_string(),
                 arg_count: 1,
-                description: "Read a file and index it into RAG (no direct content return) usage: fs read <path>".to_string(),
+                description: "Read a file and index it into RAG (no direct content return), or attach an image or PDF to the conversation usage: fs read <path>".to_string(),
             },
             ModuleAction {
                 name: "list_directory".to_string(),
@@ -191,4 +203,17 @@ impl Module for FileSystemModule {
             },
         ]
     }
+
+    /// Attaches the images and PDF documents read by `read` and `read_multiple`
+    fn attachments(&self, action: &str, params: &[String]) -> Vec<ContentPart> {
+        let paths: Vec<&str> = match (action, params.first()) {
+            ("read", Some(path)) => vec![path.as_str()],
+            ("read_multiple", Some(paths)) => paths.split(',').collect(),
+            _ => Vec::new(),
+        };
+        paths
+            .into_iter()
+            .filter_map(ContentPart::attachment)
+            .collect()
+    }
 }
@@ -4,6 +4,7 @@
 //! maintaining cookie state across requests to the same domain.
 
 use crate::core::rag::VectorStoreProvider;
+use crate::llm::http_client;
 use crate::modules::{Module, ModuleAction};
 use async_trait::async_trait;
 use reqwest::header::{HeaderName, HeaderValue, SET_COOKIE};
@@ -147,7 +148,7 @@ impl HttpModule {
     ) -> Result<String, String> {
         let domain = Self::extract_domain(url)?;
 
-        let client = reqwest::Client::new();
+        let client = http_client();
         let mut req_builder = match method.to_lowercase().as_str() {
             "get" => client.get(url),
             "post" => {
@@ -7,6 +7,7 @@ mod sh_module;
 mod ssh_module;
 
 use crate::core::rag::VectorStoreProvider;
+use crate::llm::ContentPart;
 pub use fs_module::*;
 pub use http_module::*;
 pub use mem_module::*;
@@ -41,4 +42,8 @@ pub trait Module: std::fmt::Debug + Send + Sync {
         params: &[String],
     ) -> Result<String, String>;
     fn get_actions(&self) -> Vec<ModuleAction>;
+    /// Returns the images and documents a successful action attaches to the conversation
+    fn attachments(&self, _action: &str, _params: &[String]) -> Vec<ContentPart> {
+        Vec::new()
+    }
 }
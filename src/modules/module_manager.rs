@@ -1,8 +1,13 @@
+use crate::llm::ToolDefinition;
 use crate::modules::{
     FileSystemModule, HttpModule, MemoriesModule, Module, ShModule, SshModule, VectorStoreModule,
 };
+use serde_json::json;
 use tracing::debug;
 
+/// Names of the modules that can be declared in a task configuration
+pub const AVAILABLE_MODULES: &[&str] = &["fs", "ssh", "http", "sh", "rag", "memories"];
+
 /// Manages the loading and access of modules in the system
 #[derive(Debug)]
 pub struct ModulesManager {
@@ -81,4 +86,40 @@ impl ModulesManager {
     pub fn get_module(&self, name: &str) -> Option<&dyn Module> {
         self.modules.iter().find(|m| m.name() == name).map(|m| &**m)
     }
+
+    /// Describes every action of the loaded modules as a native tool
+    ///
+    /// Tools are named `<module>_<action>` and take the positional action parameters
+    /// as a `params` array, so values may contain spaces, quotes or newlines.
+    ///
+    /// # Returns
+    /// * `Vec<ToolDefinition>` - One tool per module action
+    pub fn tool_definitions(&self) -> Vec<ToolDefinition> {
+        self.modules
+            .iter()
+            .flat_map(|module| {
+                module
+                    .get_actions()
+                    .into_iter()
+                    .map(move |action| ToolDefinition {
+                        name: format!("{}_{}", module.name(), action.name),
+                        description: action.description,
+                        parameters: json!({
+                            "type": "object",
+                            "properties": {
+                                "params": {
+                                    "type": "array",
+                                    "items": { "type": "string" },
+                                    "description": format!(
+                                        "Parameters of the action, in order ({} expected)",
+                                        action.arg_count
+                                    )
+                                }
+                            },
+                            "required": ["params"]
+                        }),
+                    })
+            })
+            .collect()
+    }
 }
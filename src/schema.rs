@@ -19,6 +19,26 @@ diesel::table! {
         output -> Text,
         created_at -> Text,
         updated_at -> Text,
+        sink -> Nullable<Text>,
+        status -> Nullable<Text>,
+        error -> Nullable<Text>,
+        model -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    task_usage (id) {
+        id -> Nullable<Text>,
+        task_id -> Text,
+        role -> Text,
+        model -> Text,
+        calls -> BigInt,
+        input_tokens -> BigInt,
+        output_tokens -> BigInt,
+        cost -> Nullable<Double>,
+        created_at -> Text,
+        updated_at -> Text,
+        cache_hits -> BigInt,
     }
 }
 
@@ -40,7 +60,9 @@ diesel::table! {
         updated_at -> Text,
         last_run_at -> Nullable<Timestamp>,
         interval -> Nullable<Text>,
+        inputs -> Nullable<Text>,
+        progress -> Nullable<Text>,
     }
 }
 
-diesel::allow_tables_to_appear_in_same_query!(task_events, task_outputs, tasks,);
+diesel::allow_tables_to_appear_in_same_query!(task_events, task_outputs, task_usage, tasks,);
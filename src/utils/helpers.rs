@@ -1,10 +1,7 @@
 use crate::config::AgentsConfig;
 use crate::constants::*;
-use crate::llm::ChatMessage;
 use crate::modules::ModulesManager;
 
-use tracing::debug;
-
 /// Generates system instructions for the AI agents based on configuration and available modules
 ///
 /// # Arguments
@@ -75,6 +72,31 @@ pub fn generate_system_instructions(
     system_instructions
         .push_str("\n\nWhen you play the 'formatter' role, follow these instructions:\n");
     system_instructions.push_str(&formatter_prompt);
+
+    for (role, config) in &agent_config.custom {
+        system_instructions.push_str(&format!(
+            "\n\nWhen you play the '{}' role, follow these instructions:\n",
+            role
+        ));
+        system_instructions.push_str(
+            config
+                .system_prompt
+                .as_deref()
+                .unwrap_or(CUSTOM_ROLE_SYSTEM_PROMPT),
+        );
+        if !config.protocol.is_empty() {
+            let prefixes = config
+                .protocol
+                .iter()
+                .map(|rule| format!("'{}'", rule.prefix))
+                .collect::<Vec<_>>()
+                .join(", ");
+            system_instructions.push_str(&format!(
+                "\nIn this role, start every answer with one of: {}.",
+                prefixes
+            ));
+        }
+    }
     system_instructions.push_str("\n\nYou only have one system message (this one). Roles are activated by the user messages that follow.");
 
     if !modules.modules.is_empty() {
@@ -91,7 +113,10 @@ pub fn generate_system_instructions(
                 ));
             }
         }
-        system_instructions.push_str("\nTo use a module, respond with:\nMODULE_REQUEST: <module_name> <action> <params...>\n");
+        system_instructions.push_str(
+            "\nWhen the '<module_name>_<action>' tools are available, call them to use a module.\n",
+        );
+        system_instructions.push_str("Otherwise, to use a module, respond with:\nMODULE_REQUEST: <module_name> <action> <params...>\n");
         system_instructions
             .push_str("Use only the listed actions and the correct number of arguments.\n");
         system_instructions.push_str("Only one module request per response, if needed.\n");
@@ -99,61 +124,3 @@ pub fn generate_system_instructions(
 
     system_instructions
 }
-
-/// Manages token count in a vector of messages by removing old messages or truncating
-/// if the total token count exceeds the limit
-///
-/// # Arguments
-/// * `messages` - Vector of ChatMessages to manage
-/// * `token_limit` - Maximum number of tokens allowed
-///
-/// # Returns
-/// * `bool` - True if messages were modified, False otherwise
-pub fn manage_token_count(messages: &mut Vec<ChatMessage>, token_limit: usize) -> bool {
-    let mut total_tokens: usize = messages.iter().map(|msg| msg.content.chars().count()).sum();
-
-    if total_tokens >= token_limit {
-        if messages.len() > 1 {
-            let mut i = 0;
-            while total_tokens >= token_limit && i < messages.len() {
-                if messages[i].role == "assistant" && messages[i].content.contains("proposal") {
-                    total_tokens -= messages[i].content.chars().count();
-                    messages.remove(i);
-                    debug!(
-                        "Removed proposal message to reduce token count. Remaining messages: {}",
-                        messages.len()
-                    );
-                } else {
-                    i += 1;
-                }
-            }
-        }
-
-        if total_tokens >= token_limit && messages.len() > 1 {
-            while total_tokens >= token_limit && messages.len() > 1 {
-                if let Some(removed_msg) = messages.first() {
-                    total_tokens -= removed_msg.content.chars().count();
-                }
-                messages.remove(0);
-                debug!(
-                    "Removed old message to reduce token count. Remaining messages: {}",
-                    messages.len()
-                );
-            }
-        }
-
-        if total_tokens >= token_limit {
-            if let Some(last_msg) = messages.last_mut() {
-                last_msg.content = last_msg
-                    .content
-                    .chars()
-                    .take(token_limit)
-                    .collect::<String>();
-                debug!("Truncated last message to fit token limit");
-            }
-        }
-        std::thread::sleep(std::time::Duration::from_secs(60));
-        return true;
-    }
-    false
-}
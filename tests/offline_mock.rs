@@ -0,0 +1,168 @@
+//! Runs the `offline-mock` example end to end: recorded from a stub OpenAI server through
+//! the `mock` provider, then replayed from the cassette without any server.
+
+use axum::http::HeaderMap;
+use axum::response::{IntoResponse, Response};
+use axum::{Json, Router};
+use diesel::connection::SimpleConnection;
+use diesel::{Connection, SqliteConnection};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// Longest time a task may take before the test fails
+const TASK_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Answers a chat completions request as the role named in its prompt
+async fn chat_completions(headers: HeaderMap, Json(request): Json<Value>) -> Response {
+    assert_eq!(
+        headers.get("authorization").and_then(|v| v.to_str().ok()),
+        Some("Bearer offline-mock-key")
+    );
+    let prompt = request["messages"].to_string();
+    let role = prompt
+        .rsplit("Current role: ")
+        .next()
+        .and_then(|rest| rest.split("\\n").next())
+        .unwrap_or_default();
+    let content = match role {
+        "proposer" => {
+            "Proposal: Emily Dickinson's poem compares hope to a bird living in the soul."
+        }
+        "reviewer" => "Approved",
+        "validator" => "Validated",
+        _ => "## Summary\n\nEmily Dickinson's poem compares hope to a bird living in the soul.",
+    };
+
+    if request["stream"] == true {
+        let chunk = json!({ "choices": [{ "delta": { "content": content } }] });
+        let body = format!("data: {}\n\ndata: [DONE]\n\n", chunk);
+        return ([("content-type", "text/event-stream")], body).into_response();
+    }
+    Json(json!({
+        "choices": [{ "message": { "role": "assistant", "content": content } }],
+        "usage": { "prompt_tokens": 10, "completion_tokens": 5 }
+    }))
+    .into_response()
+}
+
+/// Creates an empty working directory with a migrated database
+fn workspace(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("kheish-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let mut migrations: Vec<PathBuf> = std::fs::read_dir("migrations")
+        .unwrap()
+        .map(|entry| entry.unwrap().path().join("up.sql"))
+        .collect();
+    migrations.sort();
+    let database = dir.join("kheish.db");
+    let mut connection = SqliteConnection::establish(database.to_str().unwrap()).unwrap();
+    for migration in migrations {
+        connection
+            .batch_execute(&std::fs::read_to_string(migration).unwrap())
+            .unwrap();
+    }
+    dir
+}
+
+/// Runs the example with the given parameters until its output is written
+///
+/// The task manager keeps running once the task is done, so it is stopped as soon as
+/// the output file appears.
+async fn run_example(dir: &Path, name: &str, parameters: Value) -> String {
+    let example = std::fs::canonicalize("examples/tasks/offline-mock/task.yaml").unwrap();
+    let output = dir.join(format!("{}.md", name));
+    let task = json!({
+        "extends": example,
+        "parameters": parameters,
+        "output": { "file": output }
+    });
+    let task_path = dir.join(format!("{}.yaml", name));
+    std::fs::write(&task_path, serde_yaml::to_string(&task).unwrap()).unwrap();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_kheish"))
+        .arg("--task-config")
+        .arg(&task_path)
+        .current_dir(dir)
+        .env("DATABASE_PATH", dir.join("kheish.db"))
+        .env("OFFLINE_MOCK_API_KEY", "offline-mock-key")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let started = Instant::now();
+    let result = loop {
+        if let Ok(text) = std::fs::read_to_string(&output) {
+            if !text.is_empty() {
+                break Ok(text);
+            }
+        }
+        if let Some(status) = child.try_wait().unwrap() {
+            break Err(format!(
+                "kheish exited with {} before writing its output",
+                status
+            ));
+        }
+        if started.elapsed() > TASK_TIMEOUT {
+            break Err("kheish did not write its output in time".to_string());
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    };
+    let _ = child.kill();
+    let _ = child.wait();
+    result.unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn offline_mock_replays_recorded_run() {
+    let app = Router::new().fallback(chat_completions);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/v1", listener.local_addr().unwrap());
+    let server = tokio::spawn(async move { axum::serve(listener, app).await });
+
+    let dir = workspace("offline-mock");
+    let cassette = dir.join("cassette.jsonl");
+    let recorded = run_example(
+        &dir,
+        "record",
+        json!({
+            "llm_model": cassette,
+            "mock": { "mode": "record", "provider": "openai", "model": "gpt-4o" },
+            "llm_endpoint": { "base_url": url, "api_key_env": "OFFLINE_MOCK_API_KEY" }
+        }),
+    )
+    .await;
+    server.abort();
+
+    let roles: Vec<String> = std::fs::read_to_string(&cassette)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str::<Value>(line).unwrap()["role"].to_string())
+        .collect();
+    for role in ["proposer", "reviewer", "validator", "formatter"] {
+        assert!(
+            roles.contains(&format!("\"{}\"", role)),
+            "no {} interaction recorded: {:?}",
+            role,
+            roles
+        );
+    }
+
+    let replayed = run_example(
+        &dir,
+        "replay",
+        json!({
+            "llm_model": cassette,
+            "mock": { "mode": "replay" }
+        }),
+    )
+    .await;
+
+    assert!(recorded.contains("Emily Dickinson"), "{}", recorded);
+    assert_eq!(replayed, recorded);
+    let _ = std::fs::remove_dir_all(&dir);
+}